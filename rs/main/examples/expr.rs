@@ -9,5 +9,5 @@ fn main() {
     engine
         .update_with_code(r#"o: eval `x:=x>1*(x-1.0)+x*x<=1;x`"#)
         .unwrap(); // y=math::sin(2*PI*x);x+=440.0/sr;y
-    println!("next block {:?}", engine.next_block(vec![]));
+    println!("next block {:?}", engine.next_block(&[]));
 }
@@ -11,6 +11,6 @@ fn main() {
         .unwrap();
     println!(
         "next block {:?}",
-        engine.next_block(vec![&[0.1; 8], &[0.2; 8]])
+        engine.next_block(&[&[0.1; 8], &[0.2; 8]])
     );
 }
@@ -7,5 +7,5 @@ use glicol::Engine;
 fn main() {
     let mut engine = Engine::<8>::new();
     engine.update_with_code(r#"o: pattern_synth `` 1"#).unwrap();
-    println!("next block {:?}", engine.next_block(vec![]));
+    println!("next block {:?}", engine.next_block(&[]));
 }
@@ -6,5 +6,5 @@ fn main() {
     engine
         .update_with_code(r#"o: saw 400 >> lpf "100@0.0 200@0.5"(1) 1.0"#)
         .unwrap();
-    println!("next block {:?}", engine.next_block(vec![]));
+    println!("next block {:?}", engine.next_block(&[]));
 }
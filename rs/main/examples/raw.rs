@@ -7,7 +7,7 @@ fn main() {
     // engine.next_block();
     // engine.set_code("a: constsig 42 >> mul 0.1");
     // engine.update();
-    engine.next_block(vec![]);
+    engine.next_block(&[]);
     // println!("index_info {:?}", engine.index_info);
     // engine.send_msg("o", 0, (0, "440."));
     // engine.next_block();
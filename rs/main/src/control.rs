@@ -0,0 +1,208 @@
+//! Changing node parameters without changing the code.
+
+use crate::{Engine, MsgError};
+use glicol_parser::nodes::UsizeOrRef;
+use glicol_synth::{GlicolPara, Message, Node as _, ParamInfo, SampleEvent, Smoothing};
+use hashbrown::HashMap;
+use petgraph::graph::NodeIndex;
+
+/// A node parameter, either by its position in the node's arguments or by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param<'a> {
+    Index(u8),
+    Name(&'a str),
+}
+
+impl From<u8> for Param<'_> {
+    fn from(index: u8) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl<'a> From<&'a str> for Param<'a> {
+    fn from(name: &'a str) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl std::fmt::Display for Param<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl<const N: usize> Engine<N> {
+    /// Send `value` to a parameter of the `pos`th node in `chain`, the same as changing that
+    /// argument in the code would, but without rebuilding the graph.
+    ///
+    /// ```
+    /// # use glicol::{Engine, control::Param};
+    /// # use glicol_synth::GlicolPara;
+    /// let mut engine = Engine::<32>::new();
+    /// engine.update_with_code("o: saw 440 >> lpf 1000 1.0").unwrap();
+    /// engine.set_param("o", 1, "cutoff".into(), GlicolPara::Number(800.)).unwrap();
+    /// engine.set_param("o", 1, Param::Index(1), GlicolPara::Number(0.5)).unwrap();
+    /// assert!(engine.set_param("o", 2, 0.into(), GlicolPara::Number(1.)).is_err());
+    /// ```
+    pub fn set_param(
+        &mut self,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        value: GlicolPara<String>,
+    ) -> Result<(), MsgError> {
+        let (index, param) = self.resolve_param(chain, pos, param)?;
+        let msg = self.param_msg(param, value)?;
+        self.send_to_node(index, msg);
+        Ok(())
+    }
+
+    /// Change how one parameter follows [`Self::set_param`], overriding
+    /// [`Self::set_smoothing`] for it until the node is replaced.
+    pub fn set_param_smoothing(
+        &mut self,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        smoothing: Smoothing,
+    ) -> Result<(), MsgError> {
+        let (index, param) = self.resolve_param(chain, pos, param)?;
+        self.send_to_node(index, Message::SetSmoothing(Some(param), smoothing));
+        Ok(())
+    }
+
+    // the node and the parameter index that `param` of the `pos`th node in `chain` refers to
+    pub(crate) fn resolve_param(
+        &self,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+    ) -> Result<(NodeIndex, u8), MsgError> {
+        let index = self.node_index(chain, pos)?;
+        let node = &self.context.graph[index].node;
+        let no_param = || MsgError::NonExistParam(chain.to_owned(), pos, param.to_string());
+        let param = match param {
+            // nodes that don't describe their parameters get whatever index they are sent
+            Param::Index(i) if node.params().is_empty() => i,
+            Param::Index(i) => node
+                .params()
+                .iter()
+                .any(|p| p.index == i)
+                .then_some(i)
+                .ok_or_else(no_param)?,
+            Param::Name(name) => node.param_index(name).ok_or_else(no_param)?,
+        };
+        Ok((index, param))
+    }
+
+    /// The parameters of the `pos`th node in `chain`, for listing them or checking a value
+    /// against its range. Empty if the node doesn't describe them.
+    pub fn node_params(&self, chain: &str, pos: usize) -> Result<&'static [ParamInfo], MsgError> {
+        let index = self.node_index(chain, pos)?;
+        Ok(self.context.graph[index].node.params())
+    }
+
+    pub(crate) fn node_index(&self, chain: &str, pos: usize) -> Result<NodeIndex, MsgError> {
+        self.index_info
+            .get(chain)
+            .ok_or_else(|| MsgError::NonExistChain(chain.to_owned()))?
+            .get(pos)
+            .copied()
+            .ok_or_else(|| MsgError::NonExistNode(chain.to_owned(), pos))
+    }
+
+    pub(crate) fn param_msg(&self, i: u8, value: GlicolPara<String>) -> Result<Message, MsgError> {
+        Ok(match value {
+            GlicolPara::Number(v) => Message::SetToNumber(i, v),
+            GlicolPara::Bool(b) => Message::SetToBool(i, b),
+            GlicolPara::Symbol(s) => Message::SetToSymbol(i, s),
+            GlicolPara::NumberList(l) => Message::SetToNumberList(i, l),
+            GlicolPara::SampleSymbol(s) => match self.samples_dict.get(&s) {
+                Some(sample) => Message::SetToSamples(i, sample.clone()),
+                None => return Err(MsgError::NonExistSample(s)),
+            },
+            GlicolPara::Reference(s) => {
+                return Err(MsgError::InvalidValue(format!(
+                    "{s} is a reference, which can only be changed in the code"
+                )))
+            }
+            GlicolPara::Sequence(events) => {
+                if let Some((_, UsizeOrRef::Ref(s))) =
+                    events.iter().find(|(_, e)| matches!(e, UsizeOrRef::Ref(_)))
+                {
+                    return Err(MsgError::InvalidValue(format!(
+                        "{s} is a reference, which can only be changed in the code"
+                    )));
+                }
+                Message::SetToSeq(i, events)
+            }
+            GlicolPara::Pattern(value_times, span) => {
+                let mut samples = HashMap::new();
+                let mut symbol_pattern = vec![];
+                let mut number_pattern = vec![];
+                for (value, time) in value_times {
+                    match value {
+                        GlicolPara::Number(num) => number_pattern.push((num, time)),
+                        GlicolPara::Symbol(s) => {
+                            let Some(sample) = self.samples_dict.get(&s) else {
+                                return Err(MsgError::NonExistSample(s));
+                            };
+                            samples.insert(s.clone(), sample.clone());
+                            symbol_pattern.push(SampleEvent::new(s, time));
+                        }
+                        v => {
+                            return Err(MsgError::InvalidValue(format!(
+                                "{v:?} can't be used in a pattern"
+                            )))
+                        }
+                    }
+                }
+                if symbol_pattern.is_empty() {
+                    Message::SetPattern(number_pattern, span)
+                } else {
+                    Message::SetSamplePattern(symbol_pattern, span, samples)
+                }
+            }
+            value => Message::SetParam(i, value),
+        })
+    }
+
+    /// The string form of [`Self::set_param`]: `"chain,pos,param,value"`, with several commands
+    /// separated by `;`, e.g. `"o,0,0,440; o,1,cutoff,800"`.
+    ///
+    /// `param` is an index if it is a number and a name otherwise, and `value` is sent as a number
+    /// if it parses as one and as a symbol otherwise. Every command is tried, and the first error
+    /// is returned.
+    pub fn send_msg(&mut self, msg: &str) -> Result<(), MsgError> {
+        let commands: String = msg.chars().filter(|c| !c.is_whitespace()).collect::<_>();
+        let mut result = Ok(());
+        for command in commands.split(';').filter(|c| !c.is_empty()) {
+            let res = self.send_command(command);
+            if result.is_ok() {
+                result = res;
+            }
+        }
+        result
+    }
+
+    fn send_command(&mut self, command: &str) -> Result<(), MsgError> {
+        let malformed = || MsgError::Malformed(command.to_owned());
+        let [chain, pos, param, value] = command.split(',').collect::<Vec<_>>()[..] else {
+            return Err(malformed());
+        };
+        let pos = pos.parse::<usize>().map_err(|_| malformed())?;
+        let param = match param.parse::<u8>() {
+            Ok(index) => Param::Index(index),
+            Err(_) if !param.is_empty() => Param::Name(param),
+            Err(_) => return Err(malformed()),
+        };
+        let value = match value.parse::<f32>() {
+            Ok(v) => GlicolPara::Number(v),
+            Err(_) => GlicolPara::Symbol(value.to_owned()),
+        };
+        self.set_param(chain, pos, param, value)
+    }
+}
@@ -0,0 +1,190 @@
+//! Click-free code updates.
+//!
+//! With a crossfade time set, the nodes an update removes are not dropped straight away. Each run
+//! of removed nodes in a chain keeps rendering next to the run of nodes that replaces it, fed from
+//! the same input, and a `Crossfade` node mixes from the old run to the new one. Chains that are
+//! removed from the code fade out, and new output chains fade in. Once the fade time is over, the
+//! old nodes and the crossfades are removed and the chains are connected directly again.
+
+use crate::Engine;
+use glicol_synth::{Crossfade, Node as _};
+use hashbrown::HashMap;
+use petgraph::graph::NodeIndex;
+
+/// A run of nodes in `chain` that is being replaced.
+pub(crate) struct Segment {
+    pub chain: String,
+    /// `start..end` are the positions of the replacing nodes in the new chain; it can be empty
+    /// when nodes were only removed.
+    pub start: usize,
+    pub end: usize,
+    /// The nodes being replaced, in chain order; empty when nodes were only inserted.
+    pub old: Vec<NodeIndex>,
+    pub xfade: NodeIndex,
+}
+
+/// A chain that is gone from the code but keeps playing until the fade is over.
+pub(crate) struct Ghost {
+    pub chain: String,
+    pub nodes: Vec<NodeIndex>,
+    /// Only output chains fade out; a `~` chain is kept just so that old nodes referencing it
+    /// still have an input.
+    pub xfade: Option<NodeIndex>,
+}
+
+#[derive(Default)]
+pub(crate) struct Fade {
+    pub segments: Vec<Segment>,
+    pub ghosts: Vec<Ghost>,
+    /// References of the old nodes, to wire them up the same way they were before the update.
+    pub old_refs: Vec<(NodeIndex, Vec<String>)>,
+    /// The clock at which the fade is over.
+    pub until: usize,
+}
+
+impl Fade {
+    fn nodes(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        let segments = self
+            .segments
+            .iter()
+            .flat_map(|seg| seg.old.iter().copied().chain([seg.xfade]));
+        let ghosts = self
+            .ghosts
+            .iter()
+            .flat_map(|ghost| ghost.nodes.iter().copied().chain(ghost.xfade));
+        segments.chain(ghosts)
+    }
+
+    pub fn is_old_node(&self, index: NodeIndex) -> bool {
+        self.nodes().any(|i| i == index)
+    }
+}
+
+/// Split a chain into the runs that changed, given which old positions were kept at which new
+/// positions. Returns `(old range, new range)` pairs, or `None` if the kept nodes moved around, in
+/// which case the chain can't be faded piece by piece.
+fn changed_runs(
+    old_len: usize,
+    new_len: usize,
+    kept: &mut [(usize, usize)],
+) -> Option<Vec<(std::ops::Range<usize>, std::ops::Range<usize>)>> {
+    kept.sort_unstable();
+    if kept.windows(2).any(|w| w[0].1 >= w[1].1) {
+        return None;
+    }
+    let mut runs = vec![];
+    let (mut old_start, mut new_start) = (0, 0);
+    for &(old_end, new_end) in kept.iter().chain([&(old_len, new_len)]) {
+        if old_start < old_end || new_start < new_end {
+            runs.push((old_start..old_end, new_start..new_end));
+        }
+        (old_start, new_start) = (old_end + 1, new_end + 1);
+    }
+    Some(runs)
+}
+
+impl<const N: usize> Engine<N> {
+    /// Crossfade between the old and the new graph for `ms` milliseconds whenever the code is
+    /// updated. `0.0` (the default) swaps them straight away.
+    pub fn set_crossfade(&mut self, ms: f32) {
+        self.crossfade = ms.max(0.0);
+    }
+
+    pub(crate) fn crossfade_len(&self) -> usize {
+        (self.crossfade / 1000. * self.sr as f32) as usize
+    }
+
+    /// Work out what to fade for an update, before `index_info` is changed.
+    ///
+    /// `new_chains` are the lengths of the chains in the new code, `kept` the nodes that stay as
+    /// `(chain, old position, new position)`, and `removed_chains` the chains that are gone.
+    pub(crate) fn plan_fade(
+        &mut self,
+        new_chains: &HashMap<&str, usize>,
+        kept: &[(&str, usize, usize)],
+        removed_chains: &[String],
+    ) -> Fade {
+        let mut fade = Fade {
+            until: self.clock + self.crossfade_len(),
+            ..Fade::default()
+        };
+        let mut old_nodes = vec![];
+
+        for (&chain, &new_len) in new_chains {
+            let is_output = !chain.contains('~');
+            let Some(old_chain) = self.index_info.get(chain).cloned() else {
+                // a new output chain fades in from silence
+                if is_output {
+                    fade.segments.push(Segment {
+                        chain: chain.to_owned(),
+                        start: 0,
+                        end: new_len,
+                        old: vec![],
+                        xfade: self.add_crossfade(),
+                    });
+                }
+                continue;
+            };
+            let mut chain_kept: Vec<_> = kept
+                .iter()
+                .filter(|(name, ..)| *name == chain)
+                .map(|(_, old, new)| (*old, *new))
+                .collect();
+            let Some(runs) = changed_runs(old_chain.len(), new_len, &mut chain_kept) else {
+                continue;
+            };
+            // a `~` chain is often a control signal, so it shouldn't dip towards zero
+            if !is_output
+                && runs
+                    .iter()
+                    .any(|(o, n)| n.start == 0 && (o.is_empty() || n.is_empty()))
+            {
+                continue;
+            }
+            for (old, new) in runs {
+                old_nodes.extend(old.clone().map(|pos| (chain.to_owned(), pos)));
+                fade.segments.push(Segment {
+                    chain: chain.to_owned(),
+                    start: new.start,
+                    end: new.end,
+                    old: old_chain[old].to_vec(),
+                    xfade: self.add_crossfade(),
+                });
+            }
+        }
+
+        for chain in removed_chains {
+            let is_output = !chain.contains('~');
+            let nodes = self.index_info[chain].clone();
+            old_nodes.extend((0..nodes.len()).map(|pos| (chain.clone(), pos)));
+            fade.ghosts.push(Ghost {
+                chain: chain.clone(),
+                nodes,
+                xfade: is_output.then(|| self.add_crossfade()),
+            });
+        }
+
+        fade.old_refs = self
+            .refpairs
+            .iter()
+            .filter(|(_, chain, pos)| old_nodes.contains(&(chain.clone(), *pos)))
+            .map(|(refs, chain, pos)| (self.index_info[chain][*pos], refs.clone()))
+            .collect();
+        fade
+    }
+
+    fn add_crossfade(&mut self) -> NodeIndex {
+        let channels = self.context.channels();
+        let node = Crossfade::new(self.crossfade_len()).to_boxed_nodedata(channels);
+        self.add_node(node)
+    }
+
+    /// Drop the old nodes of a running fade. The caller has to connect the graph again.
+    pub(crate) fn finish_fade(&mut self) {
+        if let Some(fade) = self.fade.take() {
+            for index in fade.nodes() {
+                self.remove_node(index);
+            }
+        }
+    }
+}
@@ -1,12 +1,96 @@
 use glicol_parser::Rule;
 use pest::error::Error;
 pub use pest::error::ErrorVariant;
+use pest::error::{InputLocation, LineColLocation};
+
+/// Where in the code an error was found.
+///
+/// `start..end` is a byte range into the code; `line` and `col` are 1-based and point at `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl From<pest::Span<'_>> for SourceSpan {
+    fn from(span: pest::Span<'_>) -> Self {
+        let (line, col) = span.start_pos().line_col();
+        Self {
+            start: span.start(),
+            end: span.end(),
+            line,
+            col,
+        }
+    }
+}
+
+impl SourceSpan {
+    /// The span of the first whole `token` inside `span`, e.g. the `~mod` in `mul ~mod`, but not
+    /// the start of `~modulator`. Falls back to the whole of `span` if `token` is empty or can't
+    /// be found.
+    pub fn of_token(span: pest::Span<'_>, token: &str) -> Self {
+        let code = span.as_str();
+        // whether `c` would make the match part of a longer token
+        let continues =
+            |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || "_.".contains(c));
+        code.match_indices(token)
+            .map(|(offset, _)| offset)
+            .filter(|_| !token.is_empty())
+            .find(|&offset| {
+                !continues(code[..offset].chars().next_back())
+                    && !continues(code[offset + token.len()..].chars().next())
+            })
+            .and_then(|offset| span.get(offset..offset + token.len()))
+            .unwrap_or(span)
+            .into()
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum EngineError {
     ParsingError(Box<pest::error::Error<glicol_parser::Rule>>),
-    NonExistReference(String),
-    NonExistSample(String),
+    NonExistReference(String, SourceSpan),
+    NonExistSample(String, SourceSpan),
+    /// The node parses, but the engine can't build it (e.g. it needs a disabled feature).
+    UnsupportedNode(String, SourceSpan),
+    /// A parameter that the grammar accepts but the node can't use.
+    InvalidParameter(String, SourceSpan),
+    /// A reference that leads back to the chain it is in, other than through `fb`.
+    CyclicReference(String, SourceSpan),
+    /// A connection between channel counts that can't be mixed, see `glicol_synth::can_mix`.
+    /// The string describes the two counts.
+    ChannelMismatch(String, SourceSpan),
+}
+
+impl EngineError {
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            Self::ParsingError(err) => {
+                let (start, end) = match err.location {
+                    InputLocation::Pos(p) => (p, p),
+                    InputLocation::Span(s) => s,
+                };
+                let (line, col) = match err.line_col {
+                    LineColLocation::Pos(p) => p,
+                    LineColLocation::Span(p, _) => p,
+                };
+                SourceSpan {
+                    start,
+                    end,
+                    line,
+                    col,
+                }
+            }
+            Self::NonExistReference(_, span)
+            | Self::NonExistSample(_, span)
+            | Self::UnsupportedNode(_, span)
+            | Self::InvalidParameter(_, span)
+            | Self::CyclicReference(_, span)
+            | Self::ChannelMismatch(_, span) => *span,
+        }
+    }
 }
 
 impl From<Box<Error<Rule>>> for EngineError {
@@ -27,12 +111,72 @@ pub fn get_error_info(e: Error<Rule>) -> (Vec<Rule>, Vec<Rule>) {
 
 impl std::fmt::Display for EngineError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let SourceSpan { line, col, .. } = self.span();
         match self {
             Self::ParsingError(err) => writeln!(f, "Parsing error: {err}"),
-            EngineError::NonExistSample(v) => writeln!(f, "There is no sample named {v}s"),
-            EngineError::NonExistReference(v) => writeln!(f, "There is no reference named {v}"),
+            EngineError::NonExistSample(v, _) => {
+                writeln!(f, "There is no sample named {v} (line {line}, col {col})")
+            }
+            EngineError::NonExistReference(v, _) => {
+                writeln!(
+                    f,
+                    "There is no reference named {v} (line {line}, col {col})"
+                )
+            }
+            EngineError::UnsupportedNode(v, _) => {
+                writeln!(f, "Unsupported node: {v} (line {line}, col {col})")
+            }
+            EngineError::InvalidParameter(v, _) => {
+                writeln!(f, "Invalid parameter: {v} (line {line}, col {col})")
+            }
+            EngineError::CyclicReference(v, _) => {
+                writeln!(
+                    f,
+                    "The reference {v} leads back to itself, use fb {v} to read it a block late (line {line}, col {col})"
+                )
+            }
+            EngineError::ChannelMismatch(v, _) => {
+                writeln!(f, "Can't mix {v} (line {line}, col {col})")
+            }
         }
     }
 }
 
 impl std::error::Error for EngineError {}
+
+/// Why a control message (see `Engine::set_param`) couldn't be delivered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MsgError {
+    /// A command in the string form that doesn't have the `chain,pos,param,value` shape.
+    Malformed(String),
+    NonExistChain(String),
+    /// There is no node at this position in the chain.
+    NonExistNode(String, usize),
+    /// The node at `(chain, pos)` has no such parameter.
+    NonExistParam(String, usize, String),
+    NonExistSample(String),
+    /// The value can't be sent at runtime, e.g. a reference, which has to change the graph.
+    InvalidValue(String),
+}
+
+impl std::fmt::Display for MsgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(cmd) => {
+                write!(
+                    f,
+                    "Malformed message {cmd:?}, expected chain,pos,param,value"
+                )
+            }
+            Self::NonExistChain(chain) => write!(f, "There is no chain named {chain}"),
+            Self::NonExistNode(chain, pos) => write!(f, "There is no node {pos} in {chain}"),
+            Self::NonExistParam(chain, pos, param) => {
+                write!(f, "Node {pos} in {chain} has no parameter {param}")
+            }
+            Self::NonExistSample(v) => write!(f, "There is no sample named {v}"),
+            Self::InvalidValue(v) => write!(f, "Invalid value: {v}"),
+        }
+    }
+}
+
+impl std::error::Error for MsgError {}
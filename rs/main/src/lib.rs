@@ -1,26 +1,53 @@
-// todo: When error, the error info still updates some nodes..
-
 pub mod util;
-use std::collections::VecDeque;
-
-use util::makenode;
+use std::{collections::VecDeque, sync::Arc};
+
+pub mod control;
+mod crossfade;
+pub mod quantize;
+pub use quantize::Quantize;
+pub mod realtime;
+pub use realtime::{AudioProcessor, Controller};
+pub mod schedule;
+pub use schedule::Time;
+
+use util::{makenode, NodeContext};
 pub mod error;
-pub use error::{get_error_info, EngineError};
+#[cfg(feature = "load-samples")]
+pub mod load;
+#[cfg(feature = "load-samples")]
+pub use load::LoadError;
+pub mod profile;
+pub use profile::Profile;
+pub mod render;
+pub use control::Param;
+pub use error::{get_error_info, EngineError, MsgError, SourceSpan};
 use glicol_parser::{
-    get_ast,
-    nodes::{Ast, Component, UsizeOrRef},
+    get_spanned_ast,
+    nodes::{Component, SpannedAst, UsizeOrRef},
     ToInnerOwned as _,
 };
 use glicol_synth::{
-    AudioContext, AudioContextConfig, BoxedNodeSend, Buffer, GlicolGraph, GlicolPara, Message,
-    NodeData, Pass,
+    can_mix, mix_into, AudioContext, AudioContextConfig, BoxedNodeSend, Buffer, GlicolPara,
+    Message, NodeData, Pass, Sample, SampleEvent, Smoothing,
 };
 use hashbrown::HashMap;
 use petgraph::graph::NodeIndex;
+pub use render::{BitDepth, RenderLength, RenderedAudio, RenderedStems, WavSpec};
 use yoke::Yoke;
 
 pub type GlicolNodeData<const N: usize> = NodeData<BoxedNodeSend<N>, N>;
-type YokedAst = Yoke<Ast<'static>, Box<str>>;
+type YokedAst = Yoke<SpannedAst<'static>, Box<str>>;
+type ChainSpans<'ast> = HashMap<&'ast str, Vec<pest::Span<'ast>>>;
+
+// where to point an error about `token` in the `pos`th node of `chain`
+fn locate(spans: &ChainSpans<'_>, chain: &str, pos: usize, token: &str) -> SourceSpan {
+    spans
+        .get(chain)
+        .and_then(|chain| chain.get(pos))
+        .map_or_else(SourceSpan::default, |span| {
+            SourceSpan::of_token(*span, token)
+        })
+}
 
 #[derive(Default)]
 struct GraphDiff<'engine, const N: usize> {
@@ -28,6 +55,8 @@ struct GraphDiff<'engine, const N: usize> {
     node_update_list: Vec<(&'engine str, usize, Vec<GlicolPara<&'engine str>>)>,
     refpairlist: Vec<(Vec<String>, &'engine str, usize)>,
     node_add_list: VecDeque<(&'engine str, usize, GlicolNodeData<N>)>,
+    // (chain, old position, new position) of the nodes that stay
+    node_keep_list: Vec<(&'engine str, usize, usize)>,
     idx_to_remove: Vec<NodeIndex>,
 }
 
@@ -35,16 +64,30 @@ pub struct Engine<const N: usize> {
     pub context: AudioContext<N>,
     ast: Option<YokedAst>,
     pub index_info: HashMap<String, Vec<NodeIndex>>,
-    pub index_info_backup: HashMap<String, Vec<NodeIndex>>,
-    temp_node_index: Vec<NodeIndex>, // created in the adding process, will be deleted if err
-    pub samples_dict: HashMap<String, (&'static [f32], usize, usize)>,
+    pub samples_dict: HashMap<String, Sample>,
+    // removed or replaced samples that nodes may still be playing
+    retired_samples: Vec<Arc<[f32]>>,
+    resample_samples: bool,
     bpm: f32,
     sr: usize,
     track_amp: f32,
     seed: usize,
     clock: usize,
     pub livecoding: bool,
-    need_update: bool,
+    quantize: Quantize,
+    pending: Option<quantize::PendingUpdate>,
+    update_error: Option<EngineError>,
+    // the references of every node in the current graph, as (refs, chain, position)
+    refpairs: Vec<(Vec<String>, String, usize)>,
+    crossfade: f32,
+    fade: Option<crossfade::Fade>,
+    // in the order they are due
+    scheduled: Vec<schedule::Scheduled>,
+    // given to every node that is added
+    smoothing: Smoothing,
+    // once the engine is split, the graph above only holds placeholders, and the changes to it are
+    // collected here for the `AudioProcessor`
+    edits: Option<Vec<realtime::Edit<N>>>,
 }
 
 impl<const N: usize> Default for Engine<N> {
@@ -55,51 +98,41 @@ impl<const N: usize> Default for Engine<N> {
 
 impl<const N: usize> Engine<N> {
     pub fn new() -> Self {
-        let mut context = AudioContext::<N>::new(AudioContextConfig::default());
+        Self::with_config(AudioContextConfig::default())
+    }
+
+    /// An engine with the sample rate and channel count of `config`. Both `~input` and the output
+    /// have `config.channels` channels. Chains keep the channel count of their nodes, so a mono
+    /// or stereo chain is upmixed where it meets a wider one, see [`glicol_synth::mix_into`].
+    pub fn with_config(config: AudioContextConfig) -> Self {
+        let sr = config.sr;
+        let channels = config.channels;
+        let mut context = AudioContext::<N>::new(config);
         let mut index_info = HashMap::new();
-        index_info.insert("~input".to_string(), vec![context.add_stereo_node(Pass {})]);
+        let input = context.add_multi_chan_node(channels, Pass {});
+        index_info.insert("~input".to_string(), vec![input]);
         Self {
             context,
             ast: None,
-            index_info: index_info.clone(),
-            index_info_backup: index_info.clone(),
-            temp_node_index: vec![],
+            index_info,
             samples_dict: HashMap::new(),
+            retired_samples: vec![],
+            resample_samples: false,
             bpm: 120.,
-            sr: 44100,
+            sr,
             track_amp: 1.0,
             seed: 42,
             clock: 0,
             livecoding: true,
-            need_update: false,
-        }
-    }
-
-    pub fn send_msg(&mut self, msg: &str) {
-        let commands: String = msg.chars().filter(|c| !c.is_whitespace()).collect::<_>();
-        for command in commands.split(';').filter(|c| !c.is_empty()) {
-            let mut list = command.split(',');
-            let (Some(chain_name), Some(chain_pos), Some(param_pos), Some(value)) =
-                (list.next(), list.next(), list.next(), list.next())
-            else {
-                continue; // todo: this should be an error
-            };
-
-            let chain_pos = chain_pos.parse::<usize>().unwrap_or_default();
-            let param_pos = param_pos.parse::<u8>().unwrap_or_default();
-            if self.index_info.contains_key(chain_name) {
-                match value.parse::<f32>() {
-                    // todo: check the name and pos
-                    Ok(v) => self.context.graph[self.index_info[chain_name][chain_pos]]
-                        .node
-                        .send_msg(Message::SetToNumber(param_pos, v)),
-                    Err(_) => {
-                        self.context.graph[self.index_info[chain_name][chain_pos]]
-                            .node
-                            .send_msg(Message::SetToSymbol(param_pos, value.to_string()));
-                    }
-                };
-            }
+            quantize: Quantize::Off,
+            pending: None,
+            update_error: None,
+            refpairs: vec![],
+            crossfade: 0.0,
+            fade: None,
+            scheduled: vec![],
+            smoothing: Smoothing::Off,
+            edits: None,
         }
     }
 
@@ -139,25 +172,152 @@ impl<const N: usize> Engine<N> {
         }
     }
 
+    /// Add a sample, with its channels one after the other, that code can then play as `name`.
+    /// If there already is one called `name`, the nodes that play it switch to the new one, even
+    /// in the middle of a note.
     #[cfg(feature = "use-samples")]
-    pub fn add_sample(&mut self, name: &str, sample: &'static [f32], channels: usize, sr: usize) {
-        self.samples_dict
-            .insert(name.to_owned(), (sample, channels, sr));
+    pub fn add_sample(
+        &mut self,
+        name: &str,
+        sample: impl Into<Arc<[f32]>>,
+        channels: usize,
+        sr: usize,
+    ) {
+        self.insert_sample(name, Sample::new(sample, channels, sr))
+    }
+
+    /// [`Self::add_sample`] for a sample with its own [`glicol_synth::Interpolation`]. It is resampled to the
+    /// engine's rate first if [`Self::set_resample_samples`] is on.
+    #[cfg(feature = "use-samples")]
+    pub fn insert_sample(&mut self, name: &str, sample: Sample) {
+        let sample = match self.resample_samples {
+            true => sample.resample(self.sr),
+            false => sample,
+        };
+        self.replace_sample(name, sample)
+    }
+
+    /// Change how the sample called `name` is read between its frames, also for the nodes that
+    /// already play it, and return whether there is such a sample.
+    #[cfg(feature = "use-samples")]
+    pub fn set_interpolation(
+        &mut self,
+        name: &str,
+        interpolation: glicol_synth::Interpolation,
+    ) -> bool {
+        self.change_sample(name, |sample| sample.interpolation(interpolation))
+    }
+
+    /// Put the sample called `name` in a choke group, or take it out of one with `None`, and
+    /// return whether there is such a sample. When a pattern starts it, the samples of the same
+    /// group that the pattern plays are faded out quickly, like a closed hi-hat cuts an open one.
+    ///
+    /// A group only reaches within one `psampler` node: the samples that other nodes play carry
+    /// on, so a closed and an open hi-hat that should cut each other go in the same pattern.
+    #[cfg(feature = "use-samples")]
+    pub fn set_choke_group(&mut self, name: &str, group: Option<u8>) -> bool {
+        self.change_sample(name, |sample| sample.choke(group))
+    }
+
+    /// Limit how many times a pattern plays the sample called `name` at once, and return whether
+    /// there is such a sample. Starting it once more fades out the oldest one. The limit is
+    /// [`glicol_synth::DEFAULT_MAX_VOICES`] until it is set.
+    #[cfg(feature = "use-samples")]
+    pub fn set_max_voices(&mut self, name: &str, max_voices: usize) -> bool {
+        self.change_sample(name, |sample| sample.max_voices(max_voices))
+    }
+
+    // change how the sample called `name` plays, also for the nodes that already play it
+    #[cfg(feature = "use-samples")]
+    fn change_sample(&mut self, name: &str, change: impl FnOnce(Sample) -> Sample) -> bool {
+        let Some(sample) = self.samples_dict.get(name) else {
+            return false;
+        };
+        let sample = change(sample.clone());
+        self.replace_sample(name, sample);
+        true
+    }
+
+    /// Resample the samples added from now on to the engine's sample rate when they are added, so
+    /// that they aren't interpolated when played at their own pitch. This takes a while for long
+    /// samples, but is done with a windowed sinc, see [`Sample::resample`].
+    #[cfg(feature = "use-samples")]
+    pub fn set_resample_samples(&mut self, resample: bool) {
+        self.resample_samples = resample;
+    }
+
+    #[cfg(feature = "use-samples")]
+    fn replace_sample(&mut self, name: &str, sample: Sample) {
+        let Some(old) = self.samples_dict.insert(name.to_owned(), sample.clone()) else {
+            return;
+        };
+        let same_data = Arc::ptr_eq(&old.data, &sample.data);
+        self.send_to_all(Message::ReplaceSample(old.clone(), sample));
+        if !same_data {
+            self.retire_sample(old);
+        }
+    }
+
+    /// Remove the sample called `name`, and return whether there was one. Code can't use it
+    /// anymore, but the nodes that already play it carry on until they are removed, and it is
+    /// freed after that.
+    #[cfg(feature = "use-samples")]
+    pub fn remove_sample(&mut self, name: &str) -> bool {
+        let Some(sample) = self.samples_dict.remove(name) else {
+            return false;
+        };
+        self.retire_sample(sample);
+        true
+    }
+
+    // Keeps `sample` until no node holds it, so that the last reference is never dropped on the
+    // audio thread of a split engine.
+    fn retire_sample(&mut self, sample: Sample) {
+        self.retired_samples.push(sample.data);
+        self.free_samples();
+    }
+
+    pub(crate) fn free_samples(&mut self) {
+        self.retired_samples
+            .retain(|sample| Arc::strong_count(sample) > 1);
     }
 
     pub fn reset(&mut self) {
-        self.context.reset();
+        self.reset_graph();
         self.ast = None;
         self.index_info.clear();
-        self.index_info_backup.clear();
-        self.temp_node_index.clear();
-        self.samples_dict.clear();
+        for (_, sample) in std::mem::take(&mut self.samples_dict) {
+            self.retire_sample(sample);
+        }
         self.bpm = 120.;
         self.track_amp = 1.0;
         self.seed = 42;
         self.clock = 0;
         self.livecoding = true;
-        self.need_update = false;
+        self.quantize = Quantize::Off;
+        self.pending = None;
+        self.update_error = None;
+        self.refpairs.clear();
+        self.fade = None;
+        self.scheduled.clear();
+        self.smoothing = Smoothing::Off;
+        self.resample_samples = false;
+    }
+
+    /// Apply `code`, or queue it for the next boundary if quantizing (see [`Self::set_quantize`]).
+    ///
+    /// Queued code replaces any code that is still waiting. Only syntax errors are reported here
+    /// for queued code; see [`Self::take_update_error`] for the rest.
+    pub fn update_with_code(&mut self, code: &str) -> Result<(), EngineError> {
+        let Some(at) = self.quantize.next_boundary(self.clock, self.sr, self.bpm) else {
+            return self.apply_code(code);
+        };
+        get_spanned_ast(code)?;
+        self.pending = Some(quantize::PendingUpdate {
+            code: code.to_owned(),
+            at,
+        });
+        Ok(())
     }
 
     // This is kind of a mess. But the basic idea is that we want to:
@@ -169,7 +329,7 @@ impl<const N: usize> Engine<N> {
     // 3. Use that `GraphDiff` to apply the changes that need to be made to `self.context.graph` to
     //       update it fully to reflect the new syntax
     // 4. Set self.ast = new_ast;
-    pub fn update_with_code(&mut self, code: &str) -> Result<(), EngineError> {
+    fn apply_code(&mut self, code: &str) -> Result<(), EngineError> {
         // It would be nice to re-use the allocation in self.ast for this Yoke creation, but I
         // can't figure out a way to.
         // We have to make this Yoke here to return it (we can't like make an `Ast` with the same
@@ -178,26 +338,25 @@ impl<const N: usize> Engine<N> {
         // 'cause then we'll be unable to do this diffing thing against what it used to be b/c
         // it'll have been overwritten.
         let new_ast: YokedAst =
-            Yoke::try_attach_to_cart(code.to_owned().into_boxed_str(), |code| get_ast(code))?;
-
-        self.temp_node_index.clear();
+            Yoke::try_attach_to_cart(code.to_owned().into_boxed_str(), |code| {
+                get_spanned_ast(code)
+            })?;
 
         let mut graph_diff = GraphDiff::default();
+        let mut removed_chains: Vec<String> = vec![];
 
         // also remove the whole chain in_old but not_in_new, after ensuring there is no problem with new stuff
         // println!("\n\nold ast {:?}\n\n new {:?}", self.ast, self.new_ast);
 
         fn add_nodes<'iter, 'ast: 'iter, const N: usize>(
             chain_name: &'ast str,
+            chain_spans: &[pest::Span<'_>],
             iter: impl Iterator<Item = (usize, &'iter Component<'ast>)>,
             graph_diff: &mut GraphDiff<'ast, N>,
-            samples_dict: &HashMap<String, (&'static [f32], usize, usize)>,
-            sr: usize,
-            bpm: f32,
-            seed: usize,
+            node_context: &NodeContext<'_>,
         ) -> Result<(), EngineError> {
             for (i, component) in iter {
-                let (nodedata, reflist) = makenode(component, samples_dict, sr, bpm, seed)?;
+                let (nodedata, reflist) = makenode(component, chain_spans[i], node_context)?;
 
                 if !reflist.is_empty() {
                     graph_diff.refpairlist.push((reflist, chain_name, i));
@@ -210,19 +369,26 @@ impl<const N: usize> Engine<N> {
             Ok(())
         }
 
+        let node_context = NodeContext {
+            samples_dict: &self.samples_dict,
+            sr: self.sr,
+            channels: self.context.channels(),
+            bpm: self.bpm,
+            seed: self.seed,
+        };
+
         if let Some(ref old_ast) = self.ast {
-            for (chain_name, new_chain) in &new_ast.get().nodes {
-                let Some(old_chain) = old_ast.get().nodes.get(chain_name) else {
+            for (chain_name, new_chain) in &new_ast.get().ast.nodes {
+                let chain_spans = &new_ast.get().spans[chain_name];
+                let Some(old_chain) = old_ast.get().ast.nodes.get(chain_name) else {
                     // if we can't find this chain in the old_ast, we just go through it and add
                     // every single one to the chain
                     add_nodes(
                         chain_name,
+                        chain_spans,
                         new_chain.iter().enumerate(),
                         &mut graph_diff,
-                        &self.samples_dict,
-                        self.sr,
-                        self.bpm,
-                        self.seed,
+                        &node_context,
                     )?;
                     continue;
                 };
@@ -237,6 +403,7 @@ impl<const N: usize> Engine<N> {
                     {
                         // If it exists in the new chain, then we have to update it
                         Some((idx, new_comp)) => {
+                            graph_diff.node_keep_list.push((chain_name, old_idx, idx));
                             // the paras can be the same
                             // but if the paras are refs, the source chain of refs can change
                             // e.g. the main chain is o: constsig 42 >> mul ~a
@@ -262,204 +429,161 @@ impl<const N: usize> Engine<N> {
                 // old one, and was thus inserted, and track it for insertion
                 add_nodes(
                     chain_name,
+                    chain_spans,
                     new_chain
                         .iter()
                         .enumerate()
                         .filter(|(_, comp)| !old_chain.iter().any(|old_comp| old_comp == *comp)),
                     &mut graph_diff,
-                    &self.samples_dict,
-                    self.sr,
-                    self.bpm,
-                    self.seed,
+                    &node_context,
                 )?;
             }
 
             // there are some chains show up in old_ast but not in new ast
-            // so we need to figure out what they are and collect them into a Vec
-            graph_diff.idx_to_remove.extend(old_ast.get().nodes.keys()
-                .filter(|key| !new_ast.get().nodes.contains_key(*key))
-                .flat_map(|key| {
-                    // This should be safe to unwrap because index_info should always be consistent
-                    // with self.ast, but we're .expect'ing just to provide a good message in case
-                    self.index_info.remove_entry(*key)
-                        .expect("Index info should be consistent with self.ast, but it turned out to not be. This is a bug.")
-                        .1
-                }));
-
-            // Then remove them all from self
-            while let Some((key, position_in_chain)) = graph_diff.node_remove_list.pop() {
-                if let Some(chain) = self.index_info.get_mut(key) {
-                    // touch the index is fine, as we have a backup
-                    let node_index = chain[position_in_chain];
-
-                    graph_diff.idx_to_remove.push(node_index);
-                    chain.remove(position_in_chain);
-                }
-            }
+            // so we need to figure out what they are and remove them once the update is checked
+            removed_chains.extend(
+                old_ast
+                    .get()
+                    .ast
+                    .nodes
+                    .keys()
+                    .filter(|key| !new_ast.get().ast.nodes.contains_key(*key))
+                    .map(|key| key.to_string()),
+            );
         } else {
             // if the old ast doesn't exist, just add everything to the new graph
-            for (chain_name, new_chain) in &new_ast.get().nodes {
+            for (chain_name, new_chain) in &new_ast.get().ast.nodes {
                 add_nodes(
                     chain_name,
+                    &new_ast.get().spans[chain_name],
                     new_chain.iter().enumerate(),
                     &mut graph_diff,
-                    &self.samples_dict,
-                    self.sr,
-                    self.bpm,
-                    self.seed,
+                    &node_context,
                 )?;
             }
         };
 
-        // go through all the nodes that are
-        while let Some((key, position_in_chain, nodedata)) = graph_diff.node_add_list.pop_front() {
-            // TODO: save these id, if there is an error, remove these node
-            let nodeindex = self.context.graph.add_node(nodedata);
-
-            self.temp_node_index.push(nodeindex);
-            match self.index_info.get_mut(key) {
-                Some(chain) => chain.insert(position_in_chain, nodeindex),
-                None => _ = self.index_info.insert(key.to_string(), vec![nodeindex]),
-            }
-        }
-
-        fn handle_node_update<const N: usize>(
-            graph_diff: &mut GraphDiff<'_, N>,
-            index_info: &HashMap<String, Vec<NodeIndex>>,
-            graph: &mut GlicolGraph<N>,
-            samples_dict: &mut HashMap<String, (&'static [f32], usize, usize)>,
-        ) -> Result<(), EngineError> {
+        // turn the paras of updated nodes into messages, but don't send them yet, so that nothing
+        // has to be undone if a later check fails
+        fn handle_node_update<'ast, const N: usize>(
+            graph_diff: &mut GraphDiff<'ast, N>,
+            samples_dict: &HashMap<String, Sample>,
+            spans: &ChainSpans<'_>,
+        ) -> Result<Vec<(&'ast str, usize, Message)>, EngineError> {
+            let mut msgs = vec![];
             while let Some((key, position_in_chain, paras)) = graph_diff.node_update_list.pop() {
                 // println!("handle update {:?} {:?}", key, position_in_chain);
-                if let Some(chain) = index_info.get(key) {
-                    // TODO: reset order here, if ref is wrong, cannot be reverted
-                    // self.context.graph[
-                    //     chain[position_in_chain]].node.send_msg(Message::ResetOrder);
-                    for (i, para) in paras.iter().enumerate() {
-                        match para {
-                            GlicolPara::Number(v) => graph[chain[position_in_chain]]
-                                .node
-                                .send_msg(Message::SetToNumber(i as u8, *v)),
-                            GlicolPara::Reference(s) => {
-                                graph_diff.refpairlist.push((
-                                    vec![s.to_string()],
-                                    key,
-                                    position_in_chain,
+                for (i, para) in paras.iter().enumerate() {
+                    let msg = match para {
+                        GlicolPara::Number(v) => Message::SetToNumber(i as u8, *v),
+                        GlicolPara::Reference(s) => {
+                            graph_diff.refpairlist.push((
+                                vec![s.to_string()],
+                                key,
+                                position_in_chain,
+                            ));
+                            continue;
+                        }
+                        GlicolPara::SampleSymbol(s) => {
+                            let Some(sample) = samples_dict.get(*s) else {
+                                return Err(EngineError::NonExistSample(
+                                    s.to_string(),
+                                    locate(spans, key, position_in_chain, s),
                                 ));
-                            }
-                            GlicolPara::SampleSymbol(s) => {
-                                let Some(sample) = samples_dict.get(*s) else {
-                                    return Err(EngineError::NonExistSample(s.to_string()));
-                                };
-
-                                graph[chain[position_in_chain]]
-                                    .node
-                                    .send_msg(Message::SetToSamples(i as u8, *sample))
-                            }
-                            GlicolPara::Points(_p) => graph[chain[position_in_chain]]
-                                .node
-                                .send_msg(Message::SetParam(i as u8, (*para).to_inner_owned())),
-                            GlicolPara::Bool(b) => graph[chain[position_in_chain]]
-                                .node
-                                .send_msg(Message::SetToBool(i as u8, *b)),
-                            GlicolPara::Symbol(s) => graph[chain[position_in_chain]]
-                                .node
-                                .send_msg(Message::SetToSymbol(i as u8, s.to_string())),
-                            GlicolPara::Sequence(events) => {
-                                // println!("found seq in update, process it {:?}", events);
-                                // todo: an issue is that when you revert it, these messages cannot be undone
-                                graph[chain[position_in_chain]]
-                                    .node
-                                    .send_msg(Message::SetToSeq(i as u8, events.to_inner_owned()));
-                                let mut reflist: Vec<String> = vec![];
-                                let mut count = 0;
-                                let mut order = hashbrown::HashMap::new();
-                                for event in events {
-                                    if let UsizeOrRef::Ref(s) = &event.1 {
-                                        // reflist: ["~a", "~b", "~a"]
-                                        if !reflist.iter().any(|r| r == s) {
-                                            reflist.push(s.to_string());
-                                            order.insert(s.to_string(), count);
-                                            count += 1;
-                                        }
+                            };
+                            Message::SetToSamples(i as u8, sample.clone())
+                        }
+                        GlicolPara::Points(_p) => {
+                            Message::SetParam(i as u8, (*para).to_inner_owned())
+                        }
+                        GlicolPara::Bool(b) => Message::SetToBool(i as u8, *b),
+                        GlicolPara::Symbol(s) => Message::SetToSymbol(i as u8, s.to_string()),
+                        GlicolPara::Sequence(events) => {
+                            // println!("found seq in update, process it {:?}", events);
+                            msgs.push((
+                                key,
+                                position_in_chain,
+                                Message::SetToSeq(i as u8, events.to_inner_owned()),
+                            ));
+                            let mut reflist: Vec<String> = vec![];
+                            let mut count = 0;
+                            let mut order = hashbrown::HashMap::new();
+                            for event in events {
+                                if let UsizeOrRef::Ref(s) = &event.1 {
+                                    // reflist: ["~a", "~b", "~a"]
+                                    if !reflist.iter().any(|r| r == s) {
+                                        reflist.push(s.to_string());
+                                        order.insert(s.to_string(), count);
+                                        count += 1;
                                     }
                                 }
-                                graph_diff
-                                    .refpairlist
-                                    .push((reflist, key, position_in_chain));
-                                graph[chain[position_in_chain]]
-                                    .node
-                                    .send_msg(Message::SetRefOrder(order));
                             }
-                            GlicolPara::NumberList(l) => graph[chain[position_in_chain]]
-                                .node
-                                .send_msg(Message::SetToNumberList(i as u8, l.clone())),
-                            GlicolPara::Pattern(value_time_list, span) => {
-                                // todo, differ a symbol pattern and number pattern?
-                                let mut samples_dict_selected = HashMap::new();
-                                let mut symbol_pattern = vec![];
-                                let mut number_pattern = vec![];
-
-                                for value_time in value_time_list.iter() {
-                                    let time = value_time.1;
-                                    match &value_time.0 {
-                                        GlicolPara::Number(num) => {
-                                            number_pattern.push((*num, time))
-                                        }
-                                        GlicolPara::Symbol(s) => {
-                                            let Some(sample) = samples_dict.get(*s) else {
-                                                return Err(EngineError::NonExistSample(
-                                                    s.to_string(),
-                                                ));
-                                            };
-
-                                            samples_dict_selected.insert(s.to_string(), *sample);
-                                            symbol_pattern.push((s.to_string(), time));
-                                        }
-                                        _ => unimplemented!(),
-                                    };
-                                    // pattern.push((value, time));
-                                }
-
-                                if !symbol_pattern.is_empty() {
-                                    graph[chain[position_in_chain]].node.send_msg(
-                                        Message::SetSamplePattern(
-                                            symbol_pattern,
-                                            *span,
-                                            samples_dict_selected,
-                                        ),
-                                    )
-                                } else {
-                                    graph[chain[position_in_chain]]
-                                        .node
-                                        .send_msg(Message::SetPattern(number_pattern, *span))
-                                }
+                            graph_diff
+                                .refpairlist
+                                .push((reflist, key, position_in_chain));
+                            Message::SetRefOrder(order)
+                        }
+                        GlicolPara::NumberList(l) => Message::SetToNumberList(i as u8, l.clone()),
+                        GlicolPara::Pattern(value_time_list, span) => {
+                            // todo, differ a symbol pattern and number pattern?
+                            let mut samples_dict_selected = HashMap::new();
+                            let mut symbol_pattern = vec![];
+                            let mut number_pattern = vec![];
+
+                            for value_time in value_time_list.iter() {
+                                let time = value_time.1;
+                                match &value_time.0 {
+                                    GlicolPara::Number(num) => number_pattern.push((*num, time)),
+                                    GlicolPara::Symbol(s) => {
+                                        let Some(sample) = samples_dict.get(*s) else {
+                                            return Err(EngineError::NonExistSample(
+                                                s.to_string(),
+                                                locate(spans, key, position_in_chain, s),
+                                            ));
+                                        };
+
+                                        samples_dict_selected.insert(s.to_string(), sample.clone());
+                                        symbol_pattern.push(SampleEvent::new(*s, time));
+                                    }
+                                    v => {
+                                        return Err(EngineError::InvalidParameter(
+                                            format!("{v:?} can't be used in a pattern"),
+                                            locate(spans, key, position_in_chain, ""),
+                                        ))
+                                    }
+                                };
+                                // pattern.push((value, time));
                             }
 
-                            _ => {}
+                            if !symbol_pattern.is_empty() {
+                                Message::SetSamplePattern(
+                                    symbol_pattern,
+                                    *span,
+                                    samples_dict_selected,
+                                )
+                            } else {
+                                Message::SetPattern(number_pattern, *span)
+                            }
                         }
-                    }
 
-                    // self.context.send_msg(index: NodeIndex, msg: Message)
+                        _ => continue,
+                    };
+                    msgs.push((key, position_in_chain, msg));
                 }
             }
-            Ok(())
+            Ok(msgs)
         }
 
-        if let Err(e) = handle_node_update(
-            &mut graph_diff,
-            &self.index_info,
-            &mut self.context.graph,
-            &mut self.samples_dict,
-        ) {
-            return Err(self.clean_up(e));
-        };
+        let node_msgs =
+            handle_node_update(&mut graph_diff, &self.samples_dict, &new_ast.get().spans)?;
 
         // verify that all refs that are mentioned in the refpairlist do, in fact, exist in the
-        // new_ast or current index_info and will thus work when we try to connect the graph
+        // new_ast or in the index_info that will be left once the removed chains are gone, and will
+        // thus work when we try to connect the graph
         fn handle_ref_check(
             refpairlist: &[(Vec<String>, &str, usize)],
             index_info: &HashMap<String, Vec<NodeIndex>>,
+            removed_chains: &[String],
             new_ast: &YokedAst,
         ) -> Result<(), EngineError> {
             // ref pair is like (~mod -> a node [e.g key: out, pos_in_chain: 3])
@@ -467,119 +591,406 @@ impl<const N: usize> Engine<N> {
             // because old ast hashmap has something that may need to be deleted
             // println!("ref check {:?}", self.refpairlist);
 
-            for (names, _, _) in refpairlist {
+            let spans = &new_ast.get().spans;
+            let new_keys = new_ast.get().ast.nodes.keys().copied();
+            let kept_keys = index_info
+                .keys()
+                .map(String::as_str)
+                .filter(|key| !removed_chains.iter().any(|removed| removed == key));
+            let keys: std::collections::HashSet<&str> = new_keys.chain(kept_keys).collect();
+
+            for (names, chain_name, position_in_chain) in refpairlist {
+                let missing = |refname: &str| {
+                    EngineError::NonExistReference(
+                        refname.to_owned(),
+                        locate(spans, chain_name, *position_in_chain, refname),
+                    )
+                };
                 for refname in names {
                     // println!("ref check {} {}", self.new_ast.contains_key(refname), refname);
                     if refname.contains("..") {
                         // println!("look for {}", &refname.replace("..", ""));
-                        if !index_info
-                            .keys()
-                            .any(|key| key.starts_with(&refname.replace("..", "")))
-                        {
-                            return Err(EngineError::NonExistReference(refname.to_owned()));
+                        let prefix = refname.replace("..", "");
+                        if !keys.iter().any(|key| key.starts_with(&prefix)) {
+                            return Err(missing(refname));
                         }
-                    } else if !new_ast.get().nodes.contains_key(&**refname)
-                        && !index_info.contains_key(refname)
-                    {
-                        return Err(EngineError::NonExistReference(refname.to_owned()));
+                    } else if !keys.contains(refname.as_str()) {
+                        return Err(missing(refname));
                     }
                 }
             }
             Ok(())
         }
 
-        match handle_ref_check(&graph_diff.refpairlist, &self.index_info, &new_ast) {
-            Ok(_) => {
-                // println!(" ref check &self.node_index_to_remove {:?}", &self.node_index_to_remove);
-                for id in &graph_diff.idx_to_remove {
-                    self.context.graph.remove_node(*id);
+        handle_ref_check(
+            &graph_diff.refpairlist,
+            &self.index_info,
+            &removed_chains,
+            &new_ast,
+        )?;
+
+        // a chain can only depend on itself through `fb`, otherwise there is no order that it can
+        // be processed in
+        fn handle_cycle_check(new_ast: &YokedAst) -> Result<(), EngineError> {
+            type Deps<'a> = HashMap<&'a str, Vec<(&'a str, usize, &'a str)>>;
+            let ast = new_ast.get();
+            let mut names: Vec<&str> = ast.ast.nodes.keys().copied().collect();
+            names.sort_unstable();
+
+            // (chain, position, reference) of everything each chain reads from
+            let mut deps = Deps::new();
+            for name in &names {
+                let reads = deps.entry(*name).or_default();
+                for (pos, component) in ast.ast.nodes[name].iter().enumerate() {
+                    if matches!(component, Component::Feedback(_)) {
+                        continue;
+                    }
+                    for refname in component.all_references() {
+                        let prefix = refname.strip_suffix("..");
+                        reads.extend(
+                            names
+                                .iter()
+                                .filter(|target| match prefix {
+                                    Some(prefix) => target.starts_with(prefix),
+                                    None => **target == refname,
+                                })
+                                .map(|target| (*target, pos, refname)),
+                        );
+                    }
                 }
             }
-            Err(e) => return Err(self.clean_up(e)),
-        };
 
-        self.context.graph.clear_edges();
-        // println!("self.index_info in handle_connection{:?}", self.index_info);
-        // println!("self.refpairlist in handle_connection {:?}", self.refpairlist);
+            // `done` is false while a chain is being visited, so meeting it again closes a loop
+            fn visit<'a>(
+                name: &'a str,
+                deps: &Deps<'a>,
+                done: &mut HashMap<&'a str, bool>,
+            ) -> Option<(&'a str, usize, &'a str)> {
+                done.insert(name, false);
+                for &(target, pos, refname) in &deps[name] {
+                    let found = match done.get(target) {
+                        Some(false) => Some((name, pos, refname)),
+                        Some(true) => None,
+                        None => visit(target, deps, done),
+                    };
+                    if found.is_some() {
+                        return found;
+                    }
+                }
+                done.insert(name, true);
+                None
+            }
 
-        // now we have to go through and actually make the graph with all the connections we have
-        let mut already_reset = std::collections::HashSet::new();
-        for (reflist, name, new_idx) in &graph_diff.refpairlist {
-            let Some(chain) = self.index_info.get(*name) else {
-                return Err(EngineError::NonExistReference(name.to_string()));
+            let mut done = HashMap::new();
+            for name in &names {
+                if done.contains_key(name) {
+                    continue;
+                }
+                if let Some((chain, pos, refname)) = visit(name, &deps, &mut done) {
+                    return Err(EngineError::CyclicReference(
+                        refname.to_owned(),
+                        locate(&ast.spans, chain, pos, refname),
+                    ));
+                }
+            }
+            Ok(())
+        }
+
+        handle_cycle_check(&new_ast)?;
+
+        // every connection has to be between channel counts that can be mixed, e.g. not from a
+        // stereo node into a 3-channel output
+        fn handle_channel_check<const N: usize>(
+            new_ast: &YokedAst,
+            graph_diff: &GraphDiff<'_, N>,
+            index_info: &HashMap<String, Vec<NodeIndex>>,
+            context: &AudioContext<N>,
+        ) -> Result<(), EngineError> {
+            let ast = new_ast.get();
+            // the channel count of every node in the new chains, and what it mixes its inputs to
+            let layout = |data: &GlicolNodeData<N>| {
+                let channels = data.buffers.len();
+                (channels, data.node.input_channels().unwrap_or(channels))
+            };
+            let mut layouts = HashMap::new();
+            for (chain, pos, data) in &graph_diff.node_add_list {
+                layouts.insert((*chain, *pos), layout(data));
+            }
+            for (chain, old, new) in &graph_diff.node_keep_list {
+                let data = &context.graph[index_info[*chain][*old]];
+                layouts.insert((*chain, *new), layout(data));
+            }
+            // the channel count at the end of a chain, which is what a reference to it reads
+            let tail = |name: &str| match ast.ast.nodes.get(name) {
+                Some(chain) => layouts
+                    .get(&(name, chain.len().checked_sub(1)?))
+                    .map(|l| l.0),
+                None => index_info
+                    .get(name)
+                    .and_then(|chain| chain.last())
+                    .map(|index| context.graph[*index].buffers.len()),
+            };
+            let check = |from: Option<usize>, to: Option<usize>, chain, pos, token| {
+                let (Some(from), Some(to)) = (from, to) else {
+                    return Ok(());
+                };
+                if can_mix(from, to) {
+                    return Ok(());
+                }
+                Err(EngineError::ChannelMismatch(
+                    format!("{from} channels into {to}"),
+                    locate(&ast.spans, chain, pos, token),
+                ))
             };
 
-            let index = chain[*new_idx];
+            let mut names: Vec<&str> = ast.ast.nodes.keys().copied().collect();
+            names.sort_unstable();
+            for name in names {
+                let len = ast.ast.nodes[name].len();
+                for pos in 1..len {
+                    let from = layouts.get(&(name, pos - 1)).map(|l| l.0);
+                    let to = layouts.get(&(name, pos)).map(|l| l.1);
+                    check(from, to, name, pos, "")?;
+                }
+                if !name.contains('~') && len > 0 {
+                    check(tail(name), Some(context.channels()), name, len - 1, "")?;
+                }
+            }
+            for (refs, chain, pos) in &graph_diff.refpairlist {
+                let to = layouts.get(&(*chain, *pos)).map(|l| l.1);
+                for refname in refs {
+                    match refname.strip_suffix("..") {
+                        Some(prefix) => {
+                            let keys = ast.ast.nodes.keys().copied();
+                            let kept = index_info.keys().map(String::as_str);
+                            for key in keys.chain(kept).filter(|key| key.starts_with(prefix)) {
+                                check(tail(key), to, chain, *pos, refname)?;
+                            }
+                        }
+                        None => check(tail(refname), to, chain, *pos, refname)?,
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        handle_channel_check(&new_ast, &graph_diff, &self.index_info, &self.context)?;
+
+        // Everything has been checked at this point, and nothing below can fail, so either the
+        // whole update is applied or the engine is left exactly as it was.
+
+        // an update in the middle of a fade cuts the rest of it short
+        self.finish_fade();
+        let fade = (self.crossfade > 0.0).then(|| {
+            let new_chains = new_ast
+                .get()
+                .ast
+                .nodes
+                .iter()
+                .map(|(name, chain)| (*name, chain.len()))
+                .collect();
+            self.plan_fade(&new_chains, &graph_diff.node_keep_list, &removed_chains)
+        });
+
+        for key in &removed_chains {
+            // This should be safe to unwrap because index_info should always be consistent
+            // with self.ast, but we're .expect'ing just to provide a good message in case
+            graph_diff.idx_to_remove.extend(self.index_info.remove(key).expect(
+                "Index info should be consistent with self.ast, but it turned out to not be. This is a bug.",
+            ));
+        }
+
+        // Then remove them all from self
+        while let Some((key, position_in_chain)) = graph_diff.node_remove_list.pop() {
+            if let Some(chain) = self.index_info.get_mut(key) {
+                let node_index = chain[position_in_chain];
 
-            if !already_reset.contains(&index) {
-                self.context.graph[index].node.send_msg(Message::ResetOrder);
-                already_reset.insert(index);
+                graph_diff.idx_to_remove.push(node_index);
+                chain.remove(position_in_chain);
             }
+        }
+
+        // go through all the nodes that are
+        while let Some((key, position_in_chain, nodedata)) = graph_diff.node_add_list.pop_front() {
+            let nodeindex = self.add_node(nodedata);
+            if self.smoothing != Smoothing::Off {
+                self.send_to_node(nodeindex, Message::SetSmoothing(None, self.smoothing));
+            }
+
+            match self.index_info.get_mut(key) {
+                Some(chain) => chain.insert(position_in_chain, nodeindex),
+                None => _ = self.index_info.insert(key.to_string(), vec![nodeindex]),
+            }
+        }
 
+        for (key, position_in_chain, msg) in node_msgs {
+            if let Some(index) = self
+                .index_info
+                .get(key)
+                .and_then(|chain| chain.get(position_in_chain))
+            {
+                self.send_to_node(*index, msg);
+            }
+        }
+
+        // println!(" ref check &self.node_index_to_remove {:?}", &self.node_index_to_remove);
+        for id in &graph_diff.idx_to_remove {
+            // the nodes that are fading out get removed once the fade is over
+            if !fade.as_ref().is_some_and(|fade| fade.is_old_node(*id)) {
+                self.remove_node(*id);
+            }
+        }
+
+        self.fade = fade;
+        self.refpairs = graph_diff
+            .refpairlist
+            .into_iter()
+            .map(|(refs, chain, pos)| (refs, chain.to_owned(), pos))
+            .collect();
+        self.connect_graph();
+
+        // We can't reuse the allocation here as far as I can tell; see the comment at the top of
+        // Self::parse
+        self.ast = Some(new_ast);
+        Ok(())
+    }
+
+    // Connect every chain, reference and fade in the graph from scratch.
+    fn connect_graph(&mut self) {
+        self.clear_edges();
+        let fade = self.fade.take();
+
+        // where the output of a chain comes from: a crossfade at its end, or its last node
+        let tail = |name: &str| -> Option<NodeIndex> {
+            let fade = fade.as_ref();
+            match self.index_info.get(name) {
+                Some(chain) => fade
+                    .and_then(|fade| {
+                        fade.segments
+                            .iter()
+                            .find(|seg| seg.chain == name && seg.end == chain.len())
+                    })
+                    .map(|seg| seg.xfade)
+                    .or(chain.last().copied()),
+                // an old node may refer to a chain that is only still around while fading out
+                None => fade
+                    .and_then(|fade| fade.ghosts.iter().find(|ghost| ghost.chain == name))
+                    .and_then(|ghost| ghost.nodes.last().copied()),
+            }
+        };
+
+        // now we have to go through and actually make the graph with all the connections we have
+        let old_refs = fade.iter().flat_map(|fade| fade.old_refs.iter().cloned());
+        let current_refs = self
+            .refpairs
+            .iter()
+            .map(|(refs, name, pos)| (self.index_info[name][*pos], refs.clone()));
+        let mut ref_edges = vec![];
+        for (index, reflist) in current_refs.chain(old_refs) {
             for refname in reflist {
                 if refname.contains("..") {
                     // println!("look for {}", &refname.replace("..", ""));
-                    for (key, value) in self.index_info.iter() {
-                        if key.starts_with(&refname.replace("..", "")) {
-                            self.context.connect(*value.last().unwrap(), index);
+                    let prefix = refname.replace("..", "");
+                    for key in self.index_info.keys() {
+                        if key.starts_with(&prefix) {
+                            ref_edges.extend(tail(key).map(|from| (from, index)));
                         }
                     }
                 } else {
-                    self.context
-                        .connect(*self.index_info[refname].last().unwrap(), index);
+                    ref_edges.extend(tail(&refname).map(|from| (from, index)));
                 }
             }
         }
 
+        let mut chain_edges = vec![];
         for (key, chain) in &self.index_info {
-            for window in chain.windows(2) {
-                // this is guaranteed to succeed as long as the argument to windows is 2
-                // TODO when array_windows is stabilized, change over to that
-                if let [start, end] = window {
-                    self.context.connect_with_order(*start, *end, 0);
+            let mut segments: Vec<_> = fade
+                .iter()
+                .flat_map(|fade| &fade.segments)
+                .filter(|seg| &seg.chain == key)
+                .collect();
+            segments.sort_by_key(|seg| seg.start);
+            let mut segments = segments.into_iter().peekable();
+
+            let mut prev = None;
+            let mut pos = 0;
+            loop {
+                if let Some(seg) = segments.next_if(|seg| seg.start == pos) {
+                    // the old and the new run both take the input of the segment, and the
+                    // crossfade takes over as the input of whatever follows
+                    let new_side = chain_run(&mut chain_edges, prev, &chain[seg.start..seg.end]);
+                    let old_side = chain_run(&mut chain_edges, prev, &seg.old);
+                    chain_edges.extend(old_side.map(|from| (from, seg.xfade, 0)));
+                    chain_edges.extend(new_side.map(|from| (from, seg.xfade, 1)));
+                    prev = Some(seg.xfade);
+                    pos = seg.end;
+                    continue;
                 }
+                let Some(node) = chain.get(pos) else { break };
+                chain_edges.extend(prev.map(|from| (from, *node, 0)));
+                prev = Some(*node);
+                pos += 1;
             }
             if !key.contains('~') {
-                if let Some(end) = chain.last() {
-                    self.context
-                        .connect_with_order(*end, self.context.destination, 0);
-                }
+                chain_edges.extend(prev.map(|from| (from, self.context.destination, 0)));
             }
         }
 
-        // We can't reuse the allocation here as far as I can tell; see the comment at the top of
-        // Self::parse
-        self.ast = Some(new_ast);
-        self.index_info_backup.clone_from(&self.index_info);
-        Ok(())
-    }
+        for ghost in fade.iter().flat_map(|fade| &fade.ghosts) {
+            let end = chain_run(&mut chain_edges, None, &ghost.nodes);
+            if let (Some(end), Some(xfade)) = (end, ghost.xfade) {
+                chain_edges.push((end, xfade, 0));
+                chain_edges.push((xfade, self.context.destination, 0));
+            }
+        }
 
-    pub fn clean_up(&mut self, e: EngineError) -> EngineError {
-        // remove the added node
-        // use the old index
-        for id in &self.temp_node_index {
-            // println!("graph.remove_node in clean_up {:?}", *id);
-            self.context.graph.remove_node(*id);
+        let mut already_reset = std::collections::HashSet::new();
+        for (from, to) in ref_edges {
+            if already_reset.insert(to) {
+                self.send_to_node(to, Message::ResetOrder);
+            }
+            self.connect(from, to);
+        }
+        if let Some(fade) = &fade {
+            for seg in &fade.segments {
+                self.send_to_node(seg.xfade, Message::ResetOrder);
+            }
         }
-        self.index_info.clone_from(&self.index_info_backup);
-        e
+        for (from, to, order) in chain_edges {
+            self.connect_with_order(from, to, order);
+        }
+        self.fade = fade;
     }
 
-    pub fn next_block(&mut self, buf: Vec<&[f32]>) -> &[Buffer<N>] {
-        //  -> &Vec<Buffer<N>>
-        if !buf.is_empty() {
-            self.context.graph[self.index_info[&"~input".to_string()][0]].buffers[0]
-                .copy_from_slice(buf[0]);
+    /// Render the next block. `buf` is copied into the `~input` chain, one slice per channel, and
+    /// up- or downmixed if it doesn't have as many slices as the engine has channels.
+    ///
+    /// Once the graph has settled, this doesn't allocate: updates, messages and crossfades only
+    /// allocate on the block where they land. The scripted `meta` and `eval` nodes are the
+    /// exception, since their interpreters allocate as they run.
+    pub fn next_block(&mut self, buf: &[&[f32]]) -> &[Buffer<N>] {
+        self.apply_pending_update();
+        if self
+            .fade
+            .as_ref()
+            .is_some_and(|fade| self.clock >= fade.until)
+        {
+            self.finish_fade();
+            self.connect_graph();
         }
+        self.send_scheduled();
 
-        if buf.len() > 1 {
-            self.context.graph[self.index_info[&"~input".to_string()][0]].buffers[1]
-                .copy_from_slice(buf[1]);
+        //  -> &Vec<Buffer<N>>
+        if !buf.is_empty() {
+            let input = self.index_info["~input"][0];
+            let buffers = &mut self.context.graph[input].buffers;
+            for buffer in buffers.iter_mut() {
+                buffer.silence();
+            }
+            mix_into(buf, buffers);
         }
 
-        self.context
-            .processor
-            .process(&mut self.context.graph, self.context.destination);
+        self.context.next_block();
         // println!("result {:?}", &self.context.graph[self.context.destination].buffers);
         self.clock += N;
         &self.context.graph[self.context.destination].buffers
@@ -587,10 +998,36 @@ impl<const N: usize> Engine<N> {
 
     pub fn set_bpm(&mut self, bpm: f32) {
         self.bpm = bpm;
-        self.context.send_msg_to_all(Message::SetBPM(bpm));
+        self.send_to_all(Message::SetBPM(bpm));
     }
+    /// Smooth every numeric parameter of every node, including the nodes added by later updates,
+    /// so that changing them doesn't click. Use [`Self::set_param_smoothing`] for a single one.
+    pub fn set_smoothing(&mut self, smoothing: Smoothing) {
+        self.smoothing = smoothing;
+        self.send_to_all(Message::SetSmoothing(None, smoothing));
+    }
+    /// Process the chains that don't depend on each other on `threads` threads. The output
+    /// stays exactly the same, only faster for big patches, so it suits offline rendering best.
+    /// Set it before [`Self::split`], as the threads go with the audio processor.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.context.processor.set_threads(threads);
+    }
+    /// Change the sample rate, e.g. when the audio device does. The nodes already in the graph
+    /// keep their state and carry on at the new rate, so playback doesn't restart; delay lines
+    /// are reallocated to keep their length in time. Smoothing, ramps, scheduled messages and a
+    /// quantized update that is waiting keep their timing too.
     pub fn set_sr(&mut self, sr: usize) {
-        self.sr = sr
+        let (from, clock) = (self.sr, self.clock);
+        self.smoothing = self.smoothing.rescale(from, sr);
+        for scheduled in &mut self.scheduled {
+            scheduled.at = schedule::retime(scheduled.at, clock, from, sr);
+        }
+        if let Some(pending) = &mut self.pending {
+            pending.at = schedule::retime(pending.at, clock, from, sr);
+        }
+        self.sr = sr;
+        self.context.set_sr(sr);
+        self.push_edit(realtime::Edit::SampleRate(sr));
     }
     pub fn set_seed(&mut self, seed: usize) {
         self.seed = seed
@@ -600,11 +1037,23 @@ impl<const N: usize> Engine<N> {
     }
 
     #[cfg(test)]
-    fn get_ast(&self) -> Option<&Ast<'_>> {
-        self.ast.as_ref().map(|y| y.get())
+    fn get_ast(&self) -> Option<&glicol_parser::nodes::Ast<'_>> {
+        self.ast.as_ref().map(|y| &y.get().ast)
     }
 }
 
+// Connect `nodes` one after another, starting from `input`, and return the end of the run.
+fn chain_run(
+    edges: &mut Vec<(NodeIndex, NodeIndex, usize)>,
+    input: Option<NodeIndex>,
+    nodes: &[NodeIndex],
+) -> Option<NodeIndex> {
+    nodes.iter().fold(input, |prev, node| {
+        edges.extend(prev.map(|from| (from, *node, 0)));
+        Some(*node)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
@@ -737,4 +1186,40 @@ mod tests {
 
         assert_eq!(eng.update_with_code("o: saw 440 >> mul 0.3"), Ok(()));
     }
+
+    #[test]
+    fn failed_update_changes_nothing() {
+        let code = "
+            o: saw 440 >> mul ~am
+            ~am: sin 2 >> mul 0.5
+            b: sin 220 >> mul 0.1
+        ";
+        let mut reference = Engine::<128>::new();
+        let mut eng = Engine::<128>::new();
+        reference.update_with_code(code).unwrap();
+        eng.update_with_code(code).unwrap();
+        reference.next_block(&[]);
+        eng.next_block(&[]);
+
+        let index_info = eng.index_info.clone();
+        let node_count = eng.context.graph.node_count();
+        let edge_count = eng.context.graph.edge_count();
+
+        // removes a chain, changes and adds nodes, and then fails on the last reference
+        assert!(eng
+            .update_with_code(
+                "
+            o: squ 110 >> mul ~am >> mul ~nope
+            ~am: sin 2 >> mul 0.5
+        "
+            )
+            .is_err());
+
+        assert_eq!(eng.index_info, index_info);
+        assert_eq!(eng.context.graph.node_count(), node_count);
+        assert_eq!(eng.context.graph.edge_count(), edge_count);
+        for _ in 0..4 {
+            assert_eq!(reference.next_block(&[]), eng.next_block(&[]));
+        }
+    }
 }
@@ -0,0 +1,190 @@
+//! Reading samples from WAV and FLAC files.
+//!
+//! Samples are named the way the browser build names them, so that code playing `\808bd` runs
+//! the same in both. A single file is named after itself, without its extension and in
+//! lowercase, with `-` and spaces spelled `_` and `#` spelled `_sharp_`, so `Piano C#4.wav` is
+//! `\piano_c_sharp_4`. The files of a directory are named after the folder they are in, spelled
+//! the same way, and their place in it counting from 0, so the files of `Drums/` are
+//! `\drums_0`, `\drums_1` and so on in the order of their paths.
+
+use crate::Engine;
+use hashbrown::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Why a file couldn't be loaded as a sample.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(PathBuf, std::io::Error),
+    Wav(PathBuf, hound::Error),
+    Flac(PathBuf, claxon::Error),
+    /// Neither a `.wav` nor a `.flac` file.
+    Format(PathBuf),
+    /// The samplers only play mono and stereo samples.
+    Channels(PathBuf, usize),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "Can't read {}: {e}", path.display()),
+            Self::Wav(path, e) => write!(f, "Can't decode {}: {e}", path.display()),
+            Self::Flac(path, e) => write!(f, "Can't decode {}: {e}", path.display()),
+            Self::Format(path) => write!(f, "{} is not a WAV or FLAC file", path.display()),
+            Self::Channels(path, channels) => write!(
+                f,
+                "{} has {channels} channels, only mono and stereo samples are supported",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Wav(_, e) => Some(e),
+            Self::Flac(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+enum Format {
+    Wav,
+    Flac,
+}
+
+fn format(path: &Path) -> Option<Format> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "wav" | "wave" => Some(Format::Wav),
+        "flac" => Some(Format::Flac),
+        _ => None,
+    }
+}
+
+/// Decode a WAV (integer or float) or FLAC file into its channels one after the other, as
+/// [`Engine::add_sample`] takes them, with its channel count and sample rate.
+pub fn decode(path: impl AsRef<Path>) -> Result<(Vec<f32>, usize, usize), LoadError> {
+    let path = path.as_ref();
+    let (interleaved, channels, sr) = match format(path) {
+        Some(Format::Wav) => decode_wav(path).map_err(|e| LoadError::Wav(path.to_owned(), e))?,
+        Some(Format::Flac) => decode_flac(path).map_err(|e| LoadError::Flac(path.to_owned(), e))?,
+        None => return Err(LoadError::Format(path.to_owned())),
+    };
+    if !(1..=2).contains(&channels) {
+        return Err(LoadError::Channels(path.to_owned(), channels));
+    }
+    let frames = interleaved.len() / channels;
+    let mut planar = vec![0.0; frames * channels];
+    for (i, frame) in interleaved.chunks_exact(channels).enumerate() {
+        for (c, x) in frame.iter().enumerate() {
+            planar[c * frames + i] = *x;
+        }
+    }
+    Ok((planar, channels, sr))
+}
+
+fn decode_wav(path: &Path) -> Result<(Vec<f32>, usize, usize), hound::Error> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = full_scale(spec.bits_per_sample.into());
+            reader
+                .samples::<i32>()
+                .map(|x| x.map(|x| x as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    Ok((samples, spec.channels.into(), spec.sample_rate as usize))
+}
+
+fn decode_flac(path: &Path) -> Result<(Vec<f32>, usize, usize), claxon::Error> {
+    let mut reader = claxon::FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let scale = full_scale(info.bits_per_sample);
+    let samples = reader
+        .samples()
+        .map(|x| x.map(|x| x as f32 / scale))
+        .collect::<Result<_, _>>()?;
+    Ok((samples, info.channels as usize, info.sample_rate as usize))
+}
+
+// what an integer sample of `bits` is divided by to land in -1..1
+fn full_scale(bits: u32) -> f32 {
+    (1u64 << (bits - 1)) as f32
+}
+
+/// The name a single sample file at `path` gets.
+pub fn sample_name(path: impl AsRef<Path>) -> String {
+    let path = path.as_ref().with_extension("");
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    format!("\\{}", symbol(&name))
+}
+
+// what the browser build makes of the name of a file or a folder
+fn symbol(name: &str) -> String {
+    name.to_lowercase()
+        .replace(['-', ' '], "_")
+        .replace('#', "_sharp_")
+}
+
+// every WAV and FLAC file under `dir`
+fn audio_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), LoadError> {
+    let io_error = |e| LoadError::Io(dir.to_owned(), e);
+    for entry in std::fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.is_dir() {
+            audio_files(&path, files)?;
+        } else if format(&path).is_some() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl<const N: usize> Engine<N> {
+    /// Load a WAV or FLAC file as a sample named after the file, and return the name. A sample
+    /// that already has that name is replaced, see [`Engine::add_sample`].
+    pub fn load_sample(&mut self, path: impl AsRef<Path>) -> Result<String, LoadError> {
+        let path = path.as_ref();
+        let (sample, channels, sr) = decode(path)?;
+        let name = sample_name(path.file_name().unwrap_or_default());
+        self.add_sample(&name, sample, channels, sr);
+        Ok(name)
+    }
+
+    /// Load every WAV and FLAC file in `dir` and its subdirectories, named after the folder
+    /// they are in (see the [module docs](self)), and return their names in the order of their
+    /// paths. Other files are skipped. If one of them can't be loaded, none are.
+    pub fn load_samples(&mut self, dir: impl AsRef<Path>) -> Result<Vec<String>, LoadError> {
+        let dir = dir.as_ref();
+        // so that the files right in `dir` have a folder name even if it is `.`
+        let dir = dir
+            .canonicalize()
+            .map_err(|e| LoadError::Io(dir.to_owned(), e))?;
+        let mut files = vec![];
+        audio_files(&dir, &mut files)?;
+        files.sort();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut samples = Vec::with_capacity(files.len());
+        for path in &files {
+            let folder = path.parent().and_then(Path::file_name).unwrap_or_default();
+            let folder = symbol(&folder.to_string_lossy());
+            let count = counts.entry(folder.clone()).or_insert(0);
+            let name = format!("\\{folder}_{count}");
+            *count += 1;
+            samples.push((name, decode(path)?));
+        }
+        Ok(samples
+            .into_iter()
+            .map(|(name, (sample, channels, sr))| {
+                self.add_sample(&name, sample, channels, sr);
+                name
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,79 @@
+//! Where the audio budget goes, chain by chain.
+//!
+//! Profiling is off until [`Engine::set_profiling`] is given a clock. From then on every node is
+//! timed on every block, and [`Engine::take_profile`] averages that per chain and position, the
+//! same way the nodes are found in `index_info`.
+
+use crate::{realtime::Edit, Engine};
+use glicol_synth::Clock;
+use hashbrown::HashMap;
+use petgraph::graph::NodeIndex;
+
+/// How long processing took, from [`Engine::take_profile`]. All times are in seconds, averaged
+/// over `blocks` blocks.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Profile {
+    pub blocks: usize,
+    /// How long a block lasts, which is the most that processing it may take.
+    pub budget: f64,
+    /// The time spent on a block, output and crossfades included.
+    pub total: f64,
+    /// The time spent in each node of each chain, by position in the chain.
+    pub chains: HashMap<String, Vec<f64>>,
+}
+
+impl Profile {
+    /// The share of the budget in use, where 1 means that processing only just keeps up. This is
+    /// what a DSP-load meter shows.
+    pub fn load(&self) -> f64 {
+        match self.budget {
+            budget if budget > 0.0 => self.total / budget,
+            _ => 0.0,
+        }
+    }
+
+    /// The time spent on a whole chain.
+    pub fn chain(&self, name: &str) -> f64 {
+        self.chains
+            .get(name)
+            .map_or(0.0, |nodes| nodes.iter().sum())
+    }
+}
+
+impl<const N: usize> Engine<N> {
+    /// Time every node with `clock`, e.g. [`glicol_synth::std_clock`], or stop with `None`.
+    /// The clock is read once per node per block, so it only costs anything while it is on.
+    pub fn set_profiling(&mut self, clock: Option<Clock>) {
+        self.context.set_profiling(clock);
+        self.push_edit(Edit::Profiling(clock));
+    }
+
+    /// Where the time went since the last call, or since the graph last changed.
+    pub fn take_profile(&mut self) -> Profile {
+        let profile = self.context.take_profile();
+        self.name_profile(profile)
+    }
+
+    /// Put chain names to the times of the nodes, as measured by the processor.
+    pub(crate) fn name_profile(&self, profile: glicol_synth::Profile<NodeIndex>) -> Profile {
+        let blocks = profile.blocks.max(1) as f64;
+        let times: HashMap<NodeIndex, f64> = profile.nodes.into_iter().collect();
+        let chains = self
+            .index_info
+            .iter()
+            .map(|(name, indices)| {
+                let nodes = indices
+                    .iter()
+                    .map(|index| times.get(index).map_or(0.0, |time| time / blocks))
+                    .collect();
+                (name.clone(), nodes)
+            })
+            .collect();
+        Profile {
+            blocks: profile.blocks,
+            budget: N as f64 / self.sr as f64,
+            total: profile.total / blocks,
+            chains,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Code updates that wait for the next beat or bar.
+//!
+//! Beats and bars are counted from `clock == 0` at the current bpm, with four beats to a bar (the
+//! same as a cycle in the patterns). The graph is processed in blocks of `N` samples, so a queued
+//! update is applied at the start of the first block that begins on or after the boundary: exactly
+//! on it when the boundary falls on a block start, otherwise less than one block later.
+
+use crate::{Engine, EngineError};
+
+/// When `Engine::update_with_code` applies new code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quantize {
+    /// On the next block.
+    #[default]
+    Off,
+    Beat,
+    Bar,
+    /// On the next multiple of this many bars.
+    Bars(usize),
+}
+
+impl Quantize {
+    /// The length of the grid in samples, or `None` when not quantizing.
+    pub fn period(self, sr: usize, bpm: f32) -> Option<f64> {
+        let beat = 60. / bpm as f64 * sr as f64;
+        match self {
+            Self::Off => None,
+            Self::Beat => Some(beat),
+            Self::Bar => Some(beat * 4.),
+            Self::Bars(n) => Some(beat * 4. * n.max(1) as f64),
+        }
+    }
+
+    /// The first boundary at or after `clock`.
+    pub fn next_boundary(self, clock: usize, sr: usize, bpm: f32) -> Option<usize> {
+        let period = self.period(sr, bpm)?;
+        let boundary = (clock as f64 / period).ceil() * period;
+        Some(boundary.round() as usize)
+    }
+}
+
+pub(crate) struct PendingUpdate {
+    pub code: String,
+    pub at: usize,
+}
+
+impl<const N: usize> Engine<N> {
+    pub fn set_quantize(&mut self, quantize: Quantize) {
+        self.quantize = quantize;
+    }
+
+    /// Whether there is queued code that hasn't been applied yet.
+    pub fn is_update_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// The clock (in samples) at which the queued code will be applied.
+    pub fn pending_update_at(&self) -> Option<usize> {
+        self.pending.as_ref().map(|pending| pending.at)
+    }
+
+    /// Drop the queued code, if any, and return it.
+    pub fn cancel_pending_update(&mut self) -> Option<String> {
+        self.pending.take().map(|pending| pending.code)
+    }
+
+    /// Queued code is only checked for syntax when it is queued, so anything else that is wrong
+    /// with it shows up when it is applied. In that case the graph is left as it was and the error
+    /// is kept here.
+    pub fn take_update_error(&mut self) -> Option<EngineError> {
+        self.update_error.take()
+    }
+
+    // called at the start of each block
+    pub(crate) fn apply_pending_update(&mut self) {
+        if self
+            .pending
+            .as_ref()
+            .is_some_and(|pending| self.clock >= pending.at)
+        {
+            let pending = self.pending.take().unwrap();
+            self.update_error = self.apply_code(&pending.code).err();
+        }
+    }
+}
@@ -0,0 +1,680 @@
+//! Running the graph on a real-time audio thread.
+//!
+//! [`Engine::split`] turns an engine into a [`Controller`], which parses code, diffs it and builds
+//! the new nodes, and an [`AudioProcessor`], which owns the nodes and renders blocks. The
+//! controller hands over what it built as batches of graph edits through a lock-free queue, and
+//! the processor sends the nodes it removes back through another one, so nothing is parsed, built
+//! or dropped on the audio thread and neither side ever waits for the other.
+//!
+//! The controller keeps its own copy of the graph in which every node is a placeholder, and makes
+//! the same structural changes to it that the processor makes to the real one, so that both hand
+//! out the same `NodeIndex`es.
+//!
+//! A block in which nothing changes doesn't allocate. Structural edits may allocate on the block
+//! where they land, though: the graph has room for the `max_nodes` and `max_edges` of its
+//! [`AudioContextConfig`] and the scheduled messages for as many as fit in the command queue, but
+//! a node that gets connected stores where its inputs come from, and working out the order of a
+//! changed graph sets up the inputs of every node again. Messages that reach nodes, such as a new
+//! pattern, can allocate inside the node as well.
+
+use crate::{
+    control::Param,
+    schedule::{self, Time},
+    Engine, EngineError, MsgError, Profile, Quantize,
+};
+use glicol_synth::{
+    mix_into, AudioContext, AudioContextConfig, BoxedNodeSend, Buffer, Clock, GlicolNodeData,
+    GlicolPara, Input, Message, Node, NodeData, ParamInfo, Ramp, Smoothing,
+};
+use hashbrown::HashMap;
+use petgraph::graph::NodeIndex;
+use rtrb::{Consumer, Producer, PushError, RingBuffer};
+use std::collections::VecDeque;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+const COMMAND_QUEUE_SIZE: usize = 256;
+const GARBAGE_QUEUE_SIZE: usize = 1024;
+
+/// A change to the graph, made by the controller to its copy and replayed by the processor.
+pub(crate) enum Edit<const N: usize> {
+    /// The node, and the index it got in the controller's copy.
+    AddNode(NodeIndex, GlicolNodeData<N>),
+    RemoveNode(NodeIndex),
+    Msg(NodeIndex, Message),
+    MsgAll(Message),
+    ClearEdges,
+    Connect(NodeIndex, NodeIndex),
+    ConnectWithOrder(NodeIndex, NodeIndex, usize),
+    /// A message for a node at a clock time. It is dropped if the node is removed before then.
+    Schedule(usize, NodeIndex, Message),
+    ClearScheduled,
+    Profiling(Option<Clock>),
+    SampleRate(usize),
+    Reset,
+}
+
+/// The edits of one call on the controller, applied together at the start of the first block that
+/// begins at or after `at`.
+pub(crate) struct Batch<const N: usize> {
+    at: usize,
+    edits: Vec<Edit<N>>,
+}
+
+/// Whatever the processor is done with, to be dropped on the control thread.
+#[allow(dead_code)] // the fields are only there to be dropped
+enum Garbage<const N: usize> {
+    Node(GlicolNodeData<N>),
+    Edits(Vec<Edit<N>>),
+    Msg(Message),
+}
+
+// Stands in for a node that lives on the audio thread, with its parameters and the channel count
+// of its inputs.
+struct Placeholder(&'static [ParamInfo], Option<usize>);
+
+impl<const N: usize> Node<N> for Placeholder {
+    fn process(&mut self, _inputs: &mut HashMap<usize, Input<N>>, _output: &mut [Buffer<N>]) {}
+    fn send_msg(&mut self, _info: Message) {}
+    fn params(&self) -> &'static [ParamInfo] {
+        self.0
+    }
+    fn input_channels(&self) -> Option<usize> {
+        self.1
+    }
+}
+
+// The buffers are only there to tell how many channels the node has.
+fn placeholder<const N: usize>(data: &GlicolNodeData<N>) -> GlicolNodeData<N> {
+    let node = Placeholder(data.node.params(), data.node.input_channels());
+    let buffers = vec![Buffer::SILENT; data.buffers.len()];
+    NodeData::new(BoxedNodeSend::new(node), buffers)
+}
+
+// Everything that changes the graph goes through these, so that it can be recorded once the
+// engine has been split.
+impl<const N: usize> Engine<N> {
+    pub(crate) fn add_node(&mut self, data: GlicolNodeData<N>) -> NodeIndex {
+        let Some(edits) = &mut self.edits else {
+            return self.context.graph.add_node(data);
+        };
+        let index = self.context.graph.add_node(placeholder(&data));
+        edits.push(Edit::AddNode(index, data));
+        index
+    }
+
+    pub(crate) fn remove_node(&mut self, index: NodeIndex) {
+        self.context.remove_node(index);
+        self.push_edit(Edit::RemoveNode(index));
+        self.free_unsplit_samples();
+    }
+
+    pub(crate) fn send_to_node(&mut self, index: NodeIndex, msg: Message) {
+        match &mut self.edits {
+            Some(edits) => edits.push(Edit::Msg(index, msg)),
+            None => self.context.graph[index].node.send_msg(msg),
+        }
+    }
+
+    pub(crate) fn send_to_all(&mut self, msg: Message) {
+        match &mut self.edits {
+            Some(edits) => edits.push(Edit::MsgAll(msg)),
+            None => self.context.send_msg_to_all(msg),
+        }
+    }
+
+    pub(crate) fn clear_edges(&mut self) {
+        self.context.clear_edges();
+        self.push_edit(Edit::ClearEdges);
+    }
+
+    pub(crate) fn connect(&mut self, from: NodeIndex, to: NodeIndex) {
+        self.context.connect(from, to);
+        self.push_edit(Edit::Connect(from, to));
+    }
+
+    pub(crate) fn connect_with_order(&mut self, from: NodeIndex, to: NodeIndex, order: usize) {
+        self.context.connect_with_order(from, to, order);
+        self.push_edit(Edit::ConnectWithOrder(from, to, order));
+    }
+
+    pub(crate) fn reset_graph(&mut self) {
+        self.context.reset();
+        self.push_edit(Edit::Reset);
+        self.free_unsplit_samples();
+    }
+
+    // The node is gone already unless the engine is split, in which case the controller frees the
+    // samples once the processor hands the node back.
+    fn free_unsplit_samples(&mut self) {
+        if self.edits.is_none() {
+            self.free_samples();
+        }
+    }
+
+    pub(crate) fn push_edit(&mut self, edit: Edit<N>) {
+        if let Some(edits) = &mut self.edits {
+            edits.push(edit);
+        }
+    }
+
+    /// Move the graph to an [`AudioProcessor`] that can run on the audio thread, and keep
+    /// everything else in a [`Controller`] for the control thread.
+    ///
+    /// ```
+    /// # use glicol::Engine;
+    /// let (mut controller, mut processor) = Engine::<128>::new().split();
+    /// controller.update_with_code("o: sin 440").unwrap();
+    /// let audio = std::thread::spawn(move || {
+    ///     for _ in 0..100 {
+    ///         processor.next_block(&[]);
+    ///     }
+    /// });
+    /// controller.send_msg("o,0,freq,220").unwrap();
+    /// audio.join().unwrap();
+    /// ```
+    pub fn split(mut self) -> (Controller<N>, AudioProcessor<N>) {
+        // the controller only needs the layout, its nodes are all placeholders
+        let mut context = AudioContext::new(AudioContextConfig {
+            sr: self.sr,
+            channels: self.context.channels(),
+            ..Default::default()
+        });
+        // `map` keeps the indices, including the free ones
+        context.graph = self
+            .context
+            .graph
+            .map(|_, data| placeholder(data), |_, _| ());
+        context.destination = self.context.destination;
+        context.input = self.context.input;
+        let context = std::mem::replace(&mut self.context, context);
+        self.edits = Some(vec![]);
+
+        let (commands, command_rx) = RingBuffer::new(COMMAND_QUEUE_SIZE);
+        let (garbage_tx, garbage) = RingBuffer::new(GARBAGE_QUEUE_SIZE);
+        let clock = Arc::new(AtomicUsize::new(self.clock));
+        let processor = AudioProcessor {
+            context,
+            input: self.index_info.get("~input").map(|chain| chain[0]),
+            clock: self.clock,
+            shared_clock: clock.clone(),
+            commands: command_rx,
+            garbage: garbage_tx,
+            overflow: VecDeque::with_capacity(GARBAGE_QUEUE_SIZE),
+            waiting: None,
+            scheduled: Vec::with_capacity(COMMAND_QUEUE_SIZE),
+        };
+
+        // what is waiting in the engine is handed over like anything sent later
+        let pending = self.pending.take();
+        for scheduled in std::mem::take(&mut self.scheduled) {
+            // one whose node has gone since would have been dropped when it was due
+            _ = self.schedule_msg(
+                Time::Samples(scheduled.at),
+                &scheduled.chain,
+                scheduled.pos,
+                scheduled.msg,
+            );
+        }
+        let mut controller = Controller {
+            engine: self,
+            commands,
+            garbage,
+            clock,
+            backlog: VecDeque::new(),
+        };
+        controller.flush();
+        if let Some(pending) = pending {
+            let at = pending.at.max(controller.engine.clock);
+            controller.engine.clock = at;
+            controller.engine.update_error = controller.engine.apply_code(&pending.code).err();
+            controller.flush();
+        }
+        (controller, processor)
+    }
+}
+
+/// The control side of a split [`Engine`], see [`Engine::split`].
+///
+/// It has the same methods as the engine, apart from rendering, with a few differences that come
+/// from only finding out what the audio thread is up to after the fact:
+///
+/// - Quantized code is checked and built straight away, so errors are returned by
+///   `update_with_code`, and it can't be cancelled. Any change made while it is waiting waits for
+///   it too, and later updates are queued after it instead of replacing it. That includes
+///   `set_sr`, so the boundary is kept in samples at the old rate.
+/// - A crossfade keeps running on the old nodes until the next call on the controller after it
+///   is over. Call [`Controller::collect_garbage`] from time to time to free them sooner.
+/// - Scheduled messages go to the node at the position they were scheduled for, and are dropped
+///   if a code update removes that node.
+/// - `reset` leaves the clock running.
+pub struct Controller<const N: usize> {
+    engine: Engine<N>,
+    commands: Producer<Batch<N>>,
+    garbage: Consumer<Garbage<N>>,
+    // the processor's clock at the start of its next block
+    clock: Arc<AtomicUsize>,
+    // batches that didn't fit in the queue yet
+    backlog: VecDeque<Batch<N>>,
+}
+
+impl<const N: usize> Controller<N> {
+    // Drop what the processor is done with, finish a crossfade that is over, and catch the clock
+    // up. Called at the start of every call.
+    fn sync(&mut self) {
+        while let Ok(garbage) = self.garbage.pop() {
+            drop(garbage);
+        }
+        self.engine.free_samples();
+        while let Some(batch) = self.backlog.pop_front() {
+            if let Err(PushError::Full(batch)) = self.commands.push(batch) {
+                self.backlog.push_front(batch);
+                break;
+            }
+        }
+        let now = self.clock.load(Ordering::Acquire);
+        // ahead of `now` while a quantized update is waiting
+        self.engine.clock = self.engine.clock.max(now);
+        if self
+            .engine
+            .fade
+            .as_ref()
+            .is_some_and(|fade| now >= fade.until)
+        {
+            self.engine.finish_fade();
+            self.engine.connect_graph();
+            self.flush();
+        }
+    }
+
+    // Send what the last call changed.
+    fn flush(&mut self) {
+        let Some(edits) = self.engine.edits.as_mut().filter(|edits| !edits.is_empty()) else {
+            return;
+        };
+        let batch = Batch {
+            at: self.engine.clock,
+            edits: std::mem::take(edits),
+        };
+        if !self.backlog.is_empty() {
+            self.backlog.push_back(batch);
+        } else if let Err(PushError::Full(batch)) = self.commands.push(batch) {
+            self.backlog.push_back(batch);
+        }
+    }
+
+    fn with_engine<T>(&mut self, f: impl FnOnce(&mut Engine<N>) -> T) -> T {
+        self.sync();
+        let result = f(&mut self.engine);
+        self.flush();
+        result
+    }
+
+    /// Free the nodes the processor has removed, and end a crossfade that is over.
+    pub fn collect_garbage(&mut self) {
+        self.with_engine(|_| ())
+    }
+
+    /// See [`Engine::update_with_code`].
+    pub fn update_with_code(&mut self, code: &str) -> Result<(), EngineError> {
+        self.with_engine(|engine| {
+            let now = engine.clock;
+            if let Some(at) = engine.quantize.next_boundary(now, engine.sr, engine.bpm) {
+                engine.clock = at;
+            }
+            engine.apply_code(code).inspect_err(|_| engine.clock = now)
+        })
+    }
+
+    /// Whether quantized code is waiting for its boundary.
+    pub fn is_update_pending(&self) -> bool {
+        self.pending_update_at().is_some()
+    }
+
+    /// When the last quantized code will be applied.
+    pub fn pending_update_at(&self) -> Option<usize> {
+        (self.engine.clock > self.clock.load(Ordering::Acquire)).then_some(self.engine.clock)
+    }
+
+    /// Errors in code that was queued before the engine was split, see
+    /// [`Engine::take_update_error`].
+    pub fn take_update_error(&mut self) -> Option<EngineError> {
+        self.engine.take_update_error()
+    }
+
+    pub fn set_param(
+        &mut self,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        value: GlicolPara<String>,
+    ) -> Result<(), MsgError> {
+        self.with_engine(|engine| engine.set_param(chain, pos, param, value))
+    }
+
+    pub fn set_param_smoothing(
+        &mut self,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        smoothing: Smoothing,
+    ) -> Result<(), MsgError> {
+        self.with_engine(|engine| engine.set_param_smoothing(chain, pos, param, smoothing))
+    }
+
+    pub fn send_msg(&mut self, msg: &str) -> Result<(), MsgError> {
+        self.with_engine(|engine| engine.send_msg(msg))
+    }
+
+    pub fn node_params(&self, chain: &str, pos: usize) -> Result<&'static [ParamInfo], MsgError> {
+        self.engine.node_params(chain, pos)
+    }
+
+    pub fn schedule(
+        &mut self,
+        at: Time,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        value: GlicolPara<String>,
+    ) -> Result<(), MsgError> {
+        self.with_engine(|engine| engine.schedule(at, chain, pos, param, value))
+    }
+
+    pub fn schedule_ramp(
+        &mut self,
+        at: Time,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        target: f32,
+        ramp: Ramp,
+    ) -> Result<(), MsgError> {
+        self.with_engine(|engine| engine.schedule_ramp(at, chain, pos, param, target, ramp))
+    }
+
+    pub fn schedule_msg(
+        &mut self,
+        at: Time,
+        chain: &str,
+        pos: usize,
+        msg: Message,
+    ) -> Result<(), MsgError> {
+        self.with_engine(|engine| engine.schedule_msg(at, chain, pos, msg))
+    }
+
+    pub fn clear_scheduled(&mut self) {
+        self.with_engine(|engine| engine.clear_scheduled())
+    }
+
+    /// See [`Engine::add_sample`].
+    #[cfg(feature = "use-samples")]
+    pub fn add_sample(
+        &mut self,
+        name: &str,
+        sample: impl Into<Arc<[f32]>>,
+        channels: usize,
+        sr: usize,
+    ) {
+        self.with_engine(|engine| engine.add_sample(name, sample, channels, sr))
+    }
+
+    /// See [`Engine::insert_sample`].
+    #[cfg(feature = "use-samples")]
+    pub fn insert_sample(&mut self, name: &str, sample: glicol_synth::Sample) {
+        self.with_engine(|engine| engine.insert_sample(name, sample))
+    }
+
+    /// See [`Engine::set_interpolation`].
+    #[cfg(feature = "use-samples")]
+    pub fn set_interpolation(
+        &mut self,
+        name: &str,
+        interpolation: glicol_synth::Interpolation,
+    ) -> bool {
+        self.with_engine(|engine| engine.set_interpolation(name, interpolation))
+    }
+
+    /// See [`Engine::set_choke_group`].
+    #[cfg(feature = "use-samples")]
+    pub fn set_choke_group(&mut self, name: &str, group: Option<u8>) -> bool {
+        self.with_engine(|engine| engine.set_choke_group(name, group))
+    }
+
+    /// See [`Engine::set_max_voices`].
+    #[cfg(feature = "use-samples")]
+    pub fn set_max_voices(&mut self, name: &str, max_voices: usize) -> bool {
+        self.with_engine(|engine| engine.set_max_voices(name, max_voices))
+    }
+
+    /// See [`Engine::set_resample_samples`].
+    #[cfg(feature = "use-samples")]
+    pub fn set_resample_samples(&mut self, resample: bool) {
+        self.engine.set_resample_samples(resample)
+    }
+
+    /// See [`Engine::remove_sample`].
+    #[cfg(feature = "use-samples")]
+    pub fn remove_sample(&mut self, name: &str) -> bool {
+        self.with_engine(|engine| engine.remove_sample(name))
+    }
+
+    /// See [`Engine::load_sample`].
+    #[cfg(feature = "load-samples")]
+    pub fn load_sample(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<String, crate::LoadError> {
+        self.with_engine(|engine| engine.load_sample(path))
+    }
+
+    /// See [`Engine::load_samples`].
+    #[cfg(feature = "load-samples")]
+    pub fn load_samples(
+        &mut self,
+        dir: impl AsRef<std::path::Path>,
+    ) -> Result<Vec<String>, crate::LoadError> {
+        self.with_engine(|engine| engine.load_samples(dir))
+    }
+
+    pub fn set_bpm(&mut self, bpm: f32) {
+        self.with_engine(|engine| engine.set_bpm(bpm))
+    }
+
+    pub fn set_quantize(&mut self, quantize: Quantize) {
+        self.engine.set_quantize(quantize)
+    }
+
+    pub fn set_crossfade(&mut self, ms: f32) {
+        self.engine.set_crossfade(ms)
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: Smoothing) {
+        self.with_engine(|engine| engine.set_smoothing(smoothing))
+    }
+
+    /// See [`Engine::set_profiling`]. The times are kept by the processor, see
+    /// [`AudioProcessor::take_profile`].
+    pub fn set_profiling(&mut self, clock: Option<Clock>) {
+        self.with_engine(|engine| engine.set_profiling(clock))
+    }
+
+    /// Put chain names to a profile from [`AudioProcessor::take_profile`], as in
+    /// [`Engine::take_profile`].
+    pub fn name_profile(&self, profile: glicol_synth::Profile<NodeIndex>) -> Profile {
+        self.engine.name_profile(profile)
+    }
+
+    pub fn set_sr(&mut self, sr: usize) {
+        self.with_engine(|engine| engine.set_sr(sr))
+    }
+
+    pub fn set_seed(&mut self, seed: usize) {
+        self.engine.set_seed(seed)
+    }
+
+    pub fn set_track_amp(&mut self, amp: f32) {
+        self.engine.set_track_amp(amp)
+    }
+
+    pub fn set_livecoding(&mut self, livecoding: bool) {
+        self.engine.livecoding = livecoding
+    }
+
+    pub fn reset(&mut self) {
+        self.with_engine(|engine| {
+            let clock = engine.clock;
+            engine.reset();
+            engine.clock = clock;
+        })
+    }
+}
+
+/// The audio side of a split [`Engine`], see [`Engine::split`].
+///
+/// [`AudioProcessor::next_block`] never blocks: it applies whatever the controller has sent so
+/// far and renders the block. If the controller falls so far behind that the nodes and messages
+/// the processor is done with don't fit in the queue back, it keeps them and holds on to new edits
+/// until the controller has caught up.
+pub struct AudioProcessor<const N: usize> {
+    context: AudioContext<N>,
+    // the `~input` chain
+    input: Option<NodeIndex>,
+    clock: usize,
+    shared_clock: Arc<AtomicUsize>,
+    commands: Consumer<Batch<N>>,
+    garbage: Producer<Garbage<N>>,
+    // garbage that didn't fit in the queue, handed over on a later block
+    overflow: VecDeque<Garbage<N>>,
+    // a batch that has arrived before its time
+    waiting: Option<Batch<N>>,
+    // (clock, node, message), in order
+    scheduled: Vec<(usize, NodeIndex, Message)>,
+}
+
+impl<const N: usize> AudioProcessor<N> {
+    /// The number of samples rendered so far.
+    pub fn clock(&self) -> usize {
+        self.clock
+    }
+
+    /// The time spent on each node since the last call, for [`Controller::name_profile`]. It
+    /// allocates, so call it now and then rather than on every block.
+    pub fn take_profile(&mut self) -> glicol_synth::Profile<NodeIndex> {
+        self.context.take_profile()
+    }
+
+    /// Render the next block. `buf` goes into the `~input` chain, one slice per channel, mixed to
+    /// the engine's channels like [`Engine::next_block`] does.
+    pub fn next_block(&mut self, buf: &[&[f32]]) -> &[Buffer<N>] {
+        while let Some(garbage) = self.overflow.pop_front() {
+            if let Err(PushError::Full(garbage)) = self.garbage.push(garbage) {
+                self.overflow.push_front(garbage);
+                break;
+            }
+        }
+        // no new edits until the controller has caught up with what is left over
+        while self.overflow.is_empty() {
+            let Some(batch) = self.waiting.take().or_else(|| self.commands.pop().ok()) else {
+                break;
+            };
+            if batch.at > self.clock {
+                self.waiting = Some(batch);
+                break;
+            }
+            self.apply(batch);
+        }
+
+        let due = self
+            .scheduled
+            .partition_point(|(at, ..)| *at < self.clock + N);
+        for (at, index, msg) in self.scheduled.drain(..due) {
+            let offset = at.saturating_sub(self.clock);
+            self.context.graph[index].node.send_msg_at(offset, msg);
+        }
+
+        if let (Some(input), false) = (self.input, buf.is_empty()) {
+            let buffers = &mut self.context.graph[input].buffers;
+            for buffer in buffers.iter_mut() {
+                buffer.silence();
+            }
+            mix_into(buf, buffers);
+        }
+        self.context.next_block();
+        self.clock += N;
+        self.shared_clock.store(self.clock, Ordering::Release);
+        &self.context.graph[self.context.destination].buffers
+    }
+
+    fn apply(&mut self, mut batch: Batch<N>) {
+        for edit in batch.edits.drain(..) {
+            match edit {
+                Edit::AddNode(index, data) => {
+                    let added = self.context.graph.add_node(data);
+                    debug_assert_eq!(added, index, "the controller's graph is out of sync");
+                }
+                Edit::RemoveNode(index) => self.remove_node(index),
+                Edit::Msg(index, msg) => {
+                    if let Some(data) = self.context.graph.node_weight_mut(index) {
+                        data.node.send_msg(msg);
+                    }
+                }
+                Edit::MsgAll(msg) => self.context.send_msg_to_all(msg),
+                Edit::ClearEdges => self.context.clear_edges(),
+                Edit::Connect(from, to) => _ = self.context.connect(from, to),
+                Edit::ConnectWithOrder(from, to, order) => {
+                    _ = self.context.connect_with_order(from, to, order)
+                }
+                Edit::Schedule(at, index, msg) => {
+                    let i = self.scheduled.partition_point(|(t, ..)| *t <= at);
+                    self.scheduled.insert(i, (at, index, msg));
+                }
+                Edit::ClearScheduled => {
+                    while let Some((.., msg)) = self.scheduled.pop() {
+                        self.throw_away(Garbage::Msg(msg));
+                    }
+                }
+                Edit::Profiling(clock) => self.context.set_profiling(clock),
+                Edit::SampleRate(sr) => {
+                    let (from, clock) = (self.context.sr(), self.clock);
+                    for (at, ..) in &mut self.scheduled {
+                        *at = schedule::retime(*at, clock, from, sr);
+                    }
+                    self.context.set_sr(sr);
+                }
+                Edit::Reset => {
+                    while let Some(index) = self.context.graph.node_indices().next() {
+                        self.remove_node(index);
+                    }
+                    self.context.reset();
+                    self.input = None;
+                }
+            }
+        }
+        self.throw_away(Garbage::Edits(batch.edits));
+    }
+
+    fn remove_node(&mut self, index: NodeIndex) {
+        while let Some(i) = self
+            .scheduled
+            .iter()
+            .position(|(_, node, _)| *node == index)
+        {
+            let (.., msg) = self.scheduled.remove(i);
+            self.throw_away(Garbage::Msg(msg));
+        }
+        if let Some(data) = self.context.remove_node(index) {
+            self.throw_away(Garbage::Node(data));
+        }
+    }
+
+    // What doesn't fit in the queue waits in `overflow`, which only grows if a single batch leaves
+    // more garbage than both can hold.
+    fn throw_away(&mut self, garbage: Garbage<N>) {
+        if let Err(PushError::Full(garbage)) = self.garbage.push(garbage) {
+            self.overflow.push_back(garbage);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Offline (faster than real-time) rendering of Glicol code.
+//!
+//! The `Engine` is normally driven by an audio callback that keeps asking for the `next_block`.
+//! For bouncing a sketch to disk, or for rendering in CI, we drive the same `next_block` loop
+//! ourselves and collect the result into a [`RenderedAudio`], which can then be written out as a
+//! WAV file or kept in memory as PCM. Long sets render faster with [`Engine::set_threads`].
+
+use crate::{Engine, EngineError};
+use glicol_synth::{mix_into, Buffer};
+use hashbrown::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// How much audio to render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderLength {
+    Seconds(f32),
+    /// One bar is four beats at the engine bpm, the same as a cycle in the patterns.
+    Bars(f32),
+    Frames(usize),
+}
+
+impl RenderLength {
+    pub fn to_frames(self, sr: usize, bpm: f32) -> usize {
+        match self {
+            Self::Seconds(s) => (s as f64 * sr as f64).round() as usize,
+            Self::Bars(b) => (b as f64 * 240. / bpm as f64 * sr as f64).round() as usize,
+            Self::Frames(n) => n,
+        }
+    }
+}
+
+/// The sample format used when encoding to WAV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitDepth {
+    Int16,
+    #[default]
+    Int24,
+    Float32,
+}
+
+impl BitDepth {
+    pub fn bits(self) -> u16 {
+        match self {
+            Self::Int16 => 16,
+            Self::Int24 => 24,
+            Self::Float32 => 32,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        self == Self::Float32
+    }
+}
+
+/// Options for encoding a [`RenderedAudio`] into a WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WavSpec {
+    pub bit_depth: BitDepth,
+    /// Add TPDF dither before quantising. Only has an effect on the integer formats.
+    pub dither: bool,
+}
+
+impl WavSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bit_depth(self, bit_depth: BitDepth) -> Self {
+        Self { bit_depth, ..self }
+    }
+
+    pub fn dither(self, dither: bool) -> Self {
+        Self { dither, ..self }
+    }
+}
+
+/// De-interleaved audio produced by [`Engine::render`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedAudio {
+    pub sr: usize,
+    /// One `Vec` per channel, all with the same length.
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl RenderedAudio {
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    pub fn interleaved(&self) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.frames() * self.channels.len());
+        for i in 0..self.frames() {
+            out.extend(self.channels.iter().map(|chan| chan[i]));
+        }
+        out
+    }
+
+    /// Quantise to the given integer bit depth and return the interleaved samples.
+    ///
+    /// # Panics
+    /// Panics if `spec.bit_depth` is `BitDepth::Float32`; use [`Self::interleaved`] for that.
+    pub fn to_pcm_i32(&self, spec: &WavSpec) -> Vec<i32> {
+        assert!(
+            !spec.bit_depth.is_float(),
+            "to_pcm_i32 only supports integer bit depths"
+        );
+        let max = ((1_i64 << (spec.bit_depth.bits() - 1)) - 1) as f32;
+        let mut dither = spec.dither.then(TpdfDither::new);
+        self.interleaved()
+            .into_iter()
+            .map(|s| {
+                let noise = dither.as_mut().map_or(0.0, TpdfDither::next);
+                (s * max + noise).round().clamp(-max - 1., max) as i32
+            })
+            .collect()
+    }
+
+    /// Encode the whole buffer as a RIFF/WAVE file in memory. Fails if it is too long for the
+    /// 32-bit sizes of the format, about 4 GiB of samples.
+    pub fn to_wav_bytes(&self, spec: &WavSpec) -> std::io::Result<Vec<u8>> {
+        let channels = self.channels.len() as u16;
+        let bytes_per_sample = spec.bit_depth.bits() / 8;
+        let block_align = channels * bytes_per_sample;
+        let float = spec.bit_depth.is_float();
+        // float wav needs the extended fmt chunk (cbSize = 0) and a fact chunk
+        let fmt_len: u32 = if float { 18 } else { 16 };
+        let fact_len: u32 = if float { 12 } else { 0 };
+        let too_long = || std::io::Error::new(std::io::ErrorKind::InvalidInput, "too long for WAV");
+        let data_len = self
+            .frames()
+            .checked_mul(block_align as usize)
+            .and_then(|len| u32::try_from(len).ok())
+            .ok_or_else(too_long)?;
+        let riff_len = data_len
+            .checked_add(4 + 8 + fmt_len + fact_len + 8)
+            .ok_or_else(too_long)?;
+
+        let mut out = Vec::with_capacity(44 + data_len as usize);
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&riff_len.to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&fmt_len.to_le_bytes());
+        out.extend_from_slice(&(if float { 3_u16 } else { 1_u16 }).to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&(self.sr as u32).to_le_bytes());
+        out.extend_from_slice(&(self.sr as u32 * block_align as u32).to_le_bytes());
+        out.extend_from_slice(&block_align.to_le_bytes());
+        out.extend_from_slice(&spec.bit_depth.bits().to_le_bytes());
+        if float {
+            out.extend_from_slice(&0_u16.to_le_bytes());
+            out.extend_from_slice(b"fact");
+            out.extend_from_slice(&4_u32.to_le_bytes());
+            out.extend_from_slice(&(self.frames() as u32).to_le_bytes());
+        }
+
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        match spec.bit_depth {
+            BitDepth::Float32 => {
+                for s in self.interleaved() {
+                    out.extend_from_slice(&s.to_le_bytes());
+                }
+            }
+            BitDepth::Int16 => {
+                for s in self.to_pcm_i32(spec) {
+                    out.extend_from_slice(&(s as i16).to_le_bytes());
+                }
+            }
+            BitDepth::Int24 => {
+                for s in self.to_pcm_i32(spec) {
+                    out.extend_from_slice(&s.to_le_bytes()[..3]);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn write_wav(&self, path: impl AsRef<Path>, spec: &WavSpec) -> std::io::Result<()> {
+        let wav = self.to_wav_bytes(spec)?;
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        file.write_all(&wav)?;
+        file.flush()
+    }
+}
+
+/// The master mix together with one stem per chain, from [`Engine::render_stems`].
+///
+/// Every stem has the same length and channel count as the master. A chain is up- or downmixed to
+/// that channel count, just like the destination does when mixing, so summing the stems of the
+/// output chains (those without a `~`) gives back the master. `~` chains are included as well, but
+/// they only reach the mix through the chains that reference them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedStems {
+    pub master: RenderedAudio,
+    pub stems: HashMap<String, RenderedAudio>,
+}
+
+impl RenderedStems {
+    /// Write `master.wav` and one `stem-<chain>.wav` per chain (with the `~` dropped) into `dir`.
+    pub fn write_wavs(&self, dir: impl AsRef<Path>, spec: &WavSpec) -> std::io::Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        self.master.write_wav(dir.join("master.wav"), spec)?;
+        for (name, stem) in &self.stems {
+            stem.write_wav(
+                dir.join(format!("stem-{}.wav", name.replace('~', ""))),
+                spec,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Triangular dither of +-1 LSB, made from two uniform random numbers.
+/// A fixed seed keeps renders reproducible.
+struct TpdfDither {
+    state: u32,
+}
+
+impl TpdfDither {
+    fn new() -> Self {
+        Self { state: 0x9E37_79B9 }
+    }
+
+    // xorshift32, returning a value in [0, 1)
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn next(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+}
+
+impl<const N: usize> Engine<N> {
+    /// Update the engine with `code` and render `length` of audio from it.
+    ///
+    /// The engine renders from whatever state it is currently in, so for a deterministic bounce
+    /// use a fresh `Engine` (with `set_sr`, `set_bpm` and samples set up before calling this).
+    pub fn render(
+        &mut self,
+        code: &str,
+        length: RenderLength,
+    ) -> Result<RenderedAudio, EngineError> {
+        self.update_with_code(code)?;
+        Ok(self.render_frames(length.to_frames(self.sr, self.bpm)))
+    }
+
+    /// Keep pulling `next_block` until `frames` frames have been collected.
+    /// The last block is truncated so the result is exactly `frames` long.
+    pub fn render_frames(&mut self, frames: usize) -> RenderedAudio {
+        let mut channels: Vec<Vec<f32>> = vec![];
+        let mut remaining = frames;
+        while remaining > 0 {
+            let block = self.next_block(&[]);
+            if channels.is_empty() {
+                channels = vec![Vec::with_capacity(frames); block.len()];
+            }
+            let n = remaining.min(N);
+            for (chan, buf) in channels.iter_mut().zip(block) {
+                chan.extend_from_slice(&buf[..n]);
+            }
+            remaining -= n;
+        }
+        RenderedAudio {
+            sr: self.sr,
+            channels,
+        }
+    }
+
+    /// Like [`Self::render`], but also records the output of the last node of every chain.
+    pub fn render_stems(
+        &mut self,
+        code: &str,
+        length: RenderLength,
+    ) -> Result<RenderedStems, EngineError> {
+        self.update_with_code(code)?;
+        Ok(self.render_stems_frames(length.to_frames(self.sr, self.bpm)))
+    }
+
+    pub fn render_stems_frames(&mut self, frames: usize) -> RenderedStems {
+        let taps: Vec<_> = match &self.ast {
+            Some(ast) => ast
+                .get()
+                .ast
+                .nodes
+                .keys()
+                .filter_map(|name| {
+                    let last = self.index_info.get(*name)?.last()?;
+                    Some((name.to_string(), *last))
+                })
+                .collect(),
+            None => vec![],
+        };
+        let mut master: Vec<Vec<f32>> = vec![];
+        let mut stems: Vec<Vec<Vec<f32>>> = vec![];
+        let mut mixed: Vec<Buffer<N>> = vec![];
+        let mut remaining = frames;
+        while remaining > 0 {
+            let chans = self.next_block(&[]).len();
+            if master.is_empty() {
+                master = vec![Vec::with_capacity(frames); chans];
+                stems = vec![master.clone(); taps.len()];
+                mixed = vec![Buffer::SILENT; chans];
+            }
+            let n = remaining.min(N);
+            let graph = &self.context.graph;
+            for (chan, buf) in master
+                .iter_mut()
+                .zip(&graph[self.context.destination].buffers)
+            {
+                chan.extend_from_slice(&buf[..n]);
+            }
+            for ((_, index), stem) in taps.iter().zip(stems.iter_mut()) {
+                // mixed the same way as the destination mixes it
+                for buf in mixed.iter_mut() {
+                    buf.silence();
+                }
+                mix_into(&graph[*index].buffers, &mut mixed);
+                for (chan, buf) in stem.iter_mut().zip(&mixed) {
+                    chan.extend_from_slice(&buf[..n]);
+                }
+            }
+            remaining -= n;
+        }
+        let sr = self.sr;
+        RenderedStems {
+            master: RenderedAudio {
+                sr,
+                channels: master,
+            },
+            stems: taps
+                .into_iter()
+                .zip(stems)
+                .map(|((name, _), channels)| (name, RenderedAudio { sr, channels }))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Messages that are sent to a node at a given sample.
+//!
+//! Scheduled messages are kept in the engine until the block that contains their time, and are
+//! then handed to the node with `Node::send_msg_at`, together with their offset into the block.
+//! Parameters that are [`glicol_synth::Automation`]s change at exactly that sample; other nodes
+//! get the message at the start of the block.
+
+use crate::{control::Param, realtime::Edit, Engine, MsgError};
+use glicol_synth::{GlicolPara, Message, Node as _, Ramp};
+
+/// A point in time, counted from the start (`clock == 0`).
+///
+/// Beats and bars are turned into samples at the bpm the engine has when the message is
+/// scheduled, with four beats to a bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Time {
+    Samples(usize),
+    Beats(f64),
+    Bars(f64),
+}
+
+impl Time {
+    pub fn to_samples(self, sr: usize, bpm: f32) -> usize {
+        let beat = 60. / bpm as f64 * sr as f64;
+        match self {
+            Self::Samples(n) => n,
+            Self::Beats(beats) => (beats * beat).round() as usize,
+            Self::Bars(bars) => (bars * 4. * beat).round() as usize,
+        }
+    }
+}
+
+// A time `at` that was counted at `from` Hz, moved so that it is still as far from `clock` in
+// time at `to` Hz. Times that have already passed are left as they are.
+pub(crate) fn retime(at: usize, clock: usize, from: usize, to: usize) -> usize {
+    let left = at.saturating_sub(clock) as f64;
+    at.min(clock) + (left * to as f64 / from as f64).round() as usize
+}
+
+// Nodes are looked up when the message is due rather than when it is scheduled, so that a code
+// update in between can't send it to a node that has taken over the old one's index.
+pub(crate) struct Scheduled {
+    pub at: usize,
+    pub chain: String,
+    pub pos: usize,
+    pub msg: Message,
+}
+
+impl<const N: usize> Engine<N> {
+    /// Like [`Engine::set_param`], but at `at`. A time that has already passed is sent with the
+    /// next block.
+    pub fn schedule(
+        &mut self,
+        at: Time,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        value: GlicolPara<String>,
+    ) -> Result<(), MsgError> {
+        let (_, param) = self.resolve_param(chain, pos, param)?;
+        let msg = self.param_msg(param, value)?;
+        self.schedule_msg(at, chain, pos, msg)
+    }
+
+    /// Start moving a parameter to `target` at `at`.
+    ///
+    /// ```
+    /// # use glicol::{Engine, schedule::Time};
+    /// # use glicol_synth::Ramp;
+    /// let mut engine = Engine::<32>::new();
+    /// engine.update_with_code("o: sin 440 >> mul 0.0").unwrap();
+    /// // fade in over the first beat
+    /// let ramp = Ramp::Linear(22050);
+    /// engine.schedule_ramp(Time::Samples(0), "o", 1, "value".into(), 0.5, ramp).unwrap();
+    /// ```
+    pub fn schedule_ramp(
+        &mut self,
+        at: Time,
+        chain: &str,
+        pos: usize,
+        param: Param<'_>,
+        target: f32,
+        ramp: Ramp,
+    ) -> Result<(), MsgError> {
+        let (_, param) = self.resolve_param(chain, pos, param)?;
+        self.schedule_msg(at, chain, pos, Message::RampToNumber(param, target, ramp))
+    }
+
+    /// Send `msg` as it is to the `pos`th node in `chain` at `at`. Fails if there is no such node
+    /// now, and the message is dropped if there is none by then.
+    pub fn schedule_msg(
+        &mut self,
+        at: Time,
+        chain: &str,
+        pos: usize,
+        msg: Message,
+    ) -> Result<(), MsgError> {
+        let index = self.node_index(chain, pos)?;
+        let at = at.to_samples(self.sr, self.bpm);
+        if self.edits.is_some() {
+            // a split engine's processor only knows about node indices
+            self.push_edit(Edit::Schedule(at, index, msg));
+            return Ok(());
+        }
+        let i = self.scheduled.partition_point(|s| s.at <= at);
+        self.scheduled.insert(
+            i,
+            Scheduled {
+                at,
+                chain: chain.to_owned(),
+                pos,
+                msg,
+            },
+        );
+        Ok(())
+    }
+
+    /// Drop every scheduled message that hasn't been sent yet.
+    pub fn clear_scheduled(&mut self) {
+        self.scheduled.clear();
+        self.push_edit(Edit::ClearScheduled);
+    }
+
+    // called before each block is processed
+    pub(crate) fn send_scheduled(&mut self) {
+        let due = self.scheduled.partition_point(|s| s.at < self.clock + N);
+        for Scheduled {
+            at,
+            chain,
+            pos,
+            msg,
+        } in self.scheduled.drain(..due)
+        {
+            if let Some(&index) = self.index_info.get(&chain).and_then(|c| c.get(pos)) {
+                let offset = at.saturating_sub(self.clock);
+                self.context.graph[index].node.send_msg_at(offset, msg);
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use glicol_synth::{
     compound::{Bd, Hh, SawSynth, Sn, SquSynth, TriSynth},
-    delay::{DelayMs, DelayN},
+    delay::{Comb, DelayMs, DelayN, Feedback},
     effect::{Balance, Pan, Plate},
     envelope::{Adsr, EnvPerc},
     filter::{AllPassFilterGain, OnePole, ResonantHighPassFilter, ResonantLowPassFilter},
@@ -23,47 +23,82 @@ use glicol_synth::dynamic::Meta;
 use glicol_synth::dynamic::Eval;
 
 #[cfg(feature = "use-samples")]
-use glicol_synth::sampling::{PSampler, Sampler};
+use glicol_synth::{
+    sampling::{LoopMode, PSampler, Position, Sampler},
+    SampleEvent,
+};
 
-use crate::EngineError;
-use glicol_synth::{BoxedNodeSend, NodeData}; //, Processor, Buffer, Input, Node
+use crate::{EngineError, SourceSpan};
+use glicol_synth::{BoxedNodeSend, Message, NodeData, Sample}; //, Processor, Buffer, Input, Node
 use hashbrown::HashMap;
 
 pub type GlicolNodeData<const N: usize> = NodeData<BoxedNodeSend<N>, N>;
 
+/// What [`makenode`] needs from the engine to build a node.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeContext<'a> {
+    pub samples_dict: &'a HashMap<String, Sample>,
+    pub sr: usize,
+    pub channels: usize,
+    pub bpm: f32,
+    pub seed: usize,
+}
+
 #[allow(unused_variables, unused_mut)]
 pub fn makenode<const N: usize>(
     component: &Component<'_>,
-    samples_dict: &HashMap<String, (&'static [f32], usize, usize)>,
-    sr: usize,
-    bpm: f32,
-    seed: usize,
+    component_span: pest::Span<'_>,
+    context: &NodeContext<'_>,
 ) -> Result<(GlicolNodeData<N>, Vec<String>), EngineError> {
-    let (nodedata, reflist) = match component {
+    let NodeContext {
+        samples_dict,
+        sr,
+        channels,
+        bpm,
+        seed,
+    } = *context;
+    let (mut nodedata, reflist) = match component {
         #[cfg(feature = "use-samples")]
         Component::PSampler(psampler) => {
             let mut samples_dict_selected = HashMap::new();
             let (pattern, span) = match psampler {
-                nodes::PSampler::Event(_) => panic!("An event inside PSampler is not yet supported; please file an issue if you encounter this"),
-                nodes::PSampler::Pattern(ref pat) => (&pat.event, pat.span)
+                nodes::PSampler::Event(_) => {
+                    return Err(EngineError::InvalidParameter(
+                        "psampler expects a pattern, an event is not supported yet".to_owned(),
+                        component_span.into(),
+                    ))
+                }
+                nodes::PSampler::Pattern(ref pat) => (&pat.event, pat.span),
             };
 
             let pattern = pattern
                 .val_times
                 .iter()
-                .map(|(val, time)| {
+                .map(|(val, time, options)| {
                     let value = match &val {
                         nodes::EventValue::Number(_) => String::new(),
                         nodes::EventValue::Symbol(sym) => sym.to_string(),
                     };
 
                     if !samples_dict.contains_key(&value) {
-                        return Err(EngineError::NonExistSample(value.clone()));
+                        return Err(EngineError::NonExistSample(
+                            value.clone(),
+                            SourceSpan::of_token(component_span, &value),
+                        ));
                     } else {
-                        samples_dict_selected.insert(value.clone(), samples_dict[&value]);
+                        samples_dict_selected.insert(value.clone(), samples_dict[&value].clone());
+                    }
+                    if options.rate <= 0.0 {
+                        return Err(EngineError::InvalidParameter(
+                            format!("the rate of {value} should be above 0"),
+                            component_span.into(),
+                        ));
                     }
 
-                    Ok((value, *time))
+                    Ok(SampleEvent::new(value, *time)
+                        .rate(options.rate)
+                        .amp(options.amp)
+                        .pan(options.pan))
                 })
                 .collect::<Result<Vec<_>, EngineError>>()?;
 
@@ -107,11 +142,17 @@ pub fn makenode<const N: usize>(
                     let mut result = event
                         .split(' ')
                         .filter(|x| !x.is_empty())
-                        .map(|x| x.replace(' ', "").parse::<f32>().unwrap());
+                        .map(|x| x.replace(' ', "").parse::<f32>().ok());
 
-                    (result.next().unwrap(), result.next().unwrap())
+                    match (result.next().flatten(), result.next().flatten()) {
+                        (Some(time), Some(value)) => Ok((time, value)),
+                        _ => Err(EngineError::InvalidParameter(
+                            format!("`{}` should be two numbers", event.trim()),
+                            SourceSpan::of_token(component_span, event.trim()),
+                        )),
+                    }
                 })
-                .collect();
+                .collect::<Result<Vec<_>, _>>()?;
 
             (
                 PatternSynth::new(events).sr(sr).to_boxed_nodedata(1),
@@ -126,12 +167,40 @@ pub fn makenode<const N: usize>(
         ),
 
         #[cfg(feature = "use-samples")]
-        Component::Sp(nodes::Sp { sample_sym }) => {
+        Component::Sp(nodes::Sp {
+            sample_sym,
+            pitch,
+            start,
+            end,
+            looping,
+            reverse,
+        }) => {
             let Some(sample) = samples_dict.get(*sample_sym) else {
-                return Err(EngineError::NonExistSample(sample_sym.to_string()));
+                return Err(EngineError::NonExistSample(
+                    sample_sym.to_string(),
+                    SourceSpan::of_token(component_span, sample_sym),
+                ));
             };
 
-            (Sampler::new(*sample, sr).to_boxed_nodedata(2), vec![])
+            let (loop_mode, loop_points) = match looping {
+                Some(nodes::SampleLoop { mode, points }) => (
+                    match mode {
+                        nodes::LoopMode::Forward => LoopMode::Forward,
+                        nodes::LoopMode::PingPong => LoopMode::PingPong,
+                    },
+                    points.map(|(a, b)| (position(a), position(b))),
+                ),
+                None => (LoopMode::Off, None),
+            };
+            let sampler = Sampler::new(sample.clone(), sr)
+                .start(position(*start))
+                .end(position(*end))
+                .looping(loop_mode, loop_points)
+                .reverse(*reverse);
+            match pitch {
+                nodes::NumberOrRef::Number(v) => (sampler.pitch(*v).to_boxed_nodedata(2), vec![]),
+                nodes::NumberOrRef::Ref(s) => (sampler.to_boxed_nodedata(2), vec![s.to_string()]),
+            }
         }
 
         #[cfg(feature = "use-meta")]
@@ -167,10 +236,20 @@ pub fn makenode<const N: usize>(
                         .to_boxed_nodedata(1)
                 }
                 nodes::Signal::Pattern(nodes::Pattern { event, span }) => {
+                    if event
+                        .val_times
+                        .iter()
+                        .any(|(_, _, options)| *options != nodes::EventOptions::default())
+                    {
+                        return Err(EngineError::InvalidParameter(
+                            "only the events of psampler take rate, amp and pan".to_owned(),
+                            component_span.into(),
+                        ));
+                    }
                     let pattern = event
                         .val_times
                         .iter()
-                        .map(|(val, time)| {
+                        .map(|(val, time, _)| {
                             let value = match val {
                                 nodes::EventValue::Number(num) => *num,
                                 nodes::EventValue::Symbol(_) => 100.0,
@@ -189,7 +268,10 @@ pub fn makenode<const N: usize>(
                         .to_boxed_nodedata(1)
                 }
                 nodes::Signal::Event(_) => {
-                    panic!("An event as a parameter to lpf is not currently supported")
+                    return Err(EngineError::InvalidParameter(
+                        "an event as the cutoff of lpf is not supported yet".to_owned(),
+                        component_span.into(),
+                    ))
                 }
             };
             (data, reflist)
@@ -222,7 +304,7 @@ pub fn makenode<const N: usize>(
                     nodes::NumberOrRef::Ref(_) => 0.0,
                 })
                 .gain(*gain)
-                .to_boxed_nodedata(2);
+                .to_boxed_nodedata(1);
 
             let mut reflist = vec![];
             if let nodes::NumberOrRef::Ref(s) = delay {
@@ -240,7 +322,7 @@ pub fn makenode<const N: usize>(
                 .sr(sr)
                 .attack(*attack)
                 .decay(*decay)
-                .to_boxed_nodedata(2),
+                .to_boxed_nodedata(1),
             vec![],
         ),
         Component::Adsr(nodes::Adsr {
@@ -255,7 +337,7 @@ pub fn makenode<const N: usize>(
                 .decay(*decay)
                 .sustain(*sustain)
                 .release(*release)
-                .to_boxed_nodedata(2),
+                .to_boxed_nodedata(1),
             vec![],
         ),
         Component::Tri(nodes::Tri { param }) => match param {
@@ -305,24 +387,43 @@ pub fn makenode<const N: usize>(
             ),
         },
         Component::Mul(nodes::Mul { param }) => match param {
-            nodes::NumberOrRef::Number(v) => (Mul::new(*v).to_boxed_nodedata(2), vec![]),
-            nodes::NumberOrRef::Ref(s) => (Mul::new(0.0).to_boxed_nodedata(2), vec![s.to_string()]),
+            nodes::NumberOrRef::Number(v) => (Mul::new(*v).to_boxed_nodedata(channels), vec![]),
+            nodes::NumberOrRef::Ref(s) => (
+                Mul::new(0.0).to_boxed_nodedata(channels),
+                vec![s.to_string()],
+            ),
         },
         Component::Pan(nodes::Pan { param }) => match param {
             nodes::NumberOrRef::Number(v) => (Pan::new(*v).to_boxed_nodedata(2), vec![]),
             nodes::NumberOrRef::Ref(s) => (Pan::new(0.0).to_boxed_nodedata(2), vec![s.to_string()]),
         },
         Component::Delayn(nodes::Delayn { param }) => match param {
-            nodes::UsizeOrRef::Usize(v) => (DelayN::new(*v).to_boxed_nodedata(2), vec![]),
-            nodes::UsizeOrRef::Ref(s) => (DelayN::new(0).to_boxed_nodedata(2), vec![s.to_string()]),
+            nodes::UsizeOrRef::Usize(v) => (
+                DelayN::new(*v)
+                    .channels(channels)
+                    .to_boxed_nodedata(channels),
+                vec![],
+            ),
+            nodes::UsizeOrRef::Ref(s) => (
+                DelayN::new(0)
+                    .channels(channels)
+                    .to_boxed_nodedata(channels),
+                vec![s.to_string()],
+            ),
         },
         Component::Delayms(nodes::Delayms { param }) => match param {
             nodes::NumberOrRef::Number(v) => (
-                DelayMs::new().sr(sr).delay(*v, 2).to_boxed_nodedata(2),
+                DelayMs::new()
+                    .sr(sr)
+                    .delay(*v, channels as u8)
+                    .to_boxed_nodedata(channels),
                 vec![],
             ),
             nodes::NumberOrRef::Ref(s) => (
-                DelayMs::new().sr(sr).delay(2000., 2).to_boxed_nodedata(2),
+                DelayMs::new()
+                    .sr(sr)
+                    .delay(2000., channels as u8)
+                    .to_boxed_nodedata(channels),
                 vec![s.to_string()],
             ),
         },
@@ -333,11 +434,12 @@ pub fn makenode<const N: usize>(
         Component::Onepole(nodes::Onepole { param }) => {
             get_one_para_from_number_or_ref::<N, OnePole>(param, 1)
         }
-        Component::Add(nodes::Add { param }) => get_one_para_from_number_or_ref::<N, Add>(param, 2),
+        Component::Add(nodes::Add { param }) => {
+            get_one_para_from_number_or_ref::<N, Add>(param, channels)
+        }
         Component::ConstSig(nodes::ConstSig { value }) => {
             (ConstSig::new(*value).sr(sr).to_boxed_nodedata(1), vec![])
         }
-        // todo: give sr to them
         Component::Bd(nodes::Bd { param }) => get_one_para_from_number_or_ref::<N, Bd<N>>(param, 2),
         Component::Hh(nodes::Hh { param }) => get_one_para_from_number_or_ref::<N, Hh<N>>(param, 2),
         Component::Sn(nodes::Sn { param }) => get_one_para_from_number_or_ref::<N, Sn<N>>(param, 2),
@@ -351,9 +453,26 @@ pub fn makenode<const N: usize>(
             (TriSynth::new(*attack, *decay).to_boxed_nodedata(2), vec![])
         }
         Component::Get(nodes::Get { reference }) => (
-            NodeData::new2(BoxedNodeSend::new(Pass {})),
+            NodeData::multi_chan_node(channels, BoxedNodeSend::new(Pass {})),
+            vec![reference.to_string()],
+        ),
+        Component::Feedback(nodes::Feedback { reference }) => (
+            NodeData::multi_chan_node(channels, BoxedNodeSend::new(Feedback::new())),
             vec![reference.to_string()],
         ),
+        Component::Comb(nodes::Comb {
+            delay,
+            feedback,
+            damp,
+        }) => (
+            Comb::new()
+                .sr(sr)
+                .delay(*delay, channels as u8)
+                .feedback(*feedback)
+                .damp(*damp)
+                .to_boxed_nodedata(channels),
+            vec![],
+        ),
         Component::Seq(nodes::Seq { events }) => {
             let mut reflist = Vec::<String>::new();
             let mut order = HashMap::new();
@@ -373,16 +492,16 @@ pub fn makenode<const N: usize>(
                     .sr(sr)
                     .bpm(bpm)
                     .ref_order(order)
-                    .to_boxed_nodedata(2),
+                    .to_boxed_nodedata(1),
                 reflist,
             )
         }
         Component::Choose(nodes::Choose { choices }) => (
-            Choose::new(choices.clone(), seed as u64).to_boxed_nodedata(2),
+            Choose::new(choices.clone(), seed as u64).to_boxed_nodedata(1),
             vec![],
         ),
         Component::Mix(nodes::Mix { nodes }) => (
-            NodeData::new2(BoxedNodeSend::new(Sum2 {})),
+            NodeData::multi_chan_node(channels, BoxedNodeSend::new(Sum2 {})),
             nodes.iter().map(ToString::to_string).collect(),
         ),
         Component::Arrange(nodes::Arrange { events }) => {
@@ -398,34 +517,50 @@ pub fn makenode<const N: usize>(
                 Arrange::new(events.to_inner_owned())
                     .sr(sr)
                     .bpm(bpm)
-                    .to_boxed_nodedata(2),
+                    .to_boxed_nodedata(1),
                 reflist,
             )
         }
         Component::Reverb(_) | Component::Expr(_) => {
-            panic!("{component:?} is currently not supported within the engine")
+            return Err(unsupported(
+                component_span,
+                "is currently not supported within the engine",
+            ))
         }
         #[cfg(not(feature = "use-samples"))]
         Component::Sp(_) | Component::PSampler(_) => {
-            panic!("The `use-samples` feature is required to use the `sp` or `psampler` node")
+            return Err(unsupported(
+                component_span,
+                "requires the `use-samples` feature",
+            ))
         }
         #[cfg(not(feature = "bela"))]
-        Component::Adc(_) => panic!("The `bela` feature is required to use the `adc` node"),
+        Component::Adc(_) => {
+            return Err(unsupported(component_span, "requires the `bela` feature"))
+        }
         #[cfg(not(feature = "use-meta"))]
-        Component::Meta(_) => panic!("The `use-meta` feature is required to use the `meta` node"),
-        // "sendpass" => {
-        //     let reflist = match &paras[0] {
-        //         GlicolPara::RefList(v) => {
-        //             v
-        //         },
-        //         _ => unimplemented!()
-        //     };
-        //     ( Pass{}.to_boxed_nodedata(2), reflist)
-        // },
+        Component::Meta(_) => {
+            return Err(unsupported(
+                component_span,
+                "requires the `use-meta` feature",
+            ))
+        }
     };
+    // some nodes, such as the drums and synths, are only told the rate once they are built
+    nodedata.node.send_msg(Message::SetSampleRate(sr));
     Ok((nodedata, reflist))
 }
 
+// names the node by its first word in the code, e.g. `reverb`
+fn unsupported(component_span: pest::Span<'_>, reason: &str) -> EngineError {
+    let name = component_span
+        .as_str()
+        .split_whitespace()
+        .next()
+        .unwrap_or_default();
+    EngineError::UnsupportedNode(format!("{name} {reason}"), component_span.into())
+}
+
 fn get_one_para_from_number_or_ref<const N: usize, T>(
     param: &nodes::NumberOrRef<&str>,
     channels: usize,
@@ -441,3 +576,11 @@ where
         ),
     }
 }
+
+#[cfg(feature = "use-samples")]
+fn position(position: nodes::SamplePosition) -> Position {
+    match position {
+        nodes::SamplePosition::Fraction(f) => Position::Fraction(f),
+        nodes::SamplePosition::Milliseconds(ms) => Position::Ms(ms),
+    }
+}
@@ -0,0 +1,214 @@
+//! The audio path must not touch the allocator once a patch is running. Every allocation made on
+//! the test thread is counted, so a node that allocates in `process` fails here.
+
+use glicol::*;
+use glicol_synth::{GlicolPara, Message};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct Counting;
+
+thread_local! {
+    static CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    // the thread local may already be gone while a thread shuts down
+    let _ = CALLS.try_with(|calls| calls.set(calls.get() + 1));
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        count();
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+fn calls_during(mut f: impl FnMut()) -> usize {
+    let before = CALLS.with(Cell::get);
+    f();
+    CALLS.with(Cell::get) - before
+}
+
+const PATCH: &str = "o: mix ~osc ~filter ~seq ~synth ~fx ~env
+~osc: sin 440 >> mul ~lfo >> add 0.1
+~lfo: tri 0.5 >> mul 0.3 >> add 0.5
+~filter: saw 110 >> lpf \"1000@0 500@0.5\"(1) 1.0 >> rhpf 200 1.0
+~seq: speed 4.0 >> seq 60 _62 _ 67 >> sawsynth 0.01 0.1 >> mul ~perc
+~perc: seq 60 ~r >> envperc 0.01 0.1
+~r: choose 60 62 64
+~synth: psynth `0.0 60, 0.5 67` 1
+~fx: imp 10 >> delayn 3 >> delayms 100 >> apfmsgain 10 0.5 >> plate 0.2
+~env: squ 220 >> adsr 0.01 0.1 0.5 0.1 >> onepole 0.5
+~x: msgsynth \\saw 0.01 0.1
+~drums: arrange ~bd 1 ~hh 1 >> balance ~x ~sn
+~bd: seq 60 _ 60 >> bd 0.1
+~sn: seq _ 60 >> sn 0.1
+~hh: seq 60 60 >> hh 0.1 >> mul ~noise
+~noise: noise 42";
+
+// notes for `msgsynth` both while warming up and while counting
+fn send_notes(send: &mut impl FnMut(&str)) {
+    for i in 4..24 {
+        send(&format!("~x,0,event,{}=>60", i as f32 * 0.25));
+    }
+}
+
+#[test]
+fn engine_does_not_allocate() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code(PATCH).unwrap();
+    send_notes(&mut |msg| engine.send_msg(msg).unwrap());
+
+    for _ in 0..1000 {
+        engine.next_block(&[]);
+    }
+    let calls = calls_during(|| {
+        for _ in 0..1000 {
+            engine.next_block(&[]);
+        }
+    });
+    assert_eq!(calls, 0);
+}
+
+#[test]
+fn processor_does_not_allocate() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code(PATCH).unwrap();
+    send_notes(&mut |msg| controller.send_msg(msg).unwrap());
+
+    for _ in 0..1000 {
+        processor.next_block(&[]);
+    }
+    let calls = calls_during(|| {
+        for _ in 0..1000 {
+            processor.next_block(&[]);
+        }
+    });
+    assert_eq!(calls, 0);
+}
+
+#[test]
+fn processor_keeps_garbage_the_queue_cannot_take() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code("o: constsig 1").unwrap();
+    for i in 0..1500 {
+        let msg = Message::SetToSymbol(0, "x".to_owned());
+        controller
+            .schedule_msg(Time::Samples(1_000_000), "o", 0, msg)
+            .unwrap();
+        if i % 100 == 0 {
+            processor.next_block(&[]);
+        }
+    }
+    for _ in 0..10 {
+        processor.next_block(&[]);
+    }
+
+    // more messages than the garbage queue holds, and the controller not keeping up
+    controller.clear_scheduled();
+    let calls = calls_during(|| {
+        for _ in 0..10 {
+            processor.next_block(&[]);
+        }
+    });
+    assert_eq!(calls, 0);
+
+    // the rest is handed over once there is room again, and later edits wait for it
+    controller.collect_garbage();
+    controller
+        .set_param("o", 0, 0.into(), GlicolPara::Number(2.))
+        .unwrap();
+    let calls = calls_during(|| {
+        for _ in 0..10 {
+            processor.next_block(&[]);
+        }
+    });
+    assert_eq!(calls, 0);
+    controller.collect_garbage();
+    assert_eq!(processor.next_block(&[])[0][0], 2.0);
+}
+
+#[test]
+fn input_does_not_allocate() {
+    let mut engine = Engine::<128>::new();
+    engine
+        .update_with_code("o: ~input >> mul 0.5 >> lpf 1000 1.0")
+        .unwrap();
+    let input = [0.25; 128];
+
+    engine.next_block(&[&input, &input]);
+    let calls = calls_during(|| {
+        for _ in 0..100 {
+            engine.next_block(&[&input, &input]);
+        }
+    });
+    assert_eq!(calls, 0);
+}
+
+#[cfg(feature = "use-samples")]
+#[test]
+fn samplers_do_not_allocate() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    engine
+        .update_with_code("o: mix ~p ~s\n~p: psampler \"\\a@0 \\a@0.5\"(1)\n~s: sp \\a")
+        .unwrap();
+
+    for _ in 0..1000 {
+        engine.next_block(&[]);
+    }
+    let calls = calls_during(|| {
+        for _ in 0..1000 {
+            engine.next_block(&[]);
+        }
+    });
+    assert_eq!(calls, 0);
+}
+
+#[cfg(feature = "use-samples")]
+#[test]
+fn swapping_samples_does_not_allocate() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    controller
+        .update_with_code("o: mix ~p ~s\n~p: psampler \"\\a@0 \\a@0.5\"(1)\n~s: sp \\a")
+        .unwrap();
+    for _ in 0..100 {
+        processor.next_block(&[]);
+    }
+
+    // the old sample is freed by the controller
+    controller.add_sample("\\a", vec![0.25; 20000], 1, 44100);
+    let calls = calls_during(|| {
+        for _ in 0..100 {
+            processor.next_block(&[]);
+        }
+    });
+    assert_eq!(calls, 0);
+
+    // the sampler lets go of a sample that has been removed
+    controller.add_sample("\\b", vec![0.5; 20000], 1, 44100);
+    controller.remove_sample("\\a");
+    let sample = GlicolPara::SampleSymbol("\\b".to_owned());
+    controller
+        .set_param("~s", 0, Param::Name("sample"), sample)
+        .unwrap();
+    let calls = calls_during(|| {
+        for _ in 0..100 {
+            processor.next_block(&[]);
+        }
+    });
+    assert_eq!(calls, 0);
+}
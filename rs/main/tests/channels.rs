@@ -0,0 +1,157 @@
+use glicol::*;
+use glicol_synth::{mix_into, AudioContextConfig, Buffer};
+
+fn engine(channels: usize) -> Engine<128> {
+    Engine::with_config(AudioContextConfig {
+        channels,
+        ..Default::default()
+    })
+}
+
+fn block(engine: &mut Engine<128>, input: &[&[f32]]) -> Vec<Vec<f32>> {
+    engine
+        .next_block(input)
+        .iter()
+        .map(|buf| buf.to_vec())
+        .collect()
+}
+
+fn ramp(offset: f32) -> Vec<f32> {
+    (0..128).map(|i| offset + i as f32 / 128.).collect()
+}
+
+#[test]
+fn mono_goes_to_every_channel() {
+    let mut stereo = engine(2);
+    let mut octo = engine(8);
+    for engine in [&mut stereo, &mut octo] {
+        engine.update_with_code("o: sin 440 >> mul 0.5").unwrap();
+    }
+    for _ in 0..4 {
+        let left = block(&mut stereo, &[])[0].clone();
+        let out = block(&mut octo, &[]);
+        assert_eq!(out.len(), 8);
+        assert!(out.iter().all(|chan| *chan == left));
+    }
+}
+
+#[test]
+fn input_per_channel() {
+    let mut engine = engine(4);
+    engine
+        .update_with_code("o: ~input >> mul 0.5 >> delayn 1")
+        .unwrap();
+    let input: Vec<Vec<f32>> = (0..4).map(|c| ramp(c as f32)).collect();
+    let slices: Vec<&[f32]> = input.iter().map(Vec::as_slice).collect();
+    let out = block(&mut engine, &slices);
+    for (chan, input) in out.iter().zip(&input) {
+        assert_eq!(chan[0], 0.0);
+        assert_eq!(
+            chan[1..],
+            input[..127].iter().map(|x| x * 0.5).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn stereo_input_alternates_on_quad() {
+    let mut engine = engine(4);
+    engine.update_with_code("o: ~input").unwrap();
+    let (left, right) = (ramp(0.0), ramp(1.0));
+    let out = block(&mut engine, &[&left, &right]);
+    assert_eq!(out, vec![left.clone(), right.clone(), left, right]);
+}
+
+#[test]
+fn stereo_input_averaged_on_mono() {
+    let mut engine = engine(1);
+    engine.update_with_code("o: ~input").unwrap();
+    let (left, right) = (ramp(0.0), ramp(1.0));
+    let out = block(&mut engine, &[&left, &right]);
+    let expected: Vec<f32> = left.iter().zip(&right).map(|(l, r)| (l + r) / 2.).collect();
+    assert_eq!(out, vec![expected]);
+}
+
+#[test]
+fn downmix_averages_the_folded_channels() {
+    let from: Vec<Buffer<4>> = (0..5).map(|c| Buffer::from([c as f32; 4])).collect();
+    let mut to = vec![Buffer::<4>::SILENT; 2];
+    mix_into(&from, &mut to);
+    // 0, 2 and 4 fold onto the first channel, 1 and 3 onto the second
+    assert_eq!(to[0][0], 2.0);
+    assert_eq!(to[1][0], 2.0);
+
+    let mut to = vec![Buffer::<4>::from([1.0; 4]); 3];
+    mix_into(&from[..1], &mut to);
+    assert!(to.iter().all(|buf| buf[0] == 1.0));
+}
+
+#[test]
+fn pan_a_mono_signal() {
+    let mut engine = engine(2);
+    engine.update_with_code("o: constsig 1 >> pan 1").unwrap();
+    let out = block(&mut engine, &[]);
+    assert_eq!(out, vec![vec![0.0; 128], vec![1.0; 128]]);
+}
+
+#[test]
+fn mono_nodes_take_the_average() {
+    let mut engine = engine(2);
+    engine.update_with_code("o: ~input >> pan 0").unwrap();
+    let (left, right) = (ramp(0.0), ramp(1.0));
+    let out = block(&mut engine, &[&left, &right]);
+    let gain = 0.5f32.sqrt();
+    for (i, (l, r)) in left.iter().zip(&right).enumerate() {
+        let mid = (l + r) / 2.;
+        assert_eq!((out[0][i], out[1][i]), (mid * gain, mid * gain));
+    }
+}
+
+#[test]
+fn envelopes_on_every_channel() {
+    let mut engine = engine(2);
+    engine
+        .update_with_code("o: sin 440 >> mul ~env\n~env: imp 2 >> envperc 0.01 0.2")
+        .unwrap();
+    let out = block(&mut engine, &[]);
+    assert!(out[0].iter().any(|x| *x != 0.0));
+    assert_eq!(out[0], out[1]);
+}
+
+#[test]
+fn stereo_into_three_channels() {
+    let mut engine = engine(3);
+    engine.update_with_code("o: sin 440").unwrap();
+    let code = "o: sin 440\nout: sin 220 >> pan 0";
+    let err = engine.update_with_code(code).unwrap_err();
+    assert!(matches!(err, EngineError::ChannelMismatch(..)));
+    assert_eq!(err.span().line, 2);
+    // the engine carries on with the code it had
+    let out = block(&mut engine, &[]);
+    assert!(out.iter().all(|chan| *chan == out[0]));
+
+    let (mut controller, _processor) = engine.split();
+    assert!(matches!(
+        controller.update_with_code("o: ~a\n~a: sin 440 >> pan 0"),
+        Err(EngineError::ChannelMismatch(..))
+    ));
+}
+
+#[test]
+fn mono_input_to_a_split_engine() {
+    for channels in [2, 4] {
+        let (mut controller, mut processor) = engine(channels).split();
+        controller.update_with_code("o: ~input").unwrap();
+        // the update lands on the first block
+        processor.next_block(&[&[0.5; 128]]);
+        let input = ramp(0.0);
+        let out = processor.next_block(&[&input]);
+        assert_eq!(out.len(), channels);
+        assert!(out.iter().all(|chan| chan[..] == input[..]));
+
+        // a short slice leaves the rest of the block silent
+        let out = processor.next_block(&[&input[..64]]);
+        assert!(out.iter().all(|chan| chan[..64] == input[..64]));
+        assert!(out.iter().all(|chan| chan[64..] == [0.0; 64]));
+    }
+}
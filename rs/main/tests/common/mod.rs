@@ -0,0 +1,19 @@
+// Each test file only uses some of these.
+#![allow(dead_code)]
+
+use glicol::Engine;
+
+/// The first channel of the next block.
+pub fn block(engine: &mut Engine<128>) -> Vec<f32> {
+    engine.next_block(&[])[0].to_vec()
+}
+
+/// The first sample of the next block.
+pub fn first_sample(engine: &mut Engine<128>) -> f32 {
+    engine.next_block(&[])[0][0]
+}
+
+/// The first channel of the next `blocks` blocks, one after the other.
+pub fn render(engine: &mut Engine<128>, blocks: usize) -> Vec<f32> {
+    (0..blocks).flat_map(|_| block(engine)).collect()
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::first_sample;
+use glicol::*;
+use glicol_synth::GlicolPara;
+
+#[test]
+fn set_param_by_index_and_name() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1 >> mul 0.5").unwrap();
+    assert_eq!(first_sample(&mut engine), 0.5);
+
+    engine
+        .set_param("o", 0, Param::Index(0), GlicolPara::Number(0.5))
+        .unwrap();
+    assert_eq!(first_sample(&mut engine), 0.25);
+
+    engine
+        .set_param("o", 1, "value".into(), GlicolPara::Number(2.))
+        .unwrap();
+    assert_eq!(first_sample(&mut engine), 1.0);
+}
+
+#[test]
+fn set_param_errors() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1 >> mul 0.5").unwrap();
+    let one = || GlicolPara::Number(1.);
+
+    assert_eq!(
+        engine.set_param("~a", 0, 0.into(), one()),
+        Err(MsgError::NonExistChain("~a".into()))
+    );
+    assert_eq!(
+        engine.set_param("o", 2, 0.into(), one()),
+        Err(MsgError::NonExistNode("o".into(), 2))
+    );
+    assert_eq!(
+        engine.set_param("o", 1, 1.into(), one()),
+        Err(MsgError::NonExistParam("o".into(), 1, "1".into()))
+    );
+    assert_eq!(
+        engine.set_param("o", 1, "cutoff".into(), one()),
+        Err(MsgError::NonExistParam("o".into(), 1, "cutoff".into()))
+    );
+    assert!(matches!(
+        engine.set_param("o", 1, 0.into(), GlicolPara::Reference("~b".into())),
+        Err(MsgError::InvalidValue(_))
+    ));
+    assert_eq!(first_sample(&mut engine), 0.5);
+}
+
+#[test]
+fn send_msg_reports_errors() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1 >> mul 0.5").unwrap();
+
+    assert_eq!(
+        engine.send_msg("o,0"),
+        Err(MsgError::Malformed("o,0".into()))
+    );
+    assert_eq!(
+        engine.send_msg("o,first,0,1"),
+        Err(MsgError::Malformed("o,first,0,1".into()))
+    );
+    // an out-of-range position used to panic
+    assert_eq!(
+        engine.send_msg("o,5,0,1"),
+        Err(MsgError::NonExistNode("o".into(), 5))
+    );
+
+    // the commands after a bad one are still sent
+    assert!(engine.send_msg("o,9,0,1; o,1,value,0.25").is_err());
+    assert_eq!(first_sample(&mut engine), 0.25);
+
+    engine.send_msg("o, 0, 0, 2; o, 1, 0, 1").unwrap();
+    assert_eq!(first_sample(&mut engine), 2.0);
+}
+
+#[test]
+fn node_params_are_listed() {
+    let mut engine = Engine::<128>::new();
+    engine
+        .update_with_code("~lead: saw 110 >> lpf 1000 1.0\no: ~lead >> mul 0.5")
+        .unwrap();
+
+    let params = engine.node_params("~lead", 1).unwrap();
+    let names: Vec<_> = params.iter().map(|p| (p.name, p.index)).collect();
+    assert_eq!(names, [("cutoff", 0), ("q", 1)]);
+    assert_eq!(params[0].unit, glicol_synth::Unit::Hz);
+
+    engine.send_msg("~lead,1,cutoff,800").unwrap();
+    assert_eq!(
+        engine.send_msg("~lead,1,2,800"),
+        Err(MsgError::NonExistParam("~lead".into(), 1, "2".into()))
+    );
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::render;
+use glicol::*;
+
+#[test]
+fn replaced_node_fades() {
+    let mut engine = Engine::<128>::new();
+    engine.set_crossfade(1000. * 256. / 44100.);
+    engine.update_with_code("o: constsig 1 >> mul 0.5").unwrap();
+    render(&mut engine, 4);
+    let nodes = engine.context.graph.node_count();
+
+    engine.update_with_code("o: constsig 0 >> mul 0.5").unwrap();
+    let out = render(&mut engine, 3);
+    assert_eq!(out[0], 0.5);
+    assert!((out[128] - 0.25).abs() < 1e-6);
+    assert!(out.windows(2).all(|w| w[1] <= w[0]));
+    assert_eq!(out[256..], [0.0; 128]);
+    assert_eq!(engine.context.graph.node_count(), nodes);
+}
+
+#[test]
+fn removed_chain_fades_out() {
+    let mut engine = Engine::<128>::new();
+    engine.set_crossfade(1000. * 256. / 44100.);
+    engine
+        .update_with_code("o: constsig 0.5\n~m: constsig 2\nb: constsig 1 >> mul ~m")
+        .unwrap();
+    render(&mut engine, 4);
+
+    engine.update_with_code("o: constsig 0.5").unwrap();
+    let out = render(&mut engine, 3);
+    assert_eq!(out[0], 2.5);
+    assert!((out[128] - 1.5).abs() < 1e-6);
+    assert_eq!(out[256..], [0.5; 128]);
+}
+
+#[test]
+fn no_crossfade_by_default() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    render(&mut engine, 1);
+    engine.update_with_code("o: constsig 0").unwrap();
+    assert_eq!(render(&mut engine, 1), [0.0; 128]);
+}
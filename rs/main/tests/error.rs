@@ -0,0 +1,52 @@
+use glicol::*;
+
+#[test]
+fn reference_span() {
+    let mut engine = Engine::<128>::new();
+    let code = "o: sin 440\nb: sin 220 >> mul ~am";
+    let err = engine.update_with_code(code).unwrap_err();
+    let span = err.span();
+    assert!(matches!(err, EngineError::NonExistReference(ref name, _) if name == "~am"));
+    assert_eq!(&code[span.start..span.end], "~am");
+    assert_eq!((span.line, span.col), (2, 19));
+}
+
+#[test]
+fn span_of_a_whole_reference() {
+    let mut engine = Engine::<128>::new();
+    let code = "~am: sin 1\no: mix ~am ~a";
+    let err = engine.update_with_code(code).unwrap_err();
+    let span = err.span();
+    assert!(matches!(err, EngineError::NonExistReference(ref name, _) if name == "~a"));
+    assert_eq!((span.start, span.end), (code.len() - 2, code.len()));
+}
+
+#[cfg(feature = "use-samples")]
+#[test]
+fn sample_span() {
+    let mut engine = Engine::<128>::new();
+    let code = r"o: sp \nope";
+    let err = engine.update_with_code(code).unwrap_err();
+    let span = err.span();
+    assert!(matches!(err, EngineError::NonExistSample(..)));
+    assert_eq!(&code[span.start..span.end], r"\nope");
+}
+
+#[test]
+fn unsupported_node_is_recoverable() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: sin 440").unwrap();
+    let err = engine
+        .update_with_code("o: sin 440 >> reverb 0.5 0.5 0.5 0.5 0.5")
+        .unwrap_err();
+    assert!(matches!(err, EngineError::UnsupportedNode(..)));
+    assert_eq!(err.span().col, 15);
+    assert!(engine.update_with_code("o: sin 220").is_ok());
+}
+
+#[test]
+fn parsing_error_span() {
+    let mut engine = Engine::<128>::new();
+    let err = engine.update_with_code("o: sin 440 >>").unwrap_err();
+    assert_eq!(err.span().line, 1);
+}
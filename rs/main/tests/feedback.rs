@@ -0,0 +1,68 @@
+mod common;
+
+use common::block;
+use glicol::*;
+
+#[test]
+fn fb_is_one_block_late() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: fb ~a\n~a: constsig 1").unwrap();
+    assert_eq!(block(&mut engine), vec![0.0; 128]);
+    assert_eq!(block(&mut engine), vec![1.0; 128]);
+}
+
+#[test]
+fn loop_through_fb() {
+    let mut engine = Engine::<128>::new();
+    engine
+        .update_with_code("o: ~a\n~a: imp 10 >> add ~f >> mul 0.5\n~f: fb ~a")
+        .unwrap();
+    // the impulse comes round once a block, halved every time
+    for expected in [0.5, 0.25, 0.125] {
+        assert_eq!(block(&mut engine)[0], expected);
+    }
+}
+
+#[test]
+fn loop_without_fb() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 0.5").unwrap();
+    let code = "o: ~a\n~a: constsig 1 >> mul ~b\n~b: constsig 1 >> mul ~a";
+    let err = engine.update_with_code(code).unwrap_err();
+    assert!(matches!(err, EngineError::CyclicReference(..)));
+    assert_eq!(err.span().line, 3);
+    // the engine carries on with the code it had
+    assert_eq!(block(&mut engine), vec![0.5; 128]);
+
+    assert!(matches!(
+        engine.update_with_code("o: sin 440 >> mul o"),
+        Err(EngineError::CyclicReference(..))
+    ));
+}
+
+#[test]
+fn comb_rings() {
+    let mut engine = Engine::<128>::new();
+    // 1 ms is 44 samples at 44100 Hz
+    engine.update_with_code("o: imp 1 >> comb 1 0.9 0").unwrap();
+    let out = block(&mut engine);
+    assert_eq!(out[0], 1.0);
+    assert_eq!(out[44], 0.9);
+    assert!((out[88] - 0.81).abs() < 1e-6);
+    assert_eq!(out[45], 0.0);
+}
+
+#[test]
+fn parallel_with_fb() {
+    let code = "o: ~a >> mul 0.5\n~a: saw 110 >> add ~f >> lpf 800 1.0\n~f: fb ~a >> mul 0.7
+out: ~b\n~b: sin 220 >> add ~g\n~g: fb o >> mul 0.3";
+    let mut serial = Engine::<128>::new();
+    let mut parallel = Engine::<128>::new();
+    parallel.set_threads(4);
+    for engine in [&mut serial, &mut parallel] {
+        engine.update_with_code(code).unwrap();
+    }
+    for _ in 0..100 {
+        assert_eq!(block(&mut serial), block(&mut parallel));
+    }
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "load-samples")]
+
+use glicol::{load::sample_name, *};
+use std::path::PathBuf;
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("glicol-{test}-{}", std::process::id()));
+    _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn ramp(frames: usize, offset: f32) -> Vec<f32> {
+    (0..frames).map(|i| offset + i as f32 / 256.).collect()
+}
+
+fn crc(bytes: &[u8], poly: u16, width: u32) -> u16 {
+    let top = 1 << (width - 1);
+    let mask = ((1u32 << width) - 1) as u16;
+    let mut crc = 0u16;
+    for byte in bytes {
+        crc ^= (*byte as u16) << (width - 8);
+        for _ in 0..8 {
+            crc = if crc & top != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            } & mask;
+        }
+    }
+    crc
+}
+
+// a 16 bit FLAC file with one frame, with every channel stored verbatim
+fn flac(channels: &[Vec<i16>], sr: u32) -> Vec<u8> {
+    let frames = channels[0].len();
+    let mut out = b"fLaC".to_vec();
+    out.extend([0x80, 0, 0, 34]);
+    out.extend((frames as u16).to_be_bytes());
+    out.extend((frames as u16).to_be_bytes());
+    out.extend([0; 6]);
+    let info = (sr as u64) << 44 | ((channels.len() as u64 - 1) << 41) | (15 << 36) | frames as u64;
+    out.extend(info.to_be_bytes());
+    out.extend([0; 16]);
+
+    let mut frame = vec![0xFF, 0xF8, 0x60, (channels.len() as u8 - 1) << 4 | 0x08, 0];
+    frame.push(frames as u8 - 1);
+    frame.push(crc(&frame, 0x07, 8) as u8);
+    for channel in channels {
+        frame.push(0x02);
+        frame.extend(channel.iter().flat_map(|x| x.to_be_bytes()));
+    }
+    frame.extend(crc(&frame, 0x8005, 16).to_be_bytes());
+    out.extend(frame);
+    out
+}
+
+#[test]
+fn names_like_the_browser() {
+    assert_eq!(sample_name("808bd.wav"), "\\808bd");
+    assert_eq!(sample_name("Piano C#4.flac"), "\\piano_c_sharp_4");
+    assert_eq!(sample_name("drums/hard-kick.wav"), "\\hard_kick");
+}
+
+#[test]
+fn wav_files() {
+    let dir = temp_dir("wav");
+    let (left, right) = (ramp(100, 0.0), ramp(100, -0.5));
+    let audio = RenderedAudio {
+        sr: 48000,
+        channels: vec![left.clone(), right.clone()],
+    };
+    let float = WavSpec::new().bit_depth(BitDepth::Float32);
+    audio.write_wav(dir.join("Stereo.wav"), &float).unwrap();
+
+    let mut engine = Engine::<128>::new();
+    assert_eq!(
+        engine.load_sample(dir.join("Stereo.wav")).unwrap(),
+        "\\stereo"
+    );
+    let sample = &engine.samples_dict["\\stereo"];
+    assert_eq!((sample.channels, sample.sr), (2, 48000));
+    assert_eq!(sample.data[..], [left, right].concat());
+
+    let int = WavSpec::new().bit_depth(BitDepth::Int16);
+    audio.write_wav(dir.join("int.wav"), &int).unwrap();
+    engine.load_sample(dir.join("int.wav")).unwrap();
+    let data = &engine.samples_dict["\\int"].data;
+    assert!(data
+        .iter()
+        .zip(&engine.samples_dict["\\stereo"].data[..])
+        .all(|(a, b)| (a - b).abs() < 1e-4));
+
+    assert!(matches!(
+        engine.load_sample(dir.join("missing.wav")),
+        Err(LoadError::Wav(..))
+    ));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn flac_files() {
+    let dir = temp_dir("flac");
+    let left: Vec<i16> = (0..64).map(|i| i * 256).collect();
+    let right: Vec<i16> = left.iter().map(|x| -x).collect();
+    std::fs::write(dir.join("bd.flac"), flac(&[left.clone(), right], 44100)).unwrap();
+
+    let mut engine = Engine::<128>::new();
+    engine.load_sample(dir.join("bd.flac")).unwrap();
+    let sample = &engine.samples_dict["\\bd"];
+    assert_eq!(
+        (sample.channels, sample.sr, sample.frames()),
+        (2, 44100, 64)
+    );
+    assert_eq!(sample.channel(0)[1], 256. / 32768.);
+    assert_eq!(sample.channel(1)[1], -256. / 32768.);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn directory_trees() {
+    let root = temp_dir("tree");
+    let dir = root.join("My Kit");
+    std::fs::create_dir_all(dir.join("Drums")).unwrap();
+    let audio = RenderedAudio {
+        sr: 44100,
+        channels: vec![vec![0.5; 10000]],
+    };
+    audio
+        .write_wav(dir.join("808bd.wav"), &WavSpec::new())
+        .unwrap();
+    audio
+        .write_wav(dir.join("Drums/snare-2.WAV"), &WavSpec::new())
+        .unwrap();
+    std::fs::write(dir.join("Drums/hh.flac"), flac(&[vec![0; 16]], 44100)).unwrap();
+    std::fs::write(dir.join("zz.flac"), flac(&[vec![0; 16]], 44100)).unwrap();
+    std::fs::write(dir.join("2json.js"), "").unwrap();
+
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    let names = controller.load_samples(&dir).unwrap();
+    // numbered in each folder, as the browser does
+    assert_eq!(
+        names,
+        ["\\my_kit_0", "\\drums_0", "\\drums_1", "\\my_kit_1"]
+    );
+    controller
+        .update_with_code("o: imp 1 >> sp \\drums_1")
+        .unwrap();
+    assert!(processor.next_block(&[])[0][1] > 0.49);
+
+    // nothing is loaded if one of the files is broken
+    std::fs::write(dir.join("broken.wav"), "RIFF").unwrap();
+    let mut engine = Engine::<128>::new();
+    assert!(engine.load_samples(&dir).is_err());
+    assert!(engine.samples_dict.is_empty());
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::render;
+use glicol::*;
+
+fn patch(voices: usize) -> String {
+    let mut code = String::from("o: mix");
+    for i in 0..voices {
+        code.push_str(&format!(" ~v{i}"));
+    }
+    for i in 0..voices {
+        code.push_str(&format!(
+            "\n~v{i}: saw {} >> lpf ~c{i} 1.0 >> mul 0.02\n~c{i}: sin {} >> mul 300 >> add 600",
+            110 + i * 7,
+            0.1 * i as f32
+        ));
+    }
+    // chains straight into the output, and one that shares its input with another
+    code + "\nout: sin 220 >> mul ~c0\n~x: seq 60 _60 >> sawsynth 0.01 0.1 >> plate 0.1"
+}
+
+#[test]
+fn same_as_serial() {
+    let mut serial = Engine::<128>::new();
+    let mut parallel = Engine::<128>::new();
+    parallel.set_threads(4);
+    for engine in [&mut serial, &mut parallel] {
+        engine.update_with_code(&patch(32)).unwrap();
+    }
+    for _ in 0..200 {
+        assert_eq!(serial.next_block(&[]), parallel.next_block(&[]));
+    }
+
+    // the groups follow the graph as it changes
+    for engine in [&mut serial, &mut parallel] {
+        engine.update_with_code(&patch(20)).unwrap();
+    }
+    for _ in 0..200 {
+        assert_eq!(serial.next_block(&[]), parallel.next_block(&[]));
+    }
+}
+
+#[test]
+fn split_processor_keeps_its_threads() {
+    let mut serial = Engine::<128>::new();
+    serial.update_with_code(&patch(16)).unwrap();
+    let mut engine = Engine::<128>::new();
+    engine.set_threads(3);
+    let (mut controller, mut processor) = engine.split();
+    controller.update_with_code(&patch(16)).unwrap();
+
+    for _ in 0..100 {
+        assert_eq!(serial.next_block(&[]), processor.next_block(&[]));
+    }
+}
+
+#[test]
+fn back_to_one_thread() {
+    let mut engine = Engine::<128>::new();
+    engine.set_threads(2);
+    engine.update_with_code(&patch(4)).unwrap();
+    render(&mut engine, 10);
+    engine.set_threads(1);
+    render(&mut engine, 10);
+}
@@ -0,0 +1,85 @@
+use glicol::*;
+use std::cell::Cell;
+
+thread_local! {
+    static TICKS: Cell<u32> = const { Cell::new(0) };
+}
+
+// a millisecond passes every time the clock is read
+fn fake_clock() -> f64 {
+    TICKS.with(|ticks| {
+        ticks.set(ticks.get() + 1);
+        ticks.get() as f64 / 1000.
+    })
+}
+
+const CODE: &str = "o: sin 440 >> mul 0.5\nout: saw 110 >> lpf ~c 1.0\n~c: sin 0.1 >> mul 300 >> add 600\n~unused: saw 100";
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{a} != {b}");
+}
+
+#[test]
+fn time_per_node() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code(CODE).unwrap();
+    assert_eq!(engine.take_profile().blocks, 0);
+
+    engine.set_profiling(Some(fake_clock));
+    for _ in 0..10 {
+        engine.next_block(&[]);
+    }
+    let profile = engine.take_profile();
+    assert_eq!(profile.blocks, 10);
+    assert_eq!(profile.budget, 128. / 44100.);
+    for node in &profile.chains["o"] {
+        assert_close(*node, 0.001);
+    }
+    assert_eq!(profile.chains["~c"].len(), 3);
+    assert_close(profile.chain("out"), 0.002);
+    // not connected to anything, so never processed
+    assert_eq!(profile.chains["~unused"], vec![0.0]);
+    assert!(profile.total > profile.chain("o") + profile.chain("out") + profile.chain("~c"));
+    assert_close(profile.load(), profile.total / profile.budget);
+
+    // taking it starts a new one
+    engine.next_block(&[]);
+    assert_eq!(engine.take_profile().blocks, 1);
+
+    engine.set_profiling(None);
+    engine.next_block(&[]);
+    let profile = engine.take_profile();
+    assert_eq!(profile.blocks, 0);
+    assert_eq!(profile.chain("o"), 0.0);
+}
+
+#[test]
+fn same_times_in_parallel() {
+    let mut engine = Engine::<128>::new();
+    engine.set_threads(2);
+    engine.update_with_code(CODE).unwrap();
+    engine.set_profiling(Some(fake_clock));
+    for _ in 0..10 {
+        engine.next_block(&[]);
+    }
+    let profile = engine.take_profile();
+    for chain in ["o", "out", "~c"] {
+        for node in &profile.chains[chain] {
+            assert_close(*node, 0.001);
+        }
+    }
+}
+
+#[test]
+fn profile_a_split_engine() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code(CODE).unwrap();
+    controller.set_profiling(Some(glicol_synth::std_clock));
+    for _ in 0..10 {
+        processor.next_block(&[]);
+    }
+    let profile = controller.name_profile(processor.take_profile());
+    assert_eq!(profile.blocks, 10);
+    assert!(profile.chain("out") > 0.0);
+    assert!(profile.load() > 0.0);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::first_sample;
+use glicol::*;
+
+#[test]
+fn update_waits_for_next_beat() {
+    let mut engine = Engine::<128>::new();
+    engine.set_quantize(Quantize::Beat);
+    // the clock is on a boundary at the start, so this applies straight away
+    engine.update_with_code("o: constsig 1").unwrap();
+    assert_eq!(first_sample(&mut engine), 1.0);
+
+    engine.update_with_code("o: constsig 0.5").unwrap();
+    assert!(engine.is_update_pending());
+    // one beat at 120 bpm
+    assert_eq!(engine.pending_update_at(), Some(22050));
+
+    // the block starting at 22016 is still before the beat
+    for _ in 1..=172 {
+        assert_eq!(first_sample(&mut engine), 1.0);
+    }
+    assert_eq!(first_sample(&mut engine), 0.5);
+    assert!(!engine.is_update_pending());
+}
+
+#[test]
+fn queued_errors_are_kept() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    engine.set_quantize(Quantize::Bars(2));
+    first_sample(&mut engine);
+
+    assert!(engine.update_with_code("o: constsig").is_err());
+    assert!(!engine.is_update_pending());
+
+    engine
+        .update_with_code("o: constsig 1 >> mul ~nope")
+        .unwrap();
+    assert_eq!(engine.pending_update_at(), Some(2 * 88200));
+    while engine.is_update_pending() {
+        assert_eq!(first_sample(&mut engine), 1.0);
+    }
+    assert!(matches!(
+        engine.take_update_error(),
+        Some(EngineError::NonExistReference(..))
+    ));
+    assert_eq!(first_sample(&mut engine), 1.0);
+}
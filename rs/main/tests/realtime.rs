@@ -0,0 +1,160 @@
+mod common;
+
+use common::block;
+use glicol::*;
+use glicol_synth::{GlicolPara, Message};
+
+fn split_block(processor: &mut AudioProcessor<128>) -> Vec<f32> {
+    processor.next_block(&[])[0].to_vec()
+}
+
+#[test]
+fn split_sounds_the_same() {
+    let code = "o: sin 440 >> mul ~am\n~am: sin 0.3 >> mul 0.3 >> add 0.5";
+    let update = "o: saw 220 >> lpf 800 1.0 >> mul ~am\n~am: sin 0.3 >> mul 0.3 >> add 0.5";
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code(code).unwrap();
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code(code).unwrap();
+
+    for _ in 0..10 {
+        assert_eq!(block(&mut engine), split_block(&mut processor));
+    }
+    engine.update_with_code(update).unwrap();
+    controller.update_with_code(update).unwrap();
+    engine.send_msg("o,1,cutoff,400").unwrap();
+    controller.send_msg("o,1,cutoff,400").unwrap();
+    for _ in 0..10 {
+        assert_eq!(block(&mut engine), split_block(&mut processor));
+    }
+}
+
+#[test]
+fn split_an_engine_that_is_running() {
+    let mut engine = Engine::<128>::new();
+    engine
+        .update_with_code("o: constsig 1\n~a: constsig 2")
+        .unwrap();
+    engine.update_with_code("o: constsig 3").unwrap();
+    block(&mut engine);
+
+    let (mut controller, mut processor) = engine.split();
+    assert_eq!(split_block(&mut processor), vec![3.0; 128]);
+    // the removed `~a` left a free index behind, which the new node has to get on both sides
+    controller
+        .update_with_code("o: constsig 3 >> mul 2\n~b: constsig 4")
+        .unwrap();
+    assert_eq!(split_block(&mut processor), vec![6.0; 128]);
+}
+
+#[test]
+fn controller_reports_errors() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code("o: constsig 1").unwrap();
+    assert!(controller
+        .update_with_code("o: constsig 1 >> mul ~b")
+        .is_err());
+    assert_eq!(
+        controller.set_param("o", 1, 0.into(), GlicolPara::Number(1.)),
+        Err(MsgError::NonExistNode("o".into(), 1))
+    );
+    assert_eq!(
+        controller.schedule_msg(Time::Samples(0), "p", 0, Message::SetToNumber(0, 0.)),
+        Err(MsgError::NonExistChain("p".into()))
+    );
+    assert_eq!(
+        controller.node_params("o", 0).unwrap()[0].name,
+        "value".to_string()
+    );
+    assert_eq!(split_block(&mut processor), vec![1.0; 128]);
+}
+
+#[test]
+fn quantized_update_waits_on_the_audio_thread() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.set_bpm(60.);
+    controller.update_with_code("o: constsig 1").unwrap();
+    split_block(&mut processor);
+
+    controller.set_quantize(Quantize::Beat);
+    controller.update_with_code("o: constsig 2").unwrap();
+    // one beat at 60 bpm is 44100 samples, which falls in block 344
+    assert_eq!(controller.pending_update_at(), Some(44100));
+    while processor.clock() < 44100 {
+        assert_eq!(split_block(&mut processor)[0], 1.0);
+    }
+    assert_eq!(split_block(&mut processor)[0], 2.0);
+    assert!(!controller.is_update_pending());
+}
+
+#[test]
+fn scheduled_messages_land_on_the_sample() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code("o: constsig 1").unwrap();
+    controller
+        .schedule(
+            Time::Samples(300),
+            "o",
+            0,
+            0.into(),
+            GlicolPara::Number(0.5),
+        )
+        .unwrap();
+    // dropped along with the node it was scheduled for
+    controller
+        .schedule(Time::Samples(600), "o", 0, 0.into(), GlicolPara::Number(0.))
+        .unwrap();
+
+    split_block(&mut processor);
+    split_block(&mut processor);
+    let third = split_block(&mut processor);
+    assert_eq!(third[300 - 256 - 1], 1.0);
+    assert_eq!(third[300 - 256], 0.5);
+
+    controller.update_with_code("o: constsig 0.5").unwrap();
+    for _ in 0..3 {
+        assert_eq!(split_block(&mut processor), vec![0.5; 128]);
+    }
+}
+
+#[test]
+fn crossfade_on_the_audio_thread() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.set_crossfade(10.);
+    controller.update_with_code("o: constsig 1").unwrap();
+    for _ in 0..5 {
+        split_block(&mut processor);
+    }
+    controller.update_with_code("o: constsig 0").unwrap();
+    let first = split_block(&mut processor);
+    assert!(first[0] > first[127] && first[127] > 0.);
+    for _ in 0..4 {
+        split_block(&mut processor);
+    }
+    assert_eq!(split_block(&mut processor), vec![0.0; 128]);
+
+    // the old node goes once the controller notices the fade is over
+    controller.collect_garbage();
+    assert_eq!(split_block(&mut processor), vec![0.0; 128]);
+}
+
+#[test]
+fn threads() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller
+        .update_with_code("o: sin 440 >> mul 0.5")
+        .unwrap();
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for _ in 0..2000 {
+                let out = processor.next_block(&[]);
+                assert!(out[0].iter().all(|x| x.abs() <= 1.0));
+            }
+        });
+        for i in 0..200 {
+            let code = format!("o: sin {} >> mul 0.5\n~m{i}: saw {i}", 220 + i);
+            controller.update_with_code(&code).unwrap();
+            controller.send_msg("o,1,0,0.25").unwrap();
+        }
+    });
+}
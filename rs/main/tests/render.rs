@@ -0,0 +1,76 @@
+use glicol::*;
+
+#[test]
+fn render_exact_length() {
+    let mut engine = Engine::<128>::new();
+    let audio = engine
+        .render("o: sin 440 >> mul 0.5", RenderLength::Seconds(0.01))
+        .unwrap();
+    assert_eq!(audio.sr, 44100);
+    assert_eq!(audio.channels.len(), 2);
+    assert_eq!(audio.frames(), 441);
+    assert!(audio.channels[0].iter().any(|s| s.abs() > 0.1));
+}
+
+#[test]
+fn render_bars() {
+    let mut engine = Engine::<128>::new();
+    engine.set_bpm(120.);
+    let audio = engine.render("o: sin 440", RenderLength::Bars(1.)).unwrap();
+    assert_eq!(audio.frames(), 2 * 44100);
+}
+
+#[test]
+fn long_lengths_are_exact() {
+    assert_eq!(RenderLength::Bars(999.).to_frames(44100, 93.), 113_692_645);
+    assert_eq!(
+        RenderLength::Seconds(3000.25).to_frames(44100, 120.),
+        132_311_025
+    );
+}
+
+#[test]
+fn wav_header() {
+    let mut engine = Engine::<128>::new();
+    let audio = engine
+        .render("o: sin 440", RenderLength::Frames(100))
+        .unwrap();
+
+    let wav = audio
+        .to_wav_bytes(&WavSpec::new().bit_depth(BitDepth::Int16))
+        .unwrap();
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..12], b"WAVE");
+    assert_eq!(wav.len(), 44 + 100 * 2 * 2);
+
+    let wav = audio
+        .to_wav_bytes(&WavSpec::new().bit_depth(BitDepth::Int24).dither(true))
+        .unwrap();
+    assert_eq!(wav.len(), 44 + 100 * 2 * 3);
+
+    // extended fmt chunk plus fact chunk
+    let wav = audio
+        .to_wav_bytes(&WavSpec::new().bit_depth(BitDepth::Float32))
+        .unwrap();
+    assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 3);
+    assert_eq!(wav.len(), 58 + 100 * 2 * 4);
+}
+
+#[test]
+fn stems_null_against_master() {
+    let mut engine = Engine::<128>::new();
+    let rendered = engine
+        .render_stems(
+            "o: sin 440 >> mul ~am\n~am: sin 0.3 >> mul 0.5\nb: saw 110 >> mul 0.2",
+            RenderLength::Frames(1000),
+        )
+        .unwrap();
+    assert_eq!(rendered.stems.len(), 3);
+    let master = &rendered.master;
+    for (c, chan) in master.channels.iter().enumerate() {
+        for (i, s) in chan.iter().enumerate() {
+            let sum = rendered.stems["o"].channels[c][i] + rendered.stems["b"].channels[c][i];
+            assert!((s - sum).abs() < 1e-6);
+        }
+    }
+}
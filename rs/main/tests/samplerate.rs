@@ -0,0 +1,154 @@
+mod common;
+
+use common::render;
+use glicol::*;
+use glicol_synth::{GlicolPara, Ramp, Smoothing};
+
+fn impulses(out: &[f32]) -> Vec<usize> {
+    (0..out.len()).filter(|&i| out[i] == 1.0).collect()
+}
+
+#[test]
+fn same_as_starting_at_that_rate() {
+    for code in [
+        "o: sin 440 >> mul ~env\n~env: imp 2 >> envperc 0.01 0.2",
+        "o: imp 2 >> bd 0.2",
+        "o: imp 2 >> sn 0.2",
+        "o: imp 2 >> hh 0.2",
+        "o: imp 2 >> sawsynth 0.01 0.2",
+        "o: imp 2 >> squsynth 0.01 0.2",
+        "o: imp 2 >> trisynth 0.01 0.2",
+        "o: imp 2 >> bd 0.2 >> plate 0.5",
+    ] {
+        let mut switched = Engine::<128>::new();
+        switched.update_with_code(code).unwrap();
+        switched.set_sr(48000);
+        let mut fresh = Engine::<128>::new();
+        fresh.set_sr(48000);
+        fresh.update_with_code(code).unwrap();
+        assert_eq!(
+            render(&mut switched, 200),
+            render(&mut fresh, 200),
+            "{code}"
+        );
+    }
+}
+
+#[test]
+fn nodes_carry_on_at_the_new_rate() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: imp 10").unwrap();
+    let before = impulses(&render(&mut engine, 80));
+    assert_eq!(before, vec![0, 4410, 8820]);
+
+    engine.set_sr(48000);
+    let after = impulses(&render(&mut engine, 100));
+    // no restart, the next impulse is still the better part of a period away
+    assert!(after[0] > 2000);
+    for pair in after.windows(2) {
+        assert_eq!(pair[1] - pair[0], 4800);
+    }
+}
+
+#[test]
+fn delays_keep_their_length_in_time() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: imp 1 >> delayms 10").unwrap();
+    engine.set_sr(48000);
+    assert_eq!(impulses(&render(&mut engine, 10)), vec![480]);
+}
+
+#[test]
+fn switch_a_split_engine() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code("o: imp 10").unwrap();
+    controller.set_sr(48000);
+    let out: Vec<f32> = (0..100)
+        .flat_map(|_| processor.next_block(&[])[0].to_vec())
+        .collect();
+    assert_eq!(impulses(&out)[..3], [0, 4800, 9600]);
+}
+
+#[test]
+fn scheduled_times_keep_their_length_in_time() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    render(&mut engine, 1);
+    engine
+        .schedule(
+            Time::Samples(4410),
+            "o",
+            0,
+            0.into(),
+            GlicolPara::Number(0.),
+        )
+        .unwrap();
+    engine.set_quantize(Quantize::Beat);
+    engine.update_with_code("o: constsig 0.5").unwrap();
+    engine.set_sr(48000);
+    // 4282 and 21922 samples from the clock at 44100 Hz
+    assert_eq!(engine.pending_update_at(), Some(128 + 23861));
+    let out = render(&mut engine, 40);
+    assert_eq!((out[4660], out[4661]), (1.0, 0.0));
+}
+
+#[test]
+fn scheduled_times_on_a_split_engine() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.update_with_code("o: constsig 1").unwrap();
+    processor.next_block(&[]);
+    controller
+        .schedule(
+            Time::Samples(4410),
+            "o",
+            0,
+            0.into(),
+            GlicolPara::Number(0.),
+        )
+        .unwrap();
+    controller.set_sr(48000);
+    let out: Vec<f32> = (0..40)
+        .flat_map(|_| processor.next_block(&[])[0].to_vec())
+        .collect();
+    assert_eq!((out[4660], out[4661]), (1.0, 0.0));
+}
+
+#[test]
+fn ramps_and_smoothing_keep_their_length_in_time() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    engine
+        .schedule_ramp(Time::Samples(0), "o", 0, 0.into(), 0., Ramp::Linear(4410))
+        .unwrap();
+    render(&mut engine, 10);
+    engine.set_sr(48000);
+    // 3130 samples left at 44100 Hz
+    let out = render(&mut engine, 30);
+    assert!(out[3400] > 0.);
+    assert_eq!(out[3410], 0.);
+
+    for smoothing_first in [true, false] {
+        let mut engine = Engine::<128>::new();
+        if smoothing_first {
+            engine.set_smoothing(Smoothing::Linear(441));
+            engine.update_with_code("o: constsig 1").unwrap();
+        } else {
+            engine.update_with_code("o: constsig 1").unwrap();
+            engine.set_smoothing(Smoothing::Linear(441));
+        }
+        engine.set_sr(48000);
+        // and a node added at the new rate gets the engine's smoothing at that rate
+        engine
+            .update_with_code("o: constsig 1\np: constsig 1")
+            .unwrap();
+        render(&mut engine, 1);
+        for chain in ["o", "p"] {
+            engine
+                .set_param(chain, 0, 0.into(), GlicolPara::Number(0.))
+                .unwrap();
+        }
+        let out = render(&mut engine, 5);
+        assert!(out[470] > 0.);
+        assert_eq!(out[490], 0.);
+    }
+}
@@ -0,0 +1,287 @@
+#![cfg(feature = "use-samples")]
+
+mod common;
+
+use common::block;
+use glicol::*;
+use glicol_synth::{Interpolation, Sample};
+use std::sync::Arc;
+
+#[test]
+fn replace_while_playing() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    engine
+        .update_with_code("o: mix ~s ~p\n~s: imp 1 >> sp \\a\n~p: psampler \"\\a@0\"(1)")
+        .unwrap();
+    assert_eq!(block(&mut engine), vec![1.0; 128]);
+
+    // the notes that are playing switch to the new data
+    engine.add_sample("\\a", vec![0.25; 20000], 1, 44100);
+    assert_eq!(block(&mut engine), vec![0.5; 128]);
+}
+
+#[test]
+fn remove_while_playing() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    engine.update_with_code("o: imp 1 >> sp \\a").unwrap();
+    block(&mut engine);
+
+    assert!(engine.remove_sample("\\a"));
+    assert!(!engine.remove_sample("\\a"));
+    let err = engine
+        .update_with_code("o: imp 1 >> sp \\a\n~x: imp 1 >> sp \\a")
+        .unwrap_err();
+    assert!(matches!(err, EngineError::NonExistSample(..)));
+    // the sampler that has it carries on
+    assert_eq!(block(&mut engine), vec![0.5; 128]);
+}
+
+#[test]
+fn freed_once_no_node_plays_it() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    engine.add_sample("\\b", vec![0.5; 20000], 1, 44100);
+    let a = Arc::downgrade(&engine.samples_dict["\\a"].data);
+    let b = Arc::downgrade(&engine.samples_dict["\\b"].data);
+    engine
+        .update_with_code("o: imp 1 >> sp \\a\n~b: imp 1 >> sp \\b")
+        .unwrap();
+    block(&mut engine);
+
+    engine.remove_sample("\\a");
+    engine.add_sample("\\b", vec![0.25; 20000], 1, 44100);
+    assert!(a.strong_count() > 0);
+    engine.update_with_code("o: sin 440").unwrap();
+    assert_eq!((a.strong_count(), b.strong_count()), (0, 0));
+
+    let c = Arc::downgrade(&engine.samples_dict["\\b"].data);
+    engine.update_with_code("o: imp 1 >> sp \\b").unwrap();
+    engine.reset();
+    assert_eq!(c.strong_count(), 0);
+}
+
+#[test]
+fn replace_in_a_split_engine() {
+    let (mut controller, mut processor) = Engine::<128>::new().split();
+    controller.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    controller.update_with_code("o: imp 1 >> sp \\a").unwrap();
+    processor.next_block(&[]);
+    controller.add_sample("\\a", vec![0.25; 20000], 1, 44100);
+    assert_eq!(processor.next_block(&[])[0].to_vec(), vec![0.25; 128]);
+}
+
+fn sine(hz: f32, sr: usize, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| (std::f32::consts::TAU * hz * i as f32 / sr as f32).sin())
+        .collect()
+}
+
+#[test]
+fn interpolations_meet_at_the_frames() {
+    let data = sine(3000., 44100, 200);
+    for interpolation in [
+        Interpolation::Linear,
+        Interpolation::Cubic,
+        Interpolation::Sinc,
+    ] {
+        let sample = Sample::new(data.clone(), 1, 44100).interpolation(interpolation);
+        for (i, x) in data.iter().enumerate() {
+            assert!((sample.read(0, i as f32, 1.0) - x).abs() < 1e-5);
+        }
+    }
+    let ramp = Sample::new(vec![0.0, 1.0, 2.0, 3.0], 1, 44100);
+    assert_eq!(ramp.read(0, 1.25, 1.0), 1.25);
+    let cubic = ramp.interpolation(Interpolation::Cubic);
+    assert_eq!(cubic.read(0, 1.25, 1.0), 1.25);
+}
+
+#[test]
+fn sinc_filters_what_would_alias() {
+    // close to the top of the band, played an octave up
+    let data = sine(20000., 44100, 4000);
+    let power = |interpolation| {
+        let sample = Sample::new(data.clone(), 1, 44100).interpolation(interpolation);
+        (1000..1500)
+            .map(|i| sample.read(0, i as f32 * 2.0 + 0.5, 2.0).powi(2))
+            .sum::<f32>()
+            / 500.
+    };
+    assert!(power(Interpolation::Linear) > 0.01);
+    assert!(power(Interpolation::Sinc) < 1e-4);
+}
+
+#[test]
+fn resample_keeps_the_pitch() {
+    let sample = Sample::new(sine(1000., 48000, 48000), 1, 48000);
+    let resampled = sample.resample(44100);
+    assert_eq!((resampled.sr, resampled.frames()), (44100, 44100));
+    let expected = sine(1000., 44100, 44100);
+    // away from the ends, where the sinc fades into silence
+    for (x, y) in resampled.data[100..44000].iter().zip(&expected[100..]) {
+        assert!((x - y).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn interpolation_per_sample() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    engine.update_with_code("o: imp 1 >> sp \\a").unwrap();
+    assert!(engine.set_interpolation("\\a", Interpolation::Cubic));
+    assert!(!engine.set_interpolation("\\b", Interpolation::Cubic));
+    assert_eq!(
+        engine.samples_dict["\\a"].interpolation,
+        Interpolation::Cubic
+    );
+    assert_eq!(block(&mut engine), vec![0.5; 128]);
+
+    engine.set_resample_samples(true);
+    engine.add_sample("\\b", sine(1000., 48000, 4800), 1, 48000);
+    let sample = &engine.samples_dict["\\b"];
+    assert_eq!((sample.sr, sample.frames()), (44100, 4410));
+}
+
+// a mono sample whose frames count up from 0
+fn add_ramp(engine: &mut Engine<128>, name: &str, frames: usize) {
+    let ramp: Vec<f32> = (0..frames).map(|i| i as f32).collect();
+    engine.add_sample(name, ramp, 1, 44100);
+}
+
+fn play(code: &str, frames: usize) -> Vec<f32> {
+    let mut engine = Engine::<128>::new();
+    add_ramp(&mut engine, "\\r", frames);
+    engine.update_with_code(code).unwrap();
+    block(&mut engine)
+}
+
+#[test]
+fn start_end_and_reverse() {
+    assert_eq!(
+        play("o: imp 1 >> sp \\r start=0.5", 201)[..3],
+        [100., 101., 102.]
+    );
+    assert_eq!(
+        play("o: imp 1 >> sp \\r start=10_ms", 1000)[..2],
+        [441., 442.]
+    );
+    let out = play("o: imp 1 >> sp \\r end=0.1", 201);
+    assert_eq!(out[20], 20.);
+    assert_eq!(out[21..], vec![0.; 107]);
+    let out = play("o: imp 1 >> sp \\r start=0.1 end=0.2 reverse", 201);
+    assert_eq!(out[..3], [40., 39., 38.]);
+    assert_eq!(out[20], 20.);
+    assert_eq!(out[21..], vec![0.; 107]);
+}
+
+#[test]
+fn pitch_as_number_or_reference() {
+    assert_eq!(play("o: imp 1 >> sp \\r pitch=2", 400)[..3], [0., 2., 4.]);
+    assert_eq!(
+        play("o: imp 1 >> sp \\r pitch=~p\n~p: sig 0.5", 400)[..3],
+        [0., 0.5, 1.]
+    );
+
+    let mut engine = Engine::<128>::new();
+    add_ramp(&mut engine, "\\r", 400);
+    engine.update_with_code("o: imp 1 >> sp \\r").unwrap();
+    engine
+        .set_param("o", 1, "pitch".into(), glicol_synth::GlicolPara::Number(3.))
+        .unwrap();
+    assert_eq!(block(&mut engine)[..3], [0., 3., 6.]);
+}
+
+#[test]
+fn loops() {
+    let out = play("o: imp 1 >> sp \\r loop=0.2:0.5", 11);
+    assert_eq!(out[..10], [0., 1., 2., 3., 4., 2., 3., 4., 2., 3.]);
+    // a looping sampler keeps playing
+    assert_eq!(out[127], 4.);
+    let out = play("o: imp 1 >> sp \\r pingpong=0.2:0.5", 11);
+    assert_eq!(out[..10], [0., 1., 2., 3., 4., 5., 4., 3., 2., 3.]);
+    let out = play("o: imp 1 >> sp \\r end=0.4 loop reverse", 11);
+    assert_eq!(out[..7], [4., 3., 2., 1., 0., 3., 2.]);
+
+    // a new trigger starts it over instead of adding a voice
+    let out = play("o: imp 22050 >> sp \\r loop", 11);
+    assert_eq!(out[..4], [0., 1., 0., 1.]);
+}
+
+#[test]
+fn rate_amp_and_pan_per_event() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\a", vec![0.5; 20000], 1, 44100);
+    engine
+        .update_with_code("o: psampler \"\\a@0:amp=0.5:pan=0.5\"(1)")
+        .unwrap();
+    let out = engine.next_block(&[]);
+    assert_eq!((out[0][0], out[1][0]), (0.125, 0.25));
+
+    let mut engine = Engine::<128>::new();
+    add_ramp(&mut engine, "\\r", 400);
+    engine
+        .update_with_code("o: psampler \"\\r@0:rate=2 \\r@0:pan=-1\"(1)")
+        .unwrap();
+    let out = engine.next_block(&[]);
+    // both hits on the left, only the faster one on the right
+    assert!((out[0][10] - 30.).abs() < 0.1);
+    assert!((out[1][10] - 20.).abs() < 0.1);
+
+    let err = engine
+        .update_with_code("o: sin 440 >> lpf \"300@0:amp=2\"(1) 1.0")
+        .unwrap_err();
+    assert!(matches!(err, EngineError::InvalidParameter(..)));
+    // a voice that doesn't move would never end
+    for rate in ["0", "-1.5"] {
+        let code = format!("o: psampler \"\\r@0:rate={rate}\"(1)");
+        let err = engine.update_with_code(&code).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidParameter(..)));
+    }
+}
+
+// the first channel of the block in which the pattern has been playing for `steps`
+fn after(engine: &mut Engine<128>, steps: usize) -> Vec<f32> {
+    for _ in 1..steps.div_ceil(128) {
+        engine.next_block(&[]);
+    }
+    block(engine)
+}
+
+#[test]
+fn choke_groups() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\oh", vec![0.5; 44100], 1, 44100);
+    engine.add_sample("\\ch", vec![0.25; 44100], 1, 44100);
+    // the closed hat starts at step 88
+    let code = "o: psampler \"\\oh@0 \\ch@0.001\"(1)";
+    engine.update_with_code(code).unwrap();
+    assert_eq!(after(&mut engine, 1000)[127], 0.75);
+
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\oh", vec![0.5; 44100], 1, 44100);
+    engine.add_sample("\\ch", vec![0.25; 44100], 1, 44100);
+    assert!(engine.set_choke_group("\\oh", Some(1)));
+    assert!(engine.set_choke_group("\\ch", Some(1)));
+    assert!(!engine.set_choke_group("\\bd", Some(1)));
+    engine.update_with_code(code).unwrap();
+    let out = block(&mut engine);
+    // it fades out instead of stopping at once
+    assert!(out[90] > 0.7 && out[90] < 0.75);
+    assert_eq!(after(&mut engine, 1000)[127], 0.25);
+}
+
+#[test]
+fn voice_limits() {
+    let mut engine = Engine::<128>::new();
+    engine.add_sample("\\a", vec![0.5; 44100], 1, 44100);
+    assert!(engine.set_max_voices("\\a", 2));
+    engine
+        .update_with_code("o: psampler \"\\a@0 \\a@0.0005 \\a@0.001\"(1)")
+        .unwrap();
+    let out = block(&mut engine);
+    assert_eq!(out[60], 1.0);
+    // the oldest one makes way for the third
+    assert!(out[100] > 1.0 && out[100] < 1.5);
+    assert_eq!(after(&mut engine, 1000)[127], 1.0);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::block;
+use glicol::*;
+use glicol_synth::{GlicolPara, Message, Ramp};
+
+#[test]
+fn step_lands_on_the_sample() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    engine
+        .schedule(
+            Time::Samples(100),
+            "o",
+            0,
+            0.into(),
+            GlicolPara::Number(0.5),
+        )
+        .unwrap();
+    engine
+        .schedule(
+            Time::Samples(300),
+            "o",
+            0,
+            0.into(),
+            GlicolPara::Number(0.25),
+        )
+        .unwrap();
+
+    let first = block(&mut engine);
+    assert_eq!(first[99], 1.0);
+    assert_eq!(first[100], 0.5);
+    assert_eq!(block(&mut engine), vec![0.5; 128]);
+    let third = block(&mut engine);
+    assert_eq!(third[300 - 256 - 1], 0.5);
+    assert_eq!(third[300 - 256], 0.25);
+}
+
+#[test]
+fn bars_follow_the_bpm() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    engine.set_bpm(60.);
+    engine
+        .schedule(
+            Time::Bars(0.25),
+            "o",
+            0,
+            "value".into(),
+            GlicolPara::Number(0.),
+        )
+        .unwrap();
+
+    // one beat at 60 bpm is 44100 samples, which is 68 samples into block 344
+    for _ in 0..344 {
+        assert_eq!(block(&mut engine)[127], 1.0);
+    }
+    let last = block(&mut engine);
+    assert_eq!(last[67], 1.0);
+    assert_eq!(last[68], 0.0);
+}
+
+#[test]
+fn linear_ramp() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 0").unwrap();
+    engine
+        .schedule_ramp(Time::Samples(10), "o", 0, 0.into(), 1., Ramp::Linear(100))
+        .unwrap();
+
+    let out = block(&mut engine);
+    assert_eq!(out[10], 0.0);
+    assert!((out[60] - 0.5).abs() < 1e-5);
+    assert_eq!(out[110], 1.0);
+    assert_eq!(out[127], 1.0);
+}
+
+#[test]
+fn exponential_ramp() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    engine
+        .schedule_ramp(
+            Time::Samples(0),
+            "o",
+            0,
+            0.into(),
+            4.,
+            Ramp::Exponential(128),
+        )
+        .unwrap();
+
+    let out = block(&mut engine);
+    assert!((out[64] - 2.0).abs() < 1e-4);
+    assert_eq!(block(&mut engine)[0], 4.0);
+}
+
+#[test]
+fn schedule_checks_the_target() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1").unwrap();
+    assert_eq!(
+        engine.schedule(Time::Samples(0), "o", 3, 0.into(), GlicolPara::Number(0.)),
+        Err(MsgError::NonExistNode("o".into(), 3))
+    );
+    assert!(engine
+        .schedule_ramp(Time::Beats(1.), "o", 0, "freq".into(), 1., Ramp::Linear(10))
+        .is_err());
+    assert_eq!(
+        engine.schedule_msg(Time::Samples(0), "p", 0, Message::SetToNumber(0, 0.)),
+        Err(MsgError::NonExistChain("p".into()))
+    );
+}
+
+#[test]
+fn lands_on_a_node_driven_by_a_reference() {
+    let run = |code: &str| {
+        let mut engine = Engine::<128>::new();
+        engine.update_with_code(code).unwrap();
+        engine
+            .schedule(
+                Time::Samples(200),
+                "o",
+                1,
+                "q".into(),
+                GlicolPara::Number(5.),
+            )
+            .unwrap();
+        (0..3).flat_map(|_| block(&mut engine)).collect::<Vec<_>>()
+    };
+    // the cutoff coming from ~c leaves the q to the schedule
+    let driven = run("o: constsig 1 >> lpf ~c 1.0\n~c: constsig 1000");
+    let fixed = run("o: constsig 1 >> lpf 1000 1.0");
+    for (x, y) in driven.iter().zip(&fixed) {
+        assert!((x - y).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::block;
+use glicol::*;
+use glicol_synth::{GlicolPara, Smoothing};
+
+#[test]
+fn off_by_default() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1 >> mul 0").unwrap();
+    block(&mut engine);
+    engine
+        .set_param("o", 1, 0.into(), GlicolPara::Number(1.))
+        .unwrap();
+    assert_eq!(block(&mut engine), vec![1.0; 128]);
+}
+
+#[test]
+fn linear_smoothing() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 1 >> mul 0").unwrap();
+    engine
+        .set_param_smoothing("o", 1, "value".into(), Smoothing::Linear(100))
+        .unwrap();
+    engine
+        .set_param("o", 1, 0.into(), GlicolPara::Number(1.))
+        .unwrap();
+
+    let out = block(&mut engine);
+    assert_eq!(out[0], 0.0);
+    assert!((out[50] - 0.5).abs() < 1e-5);
+    assert_eq!(out[100], 1.0);
+    assert!(out.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn one_pole_smoothing() {
+    let mut engine = Engine::<128>::new();
+    engine.update_with_code("o: constsig 0").unwrap();
+    engine.set_smoothing(Smoothing::OnePole(10));
+    engine
+        .set_param("o", 0, 0.into(), GlicolPara::Number(1.))
+        .unwrap();
+
+    let out = block(&mut engine);
+    // about two thirds of the way after one time constant
+    assert!((out[10] - 0.632).abs() < 0.01);
+    assert_eq!(out[80], 1.0);
+}
+
+#[test]
+fn default_reaches_new_nodes() {
+    let mut engine = Engine::<128>::new();
+    engine.set_smoothing(Smoothing::Linear(128));
+    engine.update_with_code("o: sin 440 >> mul 0").unwrap();
+    engine
+        .set_param("o", 1, 0.into(), GlicolPara::Number(1.))
+        .unwrap();
+
+    // the gain only gets to 1 at the end of the block, so nothing is louder than the ramp
+    let out = block(&mut engine);
+    assert!(out
+        .iter()
+        .enumerate()
+        .all(|(i, x)| x.abs() <= i as f32 / 128. + 1e-5));
+}
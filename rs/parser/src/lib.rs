@@ -14,6 +14,10 @@ pub use util::ToInnerOwned;
 pub struct GlicolParser;
 
 pub fn get_ast(code: &str) -> Result<nodes::Ast<'_>, Box<Error<Rule>>> {
+    get_spanned_ast(code).map(|spanned| spanned.ast)
+}
+
+pub fn get_spanned_ast(code: &str) -> Result<nodes::SpannedAst<'_>, Box<Error<Rule>>> {
     let mut block = GlicolParser::parse(Rule::block, code)?;
 
     // this can be a comment though, but we call it a line
@@ -24,6 +28,8 @@ pub fn get_ast(code: &str) -> Result<nodes::Ast<'_>, Box<Error<Rule>>> {
             .to_err_with_positives([Rule::line])
     })?;
 
+    let mut spans = HashMap::new();
+
     //for line in lines.into_inner() {
     let nodes = lines.into_inner()
         .filter(|line| line.as_rule() == Rule::line)
@@ -45,7 +51,9 @@ pub fn get_ast(code: &str) -> Result<nodes::Ast<'_>, Box<Error<Rule>>> {
                 // if it's not, then report an error
                 .ok_or_else(|| line_end.to_err_with_positives([Rule::chain]))?;
 
+            let mut chain_spans = vec![];
             let components = chain.into_inner().map(|node_pair| {
+                chain_spans.push(node_pair.as_span());
                 let node_end = node_pair.as_end_span();
                 let node = node_pair.into_inner()
                     .next()
@@ -87,6 +95,8 @@ pub fn get_ast(code: &str) -> Result<nodes::Ast<'_>, Box<Error<Rule>>> {
                     Rule::adsr => { Component::Adsr(nodes::Adsr::parse(node)?) },
                     Rule::plate => { Component::Plate(nodes::Plate::parse(node)?) },
                     Rule::get => { Component::Get(nodes::Get::parse(node)?) },
+                    Rule::fb => { Component::Feedback(nodes::Feedback::parse(node)?) },
+                    Rule::comb => { Component::Comb(nodes::Comb::parse(node)?) },
                     Rule::noise => { Component::Noise(nodes::Noise::parse(node)?) },
                     Rule::meta => { Component::Meta(nodes::Meta::parse(node)?) },
                     Rule::expr => { Component::Expr(nodes::Expr::parse(node)?) },
@@ -98,6 +108,7 @@ pub fn get_ast(code: &str) -> Result<nodes::Ast<'_>, Box<Error<Rule>>> {
 
                 Ok(component)
             }).collect::<Result<Vec<_>, _>>()?;
+            spans.insert(name, chain_spans);
 
             Result::<_, Box<Error<Rule>>>::Ok((
                 name,
@@ -105,5 +116,8 @@ pub fn get_ast(code: &str) -> Result<nodes::Ast<'_>, Box<Error<Rule>>> {
             ))
         }).collect::<Result<HashMap<_, _>, _>>()?;
 
-    Ok(nodes::Ast { nodes })
+    Ok(nodes::SpannedAst {
+        ast: nodes::Ast { nodes },
+        spans,
+    })
 }
@@ -19,6 +19,14 @@ pub struct Ast<'ast> {
     pub nodes: HashMap<&'ast str, Vec<Component<'ast>>>,
 }
 
+/// An [`Ast`] together with the source location of every component, so that errors found after
+/// parsing can still point at the code. `spans[chain][i]` is the span of `ast.nodes[chain][i]`.
+#[derive(yoke::Yokeable, Debug, PartialEq)]
+pub struct SpannedAst<'ast> {
+    pub ast: Ast<'ast>,
+    pub spans: HashMap<&'ast str, Vec<Span<'ast>>>,
+}
+
 pub trait Node<'ast>
 where
     Self: Sized,
@@ -90,6 +98,8 @@ pub enum Component<'ast> {
     EnvPerc(EnvPerc),
     Adsr(Adsr),
     Get(Get<'ast>),
+    Feedback(Feedback<'ast>),
+    Comb(Comb),
     Noise(Noise),
     Meta(Meta<'ast>),
     Expr(Expr<'ast>),
@@ -153,7 +163,12 @@ impl<'ast> Component<'ast> {
                 delay: NumberOrRef::Ref(r),
                 gain: _,
             })
-            | Self::Get(Get { reference: r }) => vec![r],
+            | Self::Sp(Sp {
+                pitch: NumberOrRef::Ref(r),
+                ..
+            })
+            | Self::Get(Get { reference: r })
+            | Self::Feedback(Feedback { reference: r }) => vec![r],
 
             Self::Seq(Seq { events }) => events
                 .iter()
@@ -420,6 +435,28 @@ impl<'ast> Node<'ast> for Get<'ast> {
     }
 }
 
+/// `fb ~ref`: the output of `~ref` one block late, which is what lets a chain refer back to
+/// itself.
+#[derive(PartialEq, Debug)]
+pub struct Feedback<'ast> {
+    pub reference: &'ast str,
+}
+
+impl<'ast> Node<'ast> for Feedback<'ast> {
+    #[cfg_attr(test, trace::trace(prefix_enter = "[+ Feedback]"))]
+    fn parse_from_iter(
+        pairs: &mut Pairs<'ast, Rule>,
+        span: Span<'ast>,
+    ) -> Result<Self, Box<Error<Rule>>> {
+        pairs
+            .next()
+            .ok_or_else(|| span.as_end_span().to_err_with_positives([Rule::reference]))
+            .map(|p| Self {
+                reference: p.as_str(),
+            })
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub struct Noise {
     pub seed: usize,
@@ -615,9 +652,102 @@ impl<'ast> Node<'ast> for Mix<'ast> {
     }
 }
 
+/// Where in a sample a sampler starts, ends or loops.
+#[derive(PartialEq, Debug, Clone, Copy, PartialOrd)]
+pub enum SamplePosition {
+    /// From 0 at the first frame to 1 at the last one.
+    Fraction(f32),
+    Milliseconds(f32),
+}
+
+impl Node<'_> for SamplePosition {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, Box<Error<Rule>>> {
+        let end_span = pair.as_end_span();
+        match_or_return_err!(pair,
+            Rule::number => {
+                pair.try_to_parse().map(Self::Fraction)
+            },
+            Rule::ms => {
+                pair.into_inner().next_parsed(end_span).map(Self::Milliseconds)
+            },
+        )
+    }
+
+    fn parse_from_iter(
+        pairs: &mut Pairs<'_, Rule>,
+        span: Span<'_>,
+    ) -> Result<Self, Box<Error<Rule>>> {
+        pairs
+            .next()
+            .ok_or_else(|| {
+                span.as_end_span()
+                    .to_err_with_positives([Rule::number, Rule::ms])
+            })
+            .and_then(Self::parse)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum LoopMode {
+    Forward,
+    PingPong,
+}
+
+/// `loop` or `pingpong`, between `start` and `end` unless it has its own points, as in
+/// `loop=0.2:0.5`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SampleLoop {
+    pub mode: LoopMode,
+    pub points: Option<(SamplePosition, SamplePosition)>,
+}
+
+impl<'ast> Node<'ast> for SampleLoop {
+    fn parse_from_iter(
+        pairs: &mut Pairs<'ast, Rule>,
+        span: Span<'ast>,
+    ) -> Result<Self, Box<Error<Rule>>> {
+        let mode = pairs
+            .next()
+            .ok_or_else(|| span.as_end_span().to_err_with_positives([Rule::loop_mode]))?;
+        let mode = match mode.as_str() {
+            "pingpong" => LoopMode::PingPong,
+            _ => LoopMode::Forward,
+        };
+        let points = match pairs.peek() {
+            Some(_) => Some((
+                SamplePosition::parse_from_iter(pairs, span)?,
+                SamplePosition::parse_from_iter(pairs, span)?,
+            )),
+            None => None,
+        };
+        Ok(Self { mode, points })
+    }
+}
+
+/// `sp \name`, with any of `pitch=`, `start=`, `end=`, `loop`, `pingpong` and `reverse` after
+/// it, e.g. `sp \piano pitch=~mod start=0.1 end=300_ms loop=0.2:0.5`.
 #[derive(PartialEq, Debug)]
 pub struct Sp<'ast> {
     pub sample_sym: &'ast str,
+    pub pitch: NumberOrRef<&'ast str>,
+    pub start: SamplePosition,
+    pub end: SamplePosition,
+    pub looping: Option<SampleLoop>,
+    pub reverse: bool,
+}
+
+impl<'ast> Sp<'ast> {
+    /// The whole sample, once, at its own pitch.
+    pub fn new(sample_sym: &'ast str) -> Self {
+        Self {
+            sample_sym,
+            pitch: NumberOrRef::Number(1.0),
+            start: SamplePosition::Fraction(0.0),
+            end: SamplePosition::Fraction(1.0),
+            looping: None,
+            reverse: false,
+        }
+    }
 }
 
 impl<'ast> Node<'ast> for Sp<'ast> {
@@ -626,12 +756,31 @@ impl<'ast> Node<'ast> for Sp<'ast> {
         pairs: &mut Pairs<'ast, Rule>,
         span: Span<'ast>,
     ) -> Result<Self, Box<Error<Rule>>> {
-        pairs
+        let sym = pairs
             .next()
-            .ok_or_else(|| span.as_end_span().to_err_with_positives([Rule::symbol]))
-            .map(|sym| Self {
-                sample_sym: sym.as_str(),
-            })
+            .ok_or_else(|| span.as_end_span().to_err_with_positives([Rule::symbol]))?;
+        let mut sp = Self::new(sym.as_str());
+
+        for param in pairs {
+            match_or_return_err!(param,
+                Rule::sp_pitch => {
+                    sp.pitch = NumberOrRef::parse_from_iter(&mut param.into_inner(), span)?
+                },
+                Rule::sp_start => {
+                    sp.start = SamplePosition::parse_from_iter(&mut param.into_inner(), span)?
+                },
+                Rule::sp_end => {
+                    sp.end = SamplePosition::parse_from_iter(&mut param.into_inner(), span)?
+                },
+                Rule::sp_loop => {
+                    sp.looping = Some(SampleLoop::parse(param)?)
+                },
+                Rule::sp_reverse => {
+                    sp.reverse = true
+                },
+            );
+        }
+        Ok(sp)
     }
 }
 
@@ -641,9 +790,29 @@ pub enum EventValue<'ast> {
     Number(f32),
 }
 
+/// `:rate=1.5:amp=0.7:pan=-0.5` after the time of an event, which changes how `psampler` plays
+/// that one hit.
+#[derive(PartialEq, Debug, Clone, Copy, PartialOrd)]
+pub struct EventOptions {
+    pub rate: f32,
+    pub amp: f32,
+    /// From -1 for left to 1 for right.
+    pub pan: f32,
+}
+
+impl Default for EventOptions {
+    fn default() -> Self {
+        Self {
+            rate: 1.0,
+            amp: 1.0,
+            pan: 0.0,
+        }
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub struct EventInner<'ast> {
-    pub val_times: Vec<(EventValue<'ast>, f32)>,
+    pub val_times: Vec<(EventValue<'ast>, f32, EventOptions)>,
 }
 
 impl<'ast> Node<'ast> for EventInner<'ast> {
@@ -677,7 +846,22 @@ impl<'ast> Node<'ast> for EventInner<'ast> {
 
                 let time = iter.next_parsed(end_span)?;
 
-                Ok((value, time))
+                let mut options = EventOptions::default();
+                for option in iter {
+                    let end_span = option.as_end_span();
+                    let mut option = option.into_inner();
+                    let key = option
+                        .next()
+                        .ok_or_else(|| end_span.to_err_with_positives([Rule::event_key]))?;
+                    let value = option.next_parsed(end_span)?;
+                    match key.as_str() {
+                        "rate" => options.rate = value,
+                        "amp" => options.amp = value,
+                        _ => options.pan = value,
+                    }
+                }
+
+                Ok((value, time, options))
             })
             .collect::<Result<Vec<_>, _>>()
             .map(|val_times| Self { val_times })
@@ -1072,6 +1256,28 @@ impl Node<'_> for EnvPerc {
     }
 }
 
+#[derive(PartialEq, Debug)]
+pub struct Comb {
+    pub delay: f32,
+    pub feedback: f32,
+    pub damp: f32,
+}
+
+impl Node<'_> for Comb {
+    #[cfg_attr(test, trace::trace(prefix_enter = "[+ Comb]"))]
+    fn parse_from_iter(
+        pairs: &mut Pairs<'_, Rule>,
+        span: Span<'_>,
+    ) -> Result<Self, Box<Error<Rule>>> {
+        let [delay, feedback, damp] = get_f32_arr(pairs, span)?;
+        Ok(Self {
+            delay,
+            feedback,
+            damp,
+        })
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub struct Adsr {
     pub attack: f32,
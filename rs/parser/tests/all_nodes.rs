@@ -238,22 +238,12 @@ fn mix() {
 fn sp() {
     assert_eq!(
         get_ast("o: sp \\808db"),
-        ast_from_nodes([(
-            "o",
-            vec![Component::Sp(Sp {
-                sample_sym: "\\808db"
-            })]
-        )])
+        ast_from_nodes([("o", vec![Component::Sp(Sp::new("\\808db"))])])
     );
 
     assert_eq!(
         get_ast("o: sp \\guitar"),
-        ast_from_nodes([(
-            "o",
-            vec![Component::Sp(Sp {
-                sample_sym: "\\guitar"
-            })]
-        )])
+        ast_from_nodes([("o", vec![Component::Sp(Sp::new("\\guitar"))])])
     );
 }
 
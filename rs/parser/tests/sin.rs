@@ -22,3 +22,42 @@ fn comment_within_chain() {
     );
     assert!(res.is_ok());
 }
+
+// the chain `o` of `code`
+fn chain(code: &str) -> Vec<nodes::Component<'_>> {
+    get_ast(code).unwrap().nodes.remove("o").unwrap()
+}
+
+#[test]
+fn sampler_controls() {
+    use nodes::*;
+    assert_eq!(
+        chain("o: sp \\guitar pitch=~p start=100_ms end=0.5 pingpong=0.2:0.3 reverse"),
+        vec![Component::Sp(Sp {
+            pitch: NumberOrRef::Ref("~p"),
+            start: SamplePosition::Milliseconds(100.),
+            end: SamplePosition::Fraction(0.5),
+            looping: Some(SampleLoop {
+                mode: LoopMode::PingPong,
+                points: Some((SamplePosition::Fraction(0.2), SamplePosition::Fraction(0.3))),
+            }),
+            reverse: true,
+            ..Sp::new("\\guitar")
+        })]
+    );
+    assert_eq!(
+        chain("o: sp \\guitar loop pitch=2"),
+        vec![Component::Sp(Sp {
+            pitch: NumberOrRef::Number(2.),
+            looping: Some(SampleLoop {
+                mode: LoopMode::Forward,
+                points: None,
+            }),
+            ..Sp::new("\\guitar")
+        })]
+    );
+    assert_eq!(
+        chain("o: sp \\808db"),
+        vec![Component::Sp(Sp::new("\\808db"))]
+    );
+}
@@ -0,0 +1,67 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use glicol_synth::{
+    filter::OnePole,
+    operator::{Add, Mul},
+    oscillator::{SawOsc, SinOsc},
+    AudioContext, AudioContextBuilder,
+};
+
+// 100 voices of `saw >> onepole >> mul ~lfo`, where each `~lfo` is a sine going through a
+// line of cheap `mul` and `add` nodes, so 1000 nodes in all and mostly light ones, where the
+// time spent walking the graph shows the most
+fn voices() -> AudioContext<128> {
+    let mut context = AudioContextBuilder::<128>::new()
+        .sr(44100)
+        .channels(2)
+        .max_nodes(1024)
+        .max_edges(1024)
+        .build();
+    for i in 0..100 {
+        let saw = context.add_stereo_node(SawOsc::new().freq(black_box(110. + i as f32)));
+        let filter = context.add_stereo_node(OnePole::new(0.5));
+        let amp = context.add_stereo_node(Mul::new(0.01));
+        context.chain(vec![saw, filter, amp, context.destination]);
+
+        let mut lfo = vec![context.add_stereo_node(SinOsc::new().freq(black_box(0.1 * i as f32)))];
+        for _ in 0..3 {
+            lfo.push(context.add_stereo_node(Mul::new(0.5)));
+            lfo.push(context.add_stereo_node(Add::new(0.01)));
+        }
+        lfo.push(amp);
+        context.chain(lfo);
+    }
+    context
+}
+
+fn large_graph_benchmark(c: &mut Criterion) {
+    let mut context = voices();
+    c.bench_function("large_graph", |b| {
+        b.iter(|| {
+            context.next_block();
+        })
+    });
+
+    // what every block used to cost, when the order was worked out each time
+    let mut context = voices();
+    c.bench_function("large_graph_reordered", |b| {
+        b.iter(|| {
+            context.processor.invalidate();
+            context.next_block();
+        })
+    });
+}
+
+fn parallel_benchmark(c: &mut Criterion) {
+    for threads in [2, 4] {
+        let mut context = voices();
+        context.processor.set_threads(threads);
+        c.bench_function(&format!("large_graph_{threads}_threads"), |b| {
+            b.iter(|| {
+                context.next_block();
+            })
+        });
+    }
+}
+
+criterion_group!(benches, large_graph_benchmark, parallel_benchmark);
+criterion_main!(benches);
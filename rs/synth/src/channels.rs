@@ -0,0 +1,59 @@
+//! How buffers with different channel counts are mixed when nodes connect.
+//!
+//! With `n` channels coming in and `m` going out:
+//!
+//! - `n == m`: channel for channel.
+//! - `n < m` (upmix): output channel `c` takes input channel `c % n`. A mono signal goes to every
+//!   channel, and a stereo one alternates left and right, so on a quad or 8-channel setup the
+//!   left channel ends up on every even speaker and the right one on every odd speaker.
+//! - `n > m` (downmix): input channel `c` is folded onto output channel `c % m`, and every output
+//!   channel is the average of the channels folded onto it. Stereo to mono is `(l + r) / 2`, and
+//!   quad to stereo averages the front and back of each side.
+//!
+//! The rules only make sense when one channel count is a multiple of the other, see [`can_mix`].
+
+use std::ops::Deref;
+
+use crate::Buffer;
+
+/// Whether `from` channels can be mixed into `to`. That is when one count is a multiple of the
+/// other, so that every output channel gets the same share: stereo goes into quad or mono, but not
+/// into 3 channels, where the left channel would end up on two speakers and the right on one.
+pub fn can_mix(from: usize, to: usize) -> bool {
+    from > 0 && to > 0 && (from.is_multiple_of(to) || to.is_multiple_of(from))
+}
+
+/// The input channel that output channel `c` reads when upmixing, or `None` without any input
+/// channels.
+pub fn upmix_channel<T>(from: &[T], c: usize) -> Option<&T> {
+    match from.len() {
+        0 => None,
+        n => Some(&from[c % n]),
+    }
+}
+
+/// Add `from` onto `to`, up- or downmixing following the rules above.
+pub fn mix_into<T, const N: usize>(from: &[T], to: &mut [Buffer<N>])
+where
+    T: Deref<Target = [f32]>,
+{
+    let (n, m) = (from.len(), to.len());
+    if n <= m {
+        for (c, out) in to.iter_mut().enumerate() {
+            if let Some(buf) = upmix_channel(from, c) {
+                for (o, x) in out.iter_mut().zip(buf.iter()) {
+                    *o += x;
+                }
+            }
+        }
+        return;
+    }
+    for (c, out) in to.iter_mut().enumerate() {
+        let gain = 1.0 / (n - c).div_ceil(m) as f32;
+        for buf in from.iter().skip(c).step_by(m) {
+            for (o, x) in out.iter_mut().zip(buf.iter()) {
+                *o += x * gain;
+            }
+        }
+    }
+}
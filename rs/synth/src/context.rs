@@ -1,5 +1,6 @@
 pub use crate::{
-    buffer::Buffer, node::Node, BoxedNodeSend, Message, NodeData, Pass, Processor, Sum2,
+    buffer::Buffer, node::Node, BoxedNodeSend, Clock, Message, NodeData, Pass, Processor, Profile,
+    Sum2,
 };
 use hashbrown::HashMap;
 use petgraph::{graph::NodeIndex, prelude::EdgeIndex};
@@ -123,6 +124,7 @@ impl<const N: usize> AudioContext<N> {
     pub fn reset(&mut self) {
         // self.graph.clear_edges();
         self.graph.clear();
+        self.processor.invalidate();
         self.destination = self.graph.add_node(NodeData::multi_chan_node(
             self.config.channels,
             BoxedNodeSend::<N>::new(Sum2),
@@ -177,13 +179,26 @@ impl<const N: usize> AudioContext<N> {
         )
     }
 
+    /// Remove a node along with its edges, and hand back what it held.
+    pub fn remove_node(&mut self, index: NodeIndex) -> Option<GlicolNodeData<N>> {
+        self.processor.invalidate();
+        self.graph.remove_node(index)
+    }
+
+    pub fn clear_edges(&mut self) {
+        self.processor.invalidate();
+        self.graph.clear_edges();
+    }
+
     pub fn connect(&mut self, from: NodeIndex, to: NodeIndex) -> EdgeIndex {
+        self.processor.invalidate();
         let edge_index = self.graph.add_edge(from, to, ());
         self.graph[to].node.send_msg(Message::Index(from.index()));
         edge_index
     }
 
     pub fn connect_with_order(&mut self, from: NodeIndex, to: NodeIndex, pos: usize) -> EdgeIndex {
+        self.processor.invalidate();
         let edge_index = self.graph.add_edge(from, to, ());
         self.graph[to]
             .node
@@ -192,6 +207,7 @@ impl<const N: usize> AudioContext<N> {
     }
 
     pub fn chain(&mut self, chain: Vec<NodeIndex>) -> Vec<EdgeIndex> {
+        self.processor.invalidate();
         chain
             .windows(2)
             .map(|pair| {
@@ -208,6 +224,7 @@ impl<const N: usize> AudioContext<N> {
         &mut self,
         chain: Vec<GlicolNodeData<N>>,
     ) -> (Vec<NodeIndex>, Vec<EdgeIndex>) {
+        self.processor.invalidate();
         let indices = chain
             .into_iter()
             .map(|node| self.graph.add_node(node))
@@ -231,6 +248,7 @@ impl<const N: usize> AudioContext<N> {
         &mut self,
         chain: Vec<NodeData<BoxedNodeSend<N>, N>>,
     ) -> (Vec<NodeIndex>, Vec<EdgeIndex>) {
+        self.processor.invalidate();
         let v = chain
             .into_iter()
             .map(|node| self.graph.add_node(node))
@@ -245,10 +263,21 @@ impl<const N: usize> AudioContext<N> {
     }
 
     pub fn next_block(&mut self) -> &[Buffer<N>] {
-        self.processor.process(&mut self.graph, self.destination);
+        self.processor
+            .process_in_parallel(&mut self.graph, self.destination);
         &self.graph[self.destination].buffers
     }
 
+    /// Start timing every node with `clock`, or stop with `None`.
+    pub fn set_profiling(&mut self, clock: Option<Clock>) {
+        self.processor.set_clock(clock);
+    }
+
+    /// Where the time went since the last call, per node.
+    pub fn take_profile(&mut self) -> Profile<NodeIndex> {
+        self.processor.take_profile()
+    }
+
     pub fn send_msg(&mut self, index: NodeIndex, msg: Message) {
         self.graph[index].node.send_msg(msg);
     }
@@ -258,4 +287,21 @@ impl<const N: usize> AudioContext<N> {
             nodedata.node.send_msg(msg.clone());
         }
     }
+
+    /// How many channels the input and the destination have.
+    pub fn channels(&self) -> usize {
+        self.config.channels
+    }
+
+    /// The sample rate the nodes were last given.
+    pub fn sr(&self) -> usize {
+        self.config.sr
+    }
+
+    /// Switch to another sample rate. Every node is told, and keeps its state: phases, envelopes
+    /// and sequencer positions carry on at the new rate, while delay lines are reallocated.
+    pub fn set_sr(&mut self, sr: usize) {
+        self.config.sr = sr;
+        self.send_msg_to_all(Message::SetSampleRate(sr));
+    }
 }
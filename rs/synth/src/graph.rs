@@ -20,15 +20,24 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::{buffer::Buffer, node::Input, node::Node, BoxedNode};
+use crate::{
+    buffer::Buffer,
+    mix_into,
+    node::Input,
+    node::Node,
+    pool::Pool,
+    profile::{Clock, Profile},
+    BoxedNode,
+};
+use core::ops::Range;
 use hashbrown::HashMap;
 use petgraph::data::{DataMap, DataMapMut};
 use petgraph::visit::{
     Data,
-    DfsPostOrder,
+    EdgeCount,
     GraphBase,
     IntoNeighborsDirected,
-    Reversed, //NodeCount, NodeIndexable,
+    NodeCount, //NodeIndexable,
     Visitable,
 };
 use petgraph::Incoming;
@@ -37,13 +46,73 @@ pub struct Processor<G, const N: usize>
 where
     G: Visitable,
 {
-    // State related to the traversal of the audio graph starting from the output node.
-    dfs_post_order: DfsPostOrder<G::NodeId, G::Map>,
-    // Solely for collecting the inputs of a node in order to apply its `Node::process` method.
-    inputs: HashMap<usize, Input<N>>,
+    // State related to the traversal of the audio graph starting from the output node: the nodes
+    // still to go, each with whether its inputs have been done.
+    stack: Vec<(G::NodeId, bool)>,
+    // Also kept for the traversal, so that doing it again doesn't allocate while the graph has no
+    // more than `max_nodes` nodes: how far each node is, where to start walking from, and the
+    // nodes in the order the walk finished them.
+    state: Vec<u8>,
+    roots: Vec<G::NodeId>,
+    walked: Vec<G::NodeId>,
+    // The inputs of each node in `order`, for its `Node::process` method. Every node has a map
+    // of its own, so that the order it sees its inputs in only depends on the graph, and not on
+    // which thread processed it or what came before.
+    inputs: Vec<HashMap<usize, Input<N>>>,
+    // The inputs of each node in `order` that have to be mixed to another channel count first.
+    mixes: Vec<Vec<Mix<N>>>,
+    // The order found by the last traversal, with the range of each node's inputs in `sources`.
+    // Both are reused block after block until the graph changes.
+    order: Vec<(G::NodeId, Range<usize>)>,
+    sources: Vec<G::NodeId>,
+    // The output node, node count and edge count the order was worked out for.
+    ordered_for: Option<(G::NodeId, usize, usize)>,
+    // The feedback nodes come first in `order`, and this many of them.
+    feedback: usize,
+    // Where the output node is in `order`.
+    output: usize,
+    // Everything in `order` but the feedback nodes and the output node, split into groups that
+    // don't depend on each other. Each group is a range of positions in `order`, kept in `grouped`.
+    groups: Vec<Range<usize>>,
+    grouped: Vec<usize>,
+    // Scratch space for splitting into groups, kept so that doing it again doesn't allocate while
+    // the graph has no more than `max_nodes` nodes: where each node is in `order`, the group each
+    // one is joined to, how big each group is, and the (group, position) of every member.
+    position: Vec<usize>,
+    parent: Vec<usize>,
+    sizes: Vec<usize>,
+    members: Vec<(usize, usize)>,
+    // The nodes of the current block, for handing over to the pool.
+    nodes: Vec<Raw<()>>,
+    pool: Option<Pool>,
+    // Only set while profiling. The time spent in each node in `order`, and on whole blocks.
+    clock: Option<Clock>,
+    times: Vec<f64>,
+    total: f64,
+    blocks: usize,
     // pub processed: Vec<G::NodeId>
 }
 
+// An input with a different channel count than its node wants, and the buffers that it is mixed
+// into for the node.
+struct Mix<const N: usize> {
+    from: Input<N>,
+    to: Vec<Buffer<N>>,
+}
+
+// A pointer that the pool's threads may share. Each one only dereferences the pointers of the
+// nodes in its own group.
+struct Raw<T>(*mut T);
+
+unsafe impl<T> Send for Raw<T> {}
+unsafe impl<T> Sync for Raw<T> {}
+
+impl<T> Raw<T> {
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
 /// For use as the node weight within a dasp graph. Contains the node and its buffers.
 ///
 /// For a graph to be compatible with a graph **Processor**, its node weights must be of type
@@ -61,24 +130,114 @@ where
     where
         G::Map: Default,
     {
-        let dfs_post_order = DfsPostOrder {
-            stack: Vec::with_capacity(max_nodes),
-            ..Default::default()
-        };
-        let inputs = HashMap::new(); //Vec::with_capacity(max_nodes);
+        let inputs = Vec::with_capacity(max_nodes);
         Self {
-            dfs_post_order,
+            stack: Vec::with_capacity(max_nodes),
+            state: Vec::with_capacity(max_nodes),
+            roots: Vec::with_capacity(max_nodes),
+            walked: Vec::with_capacity(max_nodes),
             inputs,
+            mixes: Vec::with_capacity(max_nodes),
+            order: Vec::with_capacity(max_nodes),
+            sources: Vec::with_capacity(max_nodes),
+            ordered_for: None,
+            feedback: 0,
+            output: 0,
+            groups: Vec::with_capacity(max_nodes),
+            grouped: Vec::with_capacity(max_nodes),
+            position: Vec::with_capacity(max_nodes),
+            parent: Vec::with_capacity(max_nodes),
+            sizes: Vec::with_capacity(max_nodes),
+            members: Vec::with_capacity(max_nodes),
+            nodes: vec![],
+            pool: None,
+            clock: None,
+            times: vec![],
+            total: 0.0,
+            blocks: 0,
         }
     }
+
+    /// Use `threads` threads, the calling one included, for the parts of the graph that don't
+    /// depend on each other, such as chains that only meet at the output node. Only
+    /// [`Self::process_in_parallel`] makes use of them; 1 goes back to a single thread.
+    ///
+    /// The threads wait for every block, so this is meant for heavy patches and offline
+    /// rendering, rather than a small patch on a real-time audio thread.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.pool = (threads > 1).then(|| Pool::new(threads - 1));
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.as_ref().map_or(1, Pool::threads)
+    }
+
+    /// Time every node with `clock` from now on, or stop when it is `None`. See
+    /// [`Self::take_profile`].
+    pub fn set_clock(&mut self, clock: Option<Clock>) {
+        self.clock = clock;
+        self.reset_profile();
+    }
+
+    pub fn clock(&self) -> Option<Clock> {
+        self.clock
+    }
+
+    /// The time spent since the last call, or since the processing order last changed. Empty
+    /// unless a clock has been set.
+    pub fn take_profile(&mut self) -> Profile<G::NodeId> {
+        let nodes = self
+            .order
+            .iter()
+            .zip(&self.times)
+            .map(|((n, _), time)| (*n, *time))
+            .collect();
+        let profile = Profile {
+            blocks: self.blocks,
+            total: self.total,
+            nodes,
+        };
+        self.reset_profile();
+        profile
+    }
+
+    fn reset_profile(&mut self) {
+        self.times.clear();
+        if self.clock.is_some() {
+            self.times.resize(self.order.len(), 0.0);
+        }
+        self.total = 0.0;
+        self.blocks = 0;
+    }
+
+    /// Work out the processing order again on the next block.
+    ///
+    /// Call this after adding or removing edges, or removing nodes, behind the processor's back.
+    /// A change in the number of nodes or edges is noticed anyway, but swapping one edge for
+    /// another is not. The `AudioContext` methods take care of this.
+    pub fn invalidate(&mut self) {
+        self.ordered_for = None;
+    }
+
     pub fn process<T>(&mut self, graph: &mut G, node: G::NodeId)
     where
-        G: Data<NodeWeight = NodeData<T, N>> + DataMapMut,
+        G: Data<NodeWeight = NodeData<T, N>> + DataMapMut + NodeCount + EdgeCount,
         for<'a> &'a G: GraphBase<NodeId = G::NodeId> + IntoNeighborsDirected,
         T: Node<N>,
     {
         process(self, graph, node)
     }
+
+    /// The same as [`Self::process`], but with the threads from [`Self::set_threads`]. Every
+    /// node still sees exactly the same inputs, so the output is identical to `process`.
+    pub fn process_in_parallel<T>(&mut self, graph: &mut G, node: G::NodeId)
+    where
+        G: Data<NodeWeight = NodeData<T, N>> + DataMapMut + NodeCount + EdgeCount,
+        for<'a> &'a G: GraphBase<NodeId = G::NodeId> + IntoNeighborsDirected,
+        T: Node<N> + Send,
+    {
+        process_in_parallel(self, graph, node)
+    }
 }
 
 impl<T, const N: usize> NodeData<T, N> {
@@ -135,32 +294,352 @@ pub fn process<G, T, const N: usize>(
     graph: &mut G,
     node: G::NodeId,
 ) where
-    G: Data<NodeWeight = NodeData<T, N>> + DataMapMut + Visitable + petgraph::visit::NodeIndexable,
+    G: Data<NodeWeight = NodeData<T, N>>
+        + DataMapMut
+        + Visitable
+        + petgraph::visit::NodeIndexable
+        + NodeCount
+        + EdgeCount,
+    for<'a> &'a G: GraphBase<NodeId = G::NodeId> + IntoNeighborsDirected,
+    T: Node<N>,
+{
+    let start = now(processor.clock);
+    prepare(processor, graph, node);
+    process_in_order(processor, graph);
+    finish_block(processor, start);
+}
+
+fn process_in_order<G, T, const N: usize>(processor: &mut Processor<G, N>, graph: &mut G)
+where
+    G: Data<NodeWeight = NodeData<T, N>> + DataMapMut + Visitable,
+    T: Node<N>,
+{
+    const NO_NODE: &str = "no node exists for the given index";
+    let clock = processor.clock;
+    let mut last = now(clock);
+    for (k, (((n, _), inputs), mixes)) in processor
+        .order
+        .iter()
+        .zip(&mut processor.inputs)
+        .zip(&mut processor.mixes)
+        .enumerate()
+    {
+        // Here we used to dereference a raw pointer to the `NodeData`. The only references to the
+        // graph at this point in time are the input references and the node itself. We know that
+        // the input references do not alias out node's mutable reference as we explicitly check
+        // for it while building the order below.
+        let data = graph.node_weight_mut(*n).expect(NO_NODE);
+        run(data, inputs, mixes);
+        lap(clock, &mut last, &mut processor.times, k);
+    }
+}
+
+// Mix the inputs that need it, then process the node.
+fn run<T: Node<N>, const N: usize>(
+    data: &mut NodeData<T, N>,
+    inputs: &mut HashMap<usize, Input<N>>,
+    mixes: &mut [Mix<N>],
+) {
+    for mix in mixes {
+        for buf in &mut mix.to {
+            buf.silence();
+        }
+        mix_into(mix.from.buffers(), &mut mix.to);
+    }
+    data.node.process(inputs, &mut data.buffers);
+}
+
+// How many channels the inputs of a node are mixed to.
+fn input_channels<T: Node<N>, const N: usize>(data: &NodeData<T, N>) -> usize {
+    data.node.input_channels().unwrap_or(data.buffers.len())
+}
+
+fn now(clock: Option<Clock>) -> f64 {
+    clock.map_or(0.0, |now| now())
+}
+
+// Add the time since `last` to the `k`th node's time, and move `last` on to now.
+fn lap(clock: Option<Clock>, last: &mut f64, times: &mut [f64], k: usize) {
+    if let (Some(now), Some(spent)) = (clock, times.get_mut(k)) {
+        let time = now();
+        *spent += time - *last;
+        *last = time;
+    }
+}
+
+fn finish_block<G: Visitable, const N: usize>(processor: &mut Processor<G, N>, start: f64) {
+    if let Some(now) = processor.clock {
+        processor.total += now() - start;
+        processor.blocks += 1;
+    }
+}
+
+pub fn process_in_parallel<G, T, const N: usize>(
+    processor: &mut Processor<G, N>,
+    graph: &mut G,
+    node: G::NodeId,
+) where
+    G: Data<NodeWeight = NodeData<T, N>>
+        + DataMapMut
+        + Visitable
+        + petgraph::visit::NodeIndexable
+        + NodeCount
+        + EdgeCount,
+    for<'a> &'a G: GraphBase<NodeId = G::NodeId> + IntoNeighborsDirected,
+    T: Node<N> + Send,
+{
+    const NO_NODE: &str = "no node exists for the given index";
+    let clock = processor.clock;
+    let start = now(clock);
+    prepare(processor, graph, node);
+    let Some(pool) = processor
+        .pool
+        .as_ref()
+        .filter(|_| processor.groups.len() > 1)
+    else {
+        process_in_order(processor, graph);
+        return finish_block(processor, start);
+    };
+
+    let mut last = now(clock);
+    for k in 0..processor.feedback {
+        let data = graph.node_weight_mut(processor.order[k].0).expect(NO_NODE);
+        run(data, &mut processor.inputs[k], &mut processor.mixes[k]);
+        lap(clock, &mut last, &mut processor.times, k);
+    }
+
+    processor.nodes.clear();
+    for (n, _) in &processor.order {
+        let data: *mut NodeData<T, N> = graph.node_weight_mut(*n).expect(NO_NODE);
+        processor.nodes.push(Raw(data.cast()));
+    }
+    let (nodes, groups, grouped) = (&processor.nodes, &processor.groups, &processor.grouped);
+    let inputs = Raw(processor.inputs.as_mut_ptr());
+    let mixes = Raw(processor.mixes.as_mut_ptr());
+    let times = Raw(processor.times.as_mut_ptr());
+    pool.run(groups.len(), &|group| {
+        let mut last = now(clock);
+        for &k in &grouped[groups[group].clone()] {
+            // SAFETY: every node is in exactly one group, and has no inputs in other groups
+            let data = unsafe { &mut *nodes[k].get().cast::<NodeData<T, N>>() };
+            let inputs = unsafe { &mut *inputs.get().add(k) };
+            let mixes = unsafe { &mut *mixes.get().add(k) };
+            run(data, inputs, mixes);
+            if let Some(now) = clock {
+                // SAFETY: `times` lines up with `order` whenever there is a clock
+                let time = now();
+                unsafe { *times.get().add(k) += time - last };
+                last = time;
+            }
+        }
+    });
+
+    // the output node goes last, once everything else is done
+    let output = processor.output;
+    let data = graph
+        .node_weight_mut(processor.order[output].0)
+        .expect(NO_NODE);
+    let mut last = now(clock);
+    run(
+        data,
+        &mut processor.inputs[output],
+        &mut processor.mixes[output],
+    );
+    lap(clock, &mut last, &mut processor.times, output);
+    finish_block(processor, start);
+}
+
+// Work out the order again if the graph has changed, and point every node at its inputs.
+fn prepare<G, T, const N: usize>(processor: &mut Processor<G, N>, graph: &G, node: G::NodeId)
+where
+    G: Data<NodeWeight = NodeData<T, N>>
+        + DataMap
+        + Visitable
+        + petgraph::visit::NodeIndexable
+        + NodeCount
+        + EdgeCount,
     for<'a> &'a G: GraphBase<NodeId = G::NodeId> + IntoNeighborsDirected,
     T: Node<N>,
 {
     const NO_NODE: &str = "no node exists for the given index";
-    processor.dfs_post_order.reset(Reversed(&*graph));
-    processor.dfs_post_order.move_to(node);
-    while let Some(n) = processor.dfs_post_order.next(Reversed(&*graph)) {
-        processor.inputs.clear();
-        for in_n in (&*graph).neighbors_directed(n, Incoming) {
+    let shape = Some((node, graph.node_count(), graph.edge_count()));
+    if processor.ordered_for != shape {
+        order(processor, graph, node);
+        group(processor, graph);
+        processor.ordered_for = shape;
+        processor.reset_profile();
+    }
+
+    let iter = processor
+        .order
+        .iter()
+        .zip(&mut processor.inputs)
+        .zip(&mut processor.mixes);
+    for (((n, sources), inputs), mixes) in iter {
+        inputs.clear();
+        let wanted = input_channels(graph.node_weight(*n).expect(NO_NODE));
+        let mut mixes = mixes.iter_mut();
+        for &in_n in &processor.sources[sources.clone()] {
+            let input_container = graph.node_weight(in_n).expect(NO_NODE);
+            let mut input = Input::new(&input_container.buffers, graph.to_index(in_n));
+            // the mixes are in the same order as the sources that need them
+            if input_container.buffers.len() != wanted {
+                let mix = mixes.next().expect("a mix for every input that needs it");
+                mix.from = input;
+                input = Input::new(&mix.to, graph.to_index(in_n));
+            }
+            inputs.insert(graph.to_index(in_n), input);
+        }
+    }
+}
+
+// Walk the graph back from `node`, so that every node comes after its inputs.
+//
+// Feedback nodes don't wait for their inputs, so the walk stops at them, and they go first.
+// Their inputs are walked back from afterwards, as nothing else might lead to them.
+fn order<G, T, const N: usize>(processor: &mut Processor<G, N>, graph: &G, node: G::NodeId)
+where
+    G: Data<NodeWeight = NodeData<T, N>> + DataMap + Visitable + petgraph::visit::NodeIndexable,
+    for<'a> &'a G: GraphBase<NodeId = G::NodeId> + IntoNeighborsDirected,
+    T: Node<N>,
+{
+    const NO_NODE: &str = "no node exists for the given index";
+    const NEW: u8 = 0;
+    const OPEN: u8 = 1;
+    const DONE: u8 = 2;
+    processor.order.clear();
+    processor.sources.clear();
+    processor.inputs.clear();
+    processor.mixes.clear();
+    let (state, roots, walked) = (
+        &mut processor.state,
+        &mut processor.roots,
+        &mut processor.walked,
+    );
+    state.clear();
+    state.resize(graph.node_bound(), NEW);
+    roots.clear();
+    roots.push(node);
+    walked.clear();
+    while let Some(root) = roots.pop() {
+        processor.stack.push((root, false));
+        while let Some((n, inputs_done)) = processor.stack.pop() {
+            let i = graph.to_index(n);
+            if inputs_done {
+                state[i] = DONE;
+                walked.push(n);
+                continue;
+            }
+            if state[i] != NEW {
+                continue;
+            }
+            state[i] = OPEN;
+            processor.stack.push((n, true));
+            let feedback = graph.node_weight(n).expect(NO_NODE).node.is_feedback();
+            for in_n in graph.neighbors_directed(n, Incoming) {
+                // An input that is still open closes a loop with no feedback node in it, which
+                // leaves that input a block late, wherever the walk happened to start.
+                if in_n == n || state[graph.to_index(in_n)] != NEW {
+                    continue;
+                }
+                match feedback {
+                    true => roots.push(in_n),
+                    false => processor.stack.push((in_n, false)),
+                }
+            }
+        }
+    }
+
+    let is_feedback = |n: &G::NodeId| graph.node_weight(*n).expect(NO_NODE).node.is_feedback();
+    let feedback = walked.iter().filter(|n| is_feedback(n));
+    let rest = walked.iter().filter(|n| !is_feedback(n));
+    processor.feedback = feedback.clone().count();
+    for &n in feedback.chain(rest) {
+        if n == node {
+            processor.output = processor.order.len();
+        }
+        let start = processor.sources.len();
+        let wanted = input_channels(graph.node_weight(n).expect(NO_NODE));
+        let mut mixes = vec![];
+        for in_n in graph.neighbors_directed(n, Incoming) {
             // Skip edges that connect the node to itself to avoid aliasing `node`.
             if n == in_n {
                 continue;
             }
-            // println!("{:?}", (&*graph).to_index(in_n));
+            processor.sources.push(in_n);
+            let from = &graph.node_weight(in_n).expect(NO_NODE).buffers;
+            if from.len() != wanted {
+                mixes.push(Mix {
+                    from: Input::new(from, graph.to_index(in_n)),
+                    to: vec![Buffer::SILENT; wanted],
+                });
+            }
+        }
+        let sources = start..processor.sources.len();
+        processor.inputs.push(HashMap::with_capacity(sources.len()));
+        processor.mixes.push(mixes);
+        processor.order.push((n, sources));
+    }
+}
 
-            let input_container = graph.node_weight(in_n).expect(NO_NODE);
-            let input = Input::new(&input_container.buffers, (*graph).to_index(in_n));
-            processor.inputs.insert((*graph).to_index(in_n), input);
+// Split everything but the feedback nodes and the output node into groups that aren't connected
+// to each other. The groups keep the order of their nodes, and the biggest go first.
+fn group<G, const N: usize>(processor: &mut Processor<G, N>, graph: &G)
+where
+    G: Visitable + petgraph::visit::NodeIndexable,
+{
+    processor.groups.clear();
+    processor.grouped.clear();
+    let len = processor.order.len();
+    let (feedback, output) = (processor.feedback, processor.output);
+    if output < feedback {
+        return;
+    }
+    let position = &mut processor.position;
+    position.clear();
+    position.resize(graph.node_bound(), usize::MAX);
+    for (k, (n, _)) in processor.order.iter().enumerate() {
+        position[graph.to_index(*n)] = k;
+    }
+
+    let parent = &mut processor.parent;
+    parent.clear();
+    parent.extend(0..len);
+    fn find(parent: &mut [usize], mut k: usize) -> usize {
+        while parent[k] != k {
+            parent[k] = parent[parent[k]];
+            k = parent[k];
         }
+        k
+    }
+    for (k, (_, sources)) in processor.order.iter().enumerate().skip(feedback) {
+        for in_n in &processor.sources[sources.clone()] {
+            let j = position[graph.to_index(*in_n)];
+            if j == output && k != output {
+                // only a single thread can make sure that this node waits for the output node
+                return;
+            }
+            if j >= feedback && k != output && j != output {
+                let (a, b) = (find(parent, k), find(parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
 
-        // Here we used to dereference a raw pointer to the `NodeData`. The only references to the
-        // graph at this point in time are the input references and the node itself. We know that
-        // the input references do not alias out node's mutable reference as we explicitly check
-        // for it while looping through the inputs above.
-        let data = graph.node_weight_mut(n).expect(NO_NODE);
-        data.node.process(&mut processor.inputs, &mut data.buffers);
+    let (sizes, members) = (&mut processor.sizes, &mut processor.members);
+    sizes.clear();
+    sizes.resize(len, 0);
+    members.clear();
+    for k in (feedback..len).filter(|k| *k != output) {
+        let root = find(parent, k);
+        sizes[root] += 1;
+        members.push((root, k));
+    }
+    // the biggest groups first, then in the order of their first node
+    members.sort_unstable_by_key(|&(root, k)| (std::cmp::Reverse(sizes[root]), root, k));
+    for group in members.chunk_by(|a, b| a.0 == b.0) {
+        let start = processor.grouped.len();
+        processor.grouped.extend(group.iter().map(|&(_, k)| k));
+        processor.groups.push(start..processor.grouped.len());
     }
 }
@@ -25,18 +25,25 @@ mod context;
 
 pub use context::*;
 
+mod channels;
+pub use channels::*;
+mod sample;
+pub use sample::*;
 mod graph;
+mod pool;
+mod profile;
 use glicol_parser::{
     nodes::{TimeList, UsizeOrRef},
     ToInnerOwned,
 };
 pub use graph::*;
+pub use profile::*;
 
 mod node;
 pub use node::{
     compound, delay, effect, envelope, filter, operator, oscillator, sequencer, signal, synth,
 };
-pub use node::{Input, Node};
+pub use node::{Automation, Crossfade, Input, Node, ParamInfo, Ramp, Smoothing, Unit};
 // pub use node::*; // TODO: Do not expose every struct here
 
 mod buffer;
@@ -62,18 +69,23 @@ use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub enum Message {
     SetToNumber(u8, f32),
+    /// Move a parameter to a number along a ramp, see `Node::send_msg_at`.
+    RampToNumber(u8, f32, Ramp),
+    /// Change how a parameter follows `SetToNumber`, or all of them with `None`.
+    SetSmoothing(Option<u8>, Smoothing),
     SetToNumberList(u8, Vec<f32>),
     SetToSymbol(u8, String),
-    SetToSamples(u8, (&'static [f32], usize, usize)),
-    SetSamplePattern(
-        Vec<(String, f32)>,
-        f32,
-        HashMap<String, (&'static [f32], usize, usize)>,
-    ),
+    SetToSamples(u8, Sample),
+    SetSamplePattern(Vec<SampleEvent>, f32, HashMap<String, Sample>),
+    /// Play the second sample wherever the first one is played, including the notes that are
+    /// already playing.
+    ReplaceSample(Sample, Sample),
     SetPattern(Vec<(f32, f32)>, f32),
     SetToSeq(u8, Vec<(f32, UsizeOrRef<String>)>),
     SetRefOrder(HashMap<String, usize>),
     SetBPM(f32),
+    /// The sample rate has changed, so whatever a node worked out from the old one has to be
+    /// worked out again.
     SetSampleRate(usize),
     MainInput(petgraph::graph::NodeIndex),
     SidechainInput(petgraph::graph::NodeIndex),
@@ -0,0 +1,247 @@
+use crate::node::rescale;
+use std::collections::VecDeque;
+
+/// How an automated parameter gets to a new value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ramp {
+    /// Jump straight to it, or glide there if the parameter is smoothed.
+    Step,
+    /// Move in a straight line, reaching it after this many samples.
+    Linear(usize),
+    /// Move along an exponential curve, reaching it after this many samples. This sounds even for
+    /// frequencies and gains, but can't cross or reach zero, so it falls back to `Linear` when
+    /// the start and the target don't have the same sign.
+    Exponential(usize),
+}
+
+impl Ramp {
+    fn rescale(self, from: usize, to: usize) -> Self {
+        match self {
+            Self::Step => Self::Step,
+            Self::Linear(len) => Self::Linear(rescale(len, from, to)),
+            Self::Exponential(len) => Self::Exponential(rescale(len, from, to)),
+        }
+    }
+}
+
+/// How an automated parameter follows a change that comes without a ramp (a plain
+/// `Message::SetToNumber`), so that sweeping it doesn't produce zipper noise.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Smoothing {
+    /// Jump straight to the new value.
+    #[default]
+    Off,
+    /// Move to it in a straight line over this many samples.
+    Linear(usize),
+    /// Close in on it exponentially, getting about two thirds of the way there after this many
+    /// samples. It is snapped to the new value after eight times as long.
+    OnePole(usize),
+}
+
+impl Smoothing {
+    pub fn linear_ms(ms: f32, sr: usize) -> Self {
+        Self::Linear((ms / 1000. * sr as f32) as usize)
+    }
+
+    pub fn one_pole_ms(ms: f32, sr: usize) -> Self {
+        Self::OnePole((ms / 1000. * sr as f32) as usize)
+    }
+
+    /// The same length in time at `to` Hz instead of `from` Hz.
+    pub fn rescale(self, from: usize, to: usize) -> Self {
+        match self {
+            Self::Off => Self::Off,
+            Self::Linear(len) => Self::Linear(rescale(len, from, to)),
+            Self::OnePole(len) => Self::OnePole(rescale(len, from, to)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Curve {
+    Linear,
+    Exponential,
+    OnePole,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveRamp {
+    target: f32,
+    // added to the value for a linear ramp, multiplied with it for an exponential one, and the
+    // share of the distance to the target covered per sample for a one-pole
+    inc: f32,
+    curve: Curve,
+    left: usize,
+}
+
+/// A numeric parameter that can change at any sample of a block, either straight away, along a
+/// ramp, or smoothed.
+///
+/// Nodes call [`Automation::tick`] once per sample to get the value for that sample, and forward
+/// the `offset` they get in `Node::send_msg_at` to [`Automation::schedule`]. Offsets count from
+/// the first sample that `tick` hasn't returned yet, which is the start of the next block.
+///
+/// Its lengths and times are in samples at the rate it was last given with
+/// [`Automation::set_sr`], 44100 Hz until then.
+#[derive(Debug, Clone)]
+pub struct Automation {
+    value: f32,
+    clock: usize,
+    sr: usize,
+    // (sample, target, ramp), in order
+    events: VecDeque<(usize, f32, Ramp)>,
+    ramp: Option<ActiveRamp>,
+    smoothing: Smoothing,
+}
+
+impl Automation {
+    pub fn new(value: f32) -> Self {
+        Self {
+            value,
+            clock: 0,
+            sr: 44100,
+            events: VecDeque::new(),
+            ramp: None,
+            smoothing: Smoothing::Off,
+        }
+    }
+
+    pub fn smoothing(self, smoothing: Smoothing) -> Self {
+        Self { smoothing, ..self }
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: Smoothing) {
+        self.smoothing = smoothing;
+    }
+
+    /// Carry on at `sr` Hz, keeping the smoothing, the ramp under way and what is scheduled the
+    /// same in time.
+    pub fn set_sr(&mut self, sr: usize) {
+        let (from, clock) = (self.sr, self.clock);
+        self.smoothing = self.smoothing.rescale(from, sr);
+        if let Some(ramp) = &mut self.ramp {
+            let left = rescale(ramp.left, from, sr).max(1);
+            // the same way to the target in fewer or more steps
+            let steps = ramp.left as f32 / left as f32;
+            ramp.inc = match ramp.curve {
+                Curve::Linear => ramp.inc * steps,
+                Curve::Exponential => ramp.inc.powf(steps),
+                Curve::OnePole => 1. - (1. - ramp.inc).powf(steps),
+            };
+            ramp.left = left;
+        }
+        for (at, _, ramp) in &mut self.events {
+            *at = clock + rescale(at.saturating_sub(clock), from, sr);
+            *ramp = ramp.rescale(from, sr);
+        }
+        self.sr = sr;
+    }
+
+    /// The value for the next sample, without moving on.
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Move to `value` now, smoothed if smoothing is on, stopping a ramp that is under way.
+    /// Scheduled changes are kept.
+    pub fn set(&mut self, value: f32) {
+        match self.smoothing {
+            Smoothing::Off | Smoothing::Linear(0) | Smoothing::OnePole(0) => self.jump(value),
+            Smoothing::Linear(len) => self.start(value, Ramp::Linear(len)),
+            Smoothing::OnePole(len) => {
+                self.ramp = Some(ActiveRamp {
+                    target: value,
+                    inc: 1. - (-1. / len as f32).exp(),
+                    curve: Curve::OnePole,
+                    left: len * 8,
+                })
+            }
+        }
+    }
+
+    /// Jump to `value` now, even if smoothing is on, e.g. for the steps of a pattern.
+    pub fn jump(&mut self, value: f32) {
+        self.value = value;
+        self.ramp = None;
+    }
+
+    /// Move to `target` from `offset` samples on.
+    pub fn schedule(&mut self, offset: usize, target: f32, ramp: Ramp) {
+        let at = self.clock + offset;
+        let i = self.events.partition_point(|(t, ..)| *t <= at);
+        self.events.insert(i, (at, target, ramp));
+    }
+
+    /// Whether the value stays the same until something new is scheduled, so that a node can
+    /// work out what depends on it once per block.
+    pub fn is_static(&self) -> bool {
+        self.ramp.is_none() && self.events.is_empty()
+    }
+
+    /// The value for this sample.
+    pub fn tick(&mut self) -> f32 {
+        while let Some(&(at, target, ramp)) = self.events.front() {
+            if at > self.clock {
+                break;
+            }
+            self.events.pop_front();
+            self.start(target, ramp);
+        }
+        let value = self.value;
+        if let Some(ramp) = &mut self.ramp {
+            ramp.left -= 1;
+            if ramp.left == 0 {
+                self.value = ramp.target;
+                self.ramp = None;
+            } else {
+                match ramp.curve {
+                    Curve::Linear => self.value += ramp.inc,
+                    Curve::Exponential => self.value *= ramp.inc,
+                    Curve::OnePole => self.value += (ramp.target - self.value) * ramp.inc,
+                }
+            }
+        }
+        self.clock += 1;
+        value
+    }
+
+    /// Move on by `samples` without reading the values, for a branch of a node that gets the
+    /// parameter from an input instead, so that what is scheduled still lands on time.
+    pub fn skip(&mut self, samples: usize) {
+        if self.is_static() {
+            self.clock += samples;
+            return;
+        }
+        for _ in 0..samples {
+            self.tick();
+        }
+    }
+
+    fn start(&mut self, target: f32, ramp: Ramp) {
+        let (len, curve) = match ramp {
+            Ramp::Step | Ramp::Linear(0) | Ramp::Exponential(0) => {
+                self.set(target);
+                return;
+            }
+            Ramp::Linear(len) => (len, Curve::Linear),
+            Ramp::Exponential(len) if self.value * target > 0. => (len, Curve::Exponential),
+            Ramp::Exponential(len) => (len, Curve::Linear),
+        };
+        let inc = match curve {
+            Curve::Exponential => (target / self.value).powf(1. / len as f32),
+            _ => (target - self.value) / len as f32,
+        };
+        self.ramp = Some(ActiveRamp {
+            target,
+            inc,
+            curve,
+            left: len,
+        });
+    }
+}
+
+impl From<f32> for Automation {
+    fn from(value: f32) -> Self {
+        Self::new(value)
+    }
+}
@@ -20,7 +20,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo};
 use core::fmt;
 use core::ops::{Deref, DerefMut};
 use hashbrown::HashMap;
@@ -72,6 +72,18 @@ impl<const N: usize> Node<N> for BoxedNode<N> {
     fn send_msg(&mut self, info: Message) {
         self.0.send_msg(info)
     }
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        self.0.send_msg_at(offset, info)
+    }
+    fn params(&self) -> &'static [ParamInfo] {
+        self.0.params()
+    }
+    fn is_feedback(&self) -> bool {
+        self.0.is_feedback()
+    }
+    fn input_channels(&self) -> Option<usize> {
+        self.0.input_channels()
+    }
 }
 
 impl<const N: usize> Node<N> for BoxedNodeSend<N> {
@@ -81,6 +93,18 @@ impl<const N: usize> Node<N> for BoxedNodeSend<N> {
     fn send_msg(&mut self, info: Message) {
         self.0.send_msg(info)
     }
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        self.0.send_msg_at(offset, info)
+    }
+    fn params(&self) -> &'static [ParamInfo] {
+        self.0.params()
+    }
+    fn is_feedback(&self) -> bool {
+        self.0.is_feedback()
+    }
+    fn input_channels(&self) -> Option<usize> {
+        self.0.input_channels()
+    }
 }
 
 impl<T, const N: usize> From<Box<T>> for BoxedNode<N>
@@ -10,7 +10,7 @@ use crate::{
     AudioContext,
     Pass,
 };
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 
 use petgraph::graph::NodeIndex;
@@ -59,6 +59,18 @@ impl<const N: usize> Node<N> for SawSynth<N> {
         );
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::new("attack", 0, (0., f32::INFINITY), 0.01, Unit::Seconds),
+            ParamInfo::new("decay", 1, (0., f32::INFINITY), 0.1, Unit::Seconds),
+        ];
+        PARAMS
+    }
+
+    fn input_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(pos, value) => match pos {
@@ -70,6 +82,7 @@ impl<const N: usize> Node<N> for SawSynth<N> {
                     .send_msg(Message::SetToNumber(1, value)),
                 _ => {}
             },
+            Message::SetSampleRate(sr) => self.context.set_sr(sr),
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             _ => {}
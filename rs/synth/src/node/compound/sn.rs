@@ -13,7 +13,7 @@ use crate::{
     AudioContext,
     Pass,
 };
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 
 use petgraph::graph::NodeIndex;
@@ -74,11 +74,27 @@ impl<const N: usize> Node<N> for Sn<N> {
         )
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new(
+            "decay",
+            0,
+            (0., f32::INFINITY),
+            0.3,
+            Unit::Seconds,
+        )];
+        PARAMS
+    }
+
+    fn input_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => self.context.graph[self.context.tags["d"]]
                 .node
                 .send_msg(Message::SetToNumber(1, value)),
+            Message::SetSampleRate(sr) => self.context.set_sr(sr),
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             _ => {}
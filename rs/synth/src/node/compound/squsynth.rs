@@ -3,7 +3,7 @@
 // ~pitch: ~trigger >> mul 261.626;
 // ~env: ~trigger >> envperc #attack #decay;
 use crate::{envelope::EnvPerc, operator::Mul, oscillator::SquOsc, AudioContext, Pass};
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 
 use petgraph::graph::NodeIndex;
@@ -52,12 +52,25 @@ impl<const N: usize> Node<N> for SquSynth<N> {
         );
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::new("attack", 0, (0., f32::INFINITY), 0.01, Unit::Seconds),
+            ParamInfo::new("decay", 1, (0., f32::INFINITY), 0.1, Unit::Seconds),
+        ];
+        PARAMS
+    }
+
+    fn input_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(pos @ 0..=1, value) => self.context.graph[self.context.tags["d"]]
                 .node
                 .send_msg(Message::SetToNumber(pos, value)),
 
+            Message::SetSampleRate(sr) => self.context.set_sr(sr),
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             _ => {}
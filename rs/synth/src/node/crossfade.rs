@@ -0,0 +1,54 @@
+use crate::{upmix_channel, Buffer, Input, Message, Node};
+use hashbrown::HashMap;
+
+/// Fades linearly from an old input to a new one over `len` samples, then just passes the new one.
+///
+/// Unlike most nodes, the two inputs have fixed slots: connect the old signal with
+/// `Message::IndexOrder(0, ..)` and the new one with `Message::IndexOrder(1, ..)`. A slot that is
+/// never connected is treated as silence, so this can also fade something in or out.
+///
+/// Inputs with fewer channels than the output are upmixed, see [`upmix_channel`](crate::upmix_channel).
+#[derive(Debug, Clone)]
+pub struct Crossfade {
+    len: usize,
+    pos: usize,
+    slots: [Option<usize>; 2],
+}
+
+impl Crossfade {
+    pub fn new(len: usize) -> Self {
+        Self {
+            len: len.max(1),
+            pos: 0,
+            slots: [None, None],
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pos >= self.len
+    }
+}
+
+impl<const N: usize> Node<N> for Crossfade {
+    fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
+        let [old, new] = self.slots.map(|slot| slot.and_then(|id| inputs.get(&id)));
+        for (c, out) in output.iter_mut().enumerate() {
+            let old = old.and_then(|input| upmix_channel(input.buffers(), c));
+            let new = new.and_then(|input| upmix_channel(input.buffers(), c));
+            for i in 0..N {
+                let gain = ((self.pos + i) as f32 / self.len as f32).min(1.0);
+                out[i] =
+                    old.map_or(0.0, |b| b[i] * (1.0 - gain)) + new.map_or(0.0, |b| b[i] * gain);
+            }
+        }
+        self.pos = (self.pos + N).min(self.len);
+    }
+
+    fn send_msg(&mut self, info: Message) {
+        match info {
+            Message::IndexOrder(pos, index) if pos < 2 => self.slots[pos] = Some(index),
+            Message::ResetOrder => self.slots = [None, None],
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{upmix_channel, Buffer, Input, Message, Node, ParamInfo, Unit};
+use dasp_ring_buffer as ring_buffer;
+type Fixed = ring_buffer::Fixed<Vec<f32>>;
+use hashbrown::HashMap;
+
+/// A feedback comb filter: the output goes back into the input after `delay`, through a one-pole
+/// lowpass set by `damp`. The loop runs sample by sample, so unlike `fb` it works for delays
+/// shorter than a block, e.g. for Karplus-Strong strings.
+#[derive(Debug, Clone)]
+pub struct Comb {
+    buf: Vec<Fixed>,
+    // the state of the lowpass in the loop, for each channel
+    lowpass: Vec<f32>,
+    sr: usize,
+    delay: f32,
+    feedback: f32,
+    damp: f32,
+}
+
+impl Default for Comb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Comb {
+    pub fn new() -> Self {
+        Self {
+            buf: vec![],
+            lowpass: vec![],
+            sr: 44100,
+            delay: 10.,
+            feedback: 0.5,
+            damp: 0.,
+        }
+    }
+
+    pub fn sr(self, sr: usize) -> Self {
+        Self { sr, ..self }
+    }
+
+    pub fn delay(self, delay: f32, chan: u8) -> Self {
+        let mut comb = Self { delay, ..self };
+        comb.resize(chan as usize);
+        comb
+    }
+
+    pub fn feedback(self, feedback: f32) -> Self {
+        Self { feedback, ..self }
+    }
+
+    pub fn damp(self, damp: f32) -> Self {
+        Self {
+            damp: damp.clamp(0., 1.),
+            ..self
+        }
+    }
+
+    fn resize(&mut self, chan: usize) {
+        let delay_n = ((self.delay / 1000. * self.sr as f32) as usize).max(1);
+        self.buf = vec![Fixed::from(vec![0.0; delay_n]); chan];
+        self.lowpass = vec![0.0; chan];
+    }
+}
+
+impl<const N: usize> Node<N> for Comb {
+    fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
+        let Some(input) = inputs.values().next() else {
+            return;
+        };
+        let buffers = input.buffers();
+        let iter = self
+            .buf
+            .iter_mut()
+            .zip(&mut self.lowpass)
+            .zip(output.iter_mut())
+            .enumerate();
+        for (c, ((fixed, lowpass), out_buf)) in iter {
+            let Some(in_buf) = upmix_channel(buffers, c) else {
+                continue;
+            };
+            for (out, x) in out_buf.iter_mut().zip(in_buf.iter()) {
+                *lowpass = fixed.get(0) * (1. - self.damp) + *lowpass * self.damp;
+                *out = x + *lowpass * self.feedback;
+                fixed.push(*out);
+            }
+        }
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::new("delay", 0, (0., f32::INFINITY), 10., Unit::Ms),
+            ParamInfo::new("feedback", 1, (-1., 1.), 0.5, Unit::None),
+            ParamInfo::new("damp", 2, (0., 1.), 0., Unit::None),
+        ];
+        PARAMS
+    }
+
+    fn send_msg(&mut self, info: Message) {
+        match info {
+            Message::SetToNumber(0, value) => {
+                self.delay = value;
+                let chan = self.buf.len();
+                self.resize(chan);
+            }
+            Message::SetToNumber(1, value) => self.feedback = value,
+            Message::SetToNumber(2, value) => self.damp = value.clamp(0., 1.),
+            Message::SetSampleRate(sr) => {
+                self.sr = sr;
+                let chan = self.buf.len();
+                self.resize(chan);
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, upmix_channel, Buffer, Input, Message, Node, ParamInfo, Unit};
 use dasp_ring_buffer as ring_buffer;
 type Fixed = ring_buffer::Fixed<Vec<f32>>;
 use hashbrown::HashMap;
@@ -44,25 +44,19 @@ impl DelayMs {
 
 impl<const N: usize> Node<N> for DelayMs {
     fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
-        let main_input = inputs.values().next().unwrap();
-        if !(1..=2).contains(&main_input.buffers().len()) {
-            return;
-        }
-
         match inputs.len() {
             1 => {
                 // no modulation
-                match self.delay_n {
-                    // equal to a pass node
-                    0 => output[0].copy_from_slice(&main_input.buffers()[0]),
-                    _ => {
-                        let iter = self
-                            .buf
-                            .iter_mut()
-                            .zip(output.iter_mut())
-                            .zip(main_input.buffers());
-
-                        for ((fixed, out_buf), main_buf) in iter {
+                let main_input = inputs.values().next().unwrap().buffers();
+                let iter = self.buf.iter_mut().zip(output.iter_mut()).enumerate();
+                for (c, (fixed, out_buf)) in iter {
+                    let Some(main_buf) = upmix_channel(main_input, c) else {
+                        continue;
+                    };
+                    match self.delay_n {
+                        // equal to a pass node
+                        0 => out_buf.copy_from_slice(main_buf),
+                        _ => {
                             for (out, main) in out_buf.iter_mut().zip(main_buf.iter()) {
                                 *out = fixed.push(*main);
                             }
@@ -83,13 +77,11 @@ impl<const N: usize> Node<N> for DelayMs {
                     let pos_int = pos.floor() as usize;
                     let pos_frac = pos.fract();
 
-                    let iter = self
-                        .buf
-                        .iter_mut()
-                        .zip(output.iter_mut())
-                        .zip(main_input.buffers());
-
-                    for ((fixed, out_buf), main_buf) in iter {
+                    let iter = self.buf.iter_mut().zip(output.iter_mut()).enumerate();
+                    for (c, (fixed, out_buf)) in iter {
+                        let Some(main_buf) = upmix_channel(main_input.buffers(), c) else {
+                            continue;
+                        };
                         out_buf[i] = fixed.get(pos_int) * pos_frac
                             + fixed.get(pos_int + 1) * (1. - pos_frac);
                         fixed.push(main_buf[i]);
@@ -104,6 +96,17 @@ impl<const N: usize> Node<N> for DelayMs {
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new(
+            "delay",
+            0,
+            (0., f32::INFINITY),
+            0.,
+            Unit::Ms,
+        )];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => {
@@ -117,6 +120,14 @@ impl<const N: usize> Node<N> for DelayMs {
                     self.buf = vec![Fixed::from(vec![0.0; delay_n]); chan];
                 };
             }
+            Message::SetSampleRate(sr) => {
+                self.delay_n = rescale(self.delay_n, self.sr, sr);
+                self.sr = sr;
+                if self.delay_n > 0 {
+                    let chan = self.buf.len();
+                    self.buf = vec![Fixed::from(vec![0.0; self.delay_n]); chan];
+                }
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
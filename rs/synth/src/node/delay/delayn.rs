@@ -1,68 +1,70 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{upmix_channel, Buffer, Input, Message, Node, ParamInfo, Unit};
 use dasp_ring_buffer as ring_buffer;
 use hashbrown::HashMap;
 type Fixed = ring_buffer::Fixed<Vec<f32>>;
 
 #[derive(Debug, Clone)]
 pub struct DelayN {
-    buf: Fixed,
+    // one delay line per channel
+    buf: Vec<Fixed>,
     delay_n: usize,
     input_order: Vec<usize>,
 }
 
 impl DelayN {
     pub fn new(n: usize) -> Self {
-        let delay_n = n;
-        let init_n = match n {
-            0 => 1,
-            _ => n,
-        };
-        let buf = ring_buffer::Fixed::from(vec![0.0; init_n]);
         Self {
-            buf,
-            delay_n,
+            buf: vec![Fixed::from(vec![0.0; n.max(1)]); 2],
+            delay_n: n,
             input_order: Vec::new(),
         }
     }
+
+    pub fn channels(self, chan: usize) -> Self {
+        Self {
+            buf: vec![Fixed::from(vec![0.0; self.delay_n.max(1)]); chan],
+            ..self
+        }
+    }
 }
 
 impl<const N: usize> Node<N> for DelayN {
     fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
         if inputs.len() == 1 {
-            let main_input = inputs.values_mut().next().unwrap();
-            if self.delay_n != 0 {
-                for i in 0..N {
-                    output[0][i] = self.buf.push(main_input.buffers()[0][i]);
-                    if main_input.buffers().len() == 1 && output.len() == 2 {
-                        output[1][i] = output[0][i];
-                    }
-                }
-            } else {
-                // same as Pass node
-                let Some(input) = inputs.values().next() else {
-                    return;
+            let main_input = inputs.values_mut().next().unwrap().buffers();
+            for (c, (fixed, out_buf)) in self.buf.iter_mut().zip(output.iter_mut()).enumerate() {
+                let Some(main_buf) = upmix_channel(main_input, c) else {
+                    continue;
                 };
-
-                match (input.buffers(), &mut *output) {
-                    ([in_buf], [ref mut out_a, ref mut out_b]) => {
-                        out_a.copy_from_slice(in_buf);
-                        out_b.copy_from_slice(in_buf);
-                    }
-                    _ => {
-                        for (out_buf, in_buf) in output.iter_mut().zip(input.buffers()) {
-                            out_buf.copy_from_slice(in_buf);
-                        }
-                    }
+                if self.delay_n == 0 {
+                    // same as Pass node
+                    out_buf.copy_from_slice(main_buf);
+                    continue;
+                }
+                for (out, main) in out_buf.iter_mut().zip(main_buf.iter()) {
+                    *out = fixed.push(*main);
                 }
             }
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new(
+            "n",
+            0,
+            (0., f32::INFINITY),
+            0.,
+            Unit::Samples,
+        )];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => {
                 self.delay_n = value as usize;
-                self.buf = Fixed::from(vec![0.0; self.delay_n]);
+                let chan = self.buf.len();
+                self.buf = vec![Fixed::from(vec![0.0; self.delay_n.max(1)]); chan];
                 // buf2 = Fixed::from(vec![0.0; delay_n]);
                 // self.buf.set_first(self.delay_n);
                 // self.buf2.set_first(delay_n);
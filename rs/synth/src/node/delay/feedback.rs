@@ -0,0 +1,33 @@
+use crate::{mix_into, Buffer, Input, Message, Node};
+use hashbrown::HashMap;
+
+/// Passes on the previous block of its inputs, summed, so that a graph can loop back on itself.
+///
+/// The processor runs feedback nodes before everything else in a block, while every buffer still
+/// holds the block before, so whatever they read is exactly one block late. Their inputs don't
+/// have to come first in the order, which is what makes a cycle through them fine.
+#[derive(Debug, Clone, Default)]
+pub struct Feedback;
+
+impl Feedback {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<const N: usize> Node<N> for Feedback {
+    fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
+        for out in output.iter_mut() {
+            out.silence();
+        }
+        for input in inputs.values() {
+            mix_into(input.buffers(), output);
+        }
+    }
+
+    fn send_msg(&mut self, _info: Message) {}
+
+    fn is_feedback(&self) -> bool {
+        true
+    }
+}
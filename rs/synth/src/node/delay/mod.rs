@@ -2,3 +2,7 @@ mod delayn;
 pub use delayn::*;
 mod delayms;
 pub use delayms::*;
+mod feedback;
+pub use feedback::*;
+mod comb;
+pub use comb::*;
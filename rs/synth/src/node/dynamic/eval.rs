@@ -1,4 +1,4 @@
-use crate::{BoxedNodeSend, Buffer, Input, Message, Node, NodeData};
+use crate::{BoxedNodeSend, Buffer, Input, Message, Node, NodeData, ParamInfo};
 use hashbrown::HashMap;
 // use evalexpr::*;
 use fasteval::Compiler;
@@ -88,16 +88,25 @@ impl<const N: usize> Eval<N> {
     }
 }
 
+// Only allocates the first time a variable is set.
+fn set_var(map: &mut BTreeMap<String, f64>, name: &str, value: f64) {
+    match map.get_mut(name) {
+        Some(v) => *v = value,
+        None => _ = map.insert(name.to_owned(), value),
+    }
+}
+
 impl<const N: usize> Node<N> for Eval<N> {
     fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
-        for i in 0..N {
+        for (i, out) in output[0].iter_mut().enumerate() {
             if !inputs.is_empty() {
-                self.map.insert(
-                    "in".to_owned(),
+                set_var(
+                    &mut self.map,
+                    "in",
                     inputs[&self.input_order[0]].buffers()[0][i] as f64,
                 );
             }
-            self.map.insert("phase".to_owned(), self.phase as f64);
+            set_var(&mut self.map, "phase", self.phase as f64);
             // output[0][i] = self.compiled.eval(&self.slab, &mut self.map).unwrap() as f32;
             // output[0][i] = fasteval::ez_eval(&self.code, &mut self.map).unwrap() as f32;
 
@@ -105,16 +114,21 @@ impl<const N: usize> Node<N> for Eval<N> {
                 // println!("i is {}", j);
                 if j < self.compiled.len() - 1 {
                     let v = ins.eval(&self.slab[j], &mut self.map).unwrap();
-                    self.map.insert(self.var[j].clone(), v);
+                    set_var(&mut self.map, &self.var[j], v);
                 } else {
                     let v = ins.eval(&self.slab[j], &mut self.map).unwrap();
-                    output[0][i] = v as f32;
+                    *out = v as f32;
                 }
             }
             self.phase += 1;
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::symbol("code", 0)];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToSymbol(0, code) => {
@@ -130,6 +144,10 @@ impl<const N: usize> Node<N> for Eval<N> {
             }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
+            Message::SetSampleRate(sr) => {
+                self.map.insert("sr".to_string(), sr as f64);
+                self.sr = sr
+            }
             Message::ResetOrder => {
                 self.input_order.clear();
             }
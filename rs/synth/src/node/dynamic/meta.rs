@@ -1,4 +1,4 @@
-use crate::{BoxedNodeSend, Buffer, Input, Message, Node, NodeData};
+use crate::{BoxedNodeSend, Buffer, Input, Message, Node, NodeData, ParamInfo};
 use hashbrown::HashMap;
 use rhai::{Array, Dynamic, Engine, OptimizationLevel, Scope, AST};
 
@@ -98,12 +98,25 @@ impl<const N: usize> Meta<N> {
 impl<const N: usize> Node<N> for Meta<N> {
     fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
         if !inputs.is_empty() {
-            let arr: Array = inputs[&self.input_order[0]].buffers()[0]
-                .iter()
-                .map(|f| Dynamic::from_float(*f))
-                .collect();
-
-            self.scope.set_or_push("input", arr);
+            let input = &inputs[&self.input_order[0]].buffers()[0];
+            // the array is only built once, and written over in the blocks after that
+            let written = match self
+                .scope
+                .get_mut("input")
+                .and_then(|arr| arr.write_lock::<Array>())
+            {
+                Some(mut arr) => {
+                    for (x, f) in arr.iter_mut().zip(input.iter()) {
+                        *x = Dynamic::from_float(*f);
+                    }
+                    true
+                }
+                None => false,
+            };
+            if !written {
+                let arr: Array = input.iter().map(|f| Dynamic::from_float(*f)).collect();
+                self.scope.set_or_push("input", arr);
+            }
             // self.engine.optimize_ast();
         }
 
@@ -125,6 +138,11 @@ impl<const N: usize> Node<N> for Meta<N> {
         }
         // self.phase += N;
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::symbol("code", 0)];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToSymbol(0, s) => {
@@ -134,6 +152,10 @@ impl<const N: usize> Node<N> for Meta<N> {
             }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
+            Message::SetSampleRate(sr) => {
+                self.scope.set_or_push("sr", sr as f32);
+                self.sr = sr
+            }
             Message::ResetOrder => {
                 self.input_order.clear();
             }
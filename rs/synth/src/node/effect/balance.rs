@@ -30,6 +30,10 @@ impl<const N: usize> Node<N> for Balance {
             output[1] = right.buffers()[0].clone();
         }
     }
+    fn input_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::Index(i) => self.input_order.push(i),
@@ -1,17 +1,17 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Unit};
 use hashbrown::HashMap;
 // use std::f32::consts::PI;
 
 #[derive(Debug, Clone)]
 pub struct Pan {
-    pan_pos: f32, // Pan position (-1.0 to 1.0)
+    pan_pos: Automation, // Pan position (-1.0 to 1.0)
     input_order: Vec<usize>,
 }
 
 impl Pan {
     pub fn new(pan_pos: f32) -> Self {
         Self {
-            pan_pos,
+            pan_pos: Automation::new(pan_pos),
             input_order: vec![],
         }
     }
@@ -24,21 +24,23 @@ impl<const N: usize> Node<N> for Pan {
             let main_input = inputs.values_mut().next().unwrap();
             let input_buffers = main_input.buffers();
 
-            let pan_norm = (self.pan_pos + 1.0) / 2.0; // Normalize pan position to 0.0 to 1.0
-            let left_gain = (1.0 - pan_norm).sqrt(); // Left channel gain
-            let right_gain = pan_norm.sqrt(); // Right channel gain
-
             let (left, right) = output.split_at_mut(1);
             for ((left, right), sample) in left[0]
                 .iter_mut()
-                .zip(right[1].iter_mut())
+                .zip(right[0].iter_mut())
                 .zip(input_buffers[0].iter())
             {
+                // Clamp pan position within valid range
+                let pan_pos = self.pan_pos.tick().clamp(-1.0, 1.0);
+                let pan_norm = (pan_pos + 1.0) / 2.0; // Normalize pan position to 0.0 to 1.0
+                let left_gain = (1.0 - pan_norm).sqrt(); // Left channel gain
+                let right_gain = pan_norm.sqrt(); // Right channel gain
                 *left = left_gain * sample; // Left channel
                 *right = right_gain * sample; // Right channel
             }
         } else {
             let ref_input = &inputs[&self.input_order[1]]; // can panic if there is no id
+            self.pan_pos.skip(N);
             let main_input = &inputs[&self.input_order[0]]; // can panic if there is no id
 
             let ref_main = ref_input.buffers()[0]
@@ -57,10 +59,31 @@ impl<const N: usize> Node<N> for Pan {
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new("pan", 0, (-1., 1.), 0., Unit::None)];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(0, value) => self.pan_pos.schedule(offset, value, Ramp::Step),
+            Message::RampToNumber(0, value, ramp) => self.pan_pos.schedule(offset, value, ramp),
+            info => Node::<N>::send_msg(self, info),
+        }
+    }
+
+    fn input_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
-            // Clamp pan position within valid range
-            Message::SetToNumber(0, value) => self.pan_pos = value.clamp(-1.0, 1.0),
+            Message::SetToNumber(0, value) => self.pan_pos.set(value),
+            Message::RampToNumber(0, value, ramp) => self.pan_pos.schedule(0, value, ramp),
+            Message::SetSmoothing(None | Some(0), smoothing) => {
+                self.pan_pos.set_smoothing(smoothing)
+            }
+            Message::SetSampleRate(sr) => self.pan_pos.set_sr(sr),
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
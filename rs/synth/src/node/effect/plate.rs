@@ -5,7 +5,7 @@ use crate::{
     node::Pass,
     operator::{Add, Mul},
     oscillator::SinOsc,
-    AudioContext, Buffer, Input, Message, Node,
+    AudioContext, Buffer, Input, Message, Node, ParamInfo, Unit,
 };
 use hashbrown::HashMap;
 use petgraph::graph::NodeIndex;
@@ -167,6 +167,15 @@ impl<const N: usize> Node<N> for Plate<N> {
         output[1][..N].copy_from_slice(&cout[1][..N]);
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new("mix", 0, (0., 1.), 0.1, Unit::None)];
+        PARAMS
+    }
+
+    fn input_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => {
@@ -184,6 +193,14 @@ impl<const N: usize> Node<N> for Plate<N> {
                     .node
                     .send_msg(Message::SetToNumber(0, 1. - value));
             }
+            Message::SetSmoothing(None | Some(0), smoothing) => {
+                for tag in ["mix1", "mix2", "mixdiff1", "mixdiff2"] {
+                    self.context.graph[self.context.tags[tag]]
+                        .node
+                        .send_msg(Message::SetSmoothing(Some(0), smoothing));
+                }
+            }
+            Message::SetSampleRate(sr) => self.context.set_sr(sr),
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub struct Adsr {
@@ -135,6 +135,16 @@ impl<const N: usize> Node<N> for Adsr {
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::new("attack", 0, (0., f32::INFINITY), 0.01, Unit::Seconds),
+            ParamInfo::new("decay", 1, (0., f32::INFINITY), 0.1, Unit::Seconds),
+            ParamInfo::new("sustain", 2, (0., 1.), 0.3, Unit::None),
+            ParamInfo::new("release", 3, (0., f32::INFINITY), 0.1, Unit::Seconds),
+        ];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(pos, value) => match pos {
@@ -144,6 +154,11 @@ impl<const N: usize> Node<N> for Adsr {
                 3 => self.release = value,
                 _ => {}
             },
+            Message::SetSampleRate(sr) => {
+                self.pos = rescale(self.pos, self.sr, sr);
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
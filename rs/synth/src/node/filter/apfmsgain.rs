@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node, ParamInfo, Unit};
 use dasp_ring_buffer as ring_buffer;
 use hashbrown::HashMap;
 type Fixed = ring_buffer::Fixed<Vec<f32>>;
@@ -98,6 +98,14 @@ impl<const N: usize> Node<N> for AllPassFilterGain {
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::new("delay", 0, (0., f32::INFINITY), 0., Unit::Ms),
+            ParamInfo::new("gain", 1, (0., 1.), 0.5, Unit::None),
+        ];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(pos, value) => match pos {
@@ -109,6 +117,12 @@ impl<const N: usize> Node<N> for AllPassFilterGain {
                 1 => self.gain = value,
                 _ => {}
             },
+            Message::SetSampleRate(sr) => {
+                let size = rescale(self.bufx.len(), self.sr, sr).max(1);
+                self.bufx = Fixed::from(vec![0.0; size]);
+                self.bufy = Fixed::from(vec![0.0; size]);
+                self.sr = sr;
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
 
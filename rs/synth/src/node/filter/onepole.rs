@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub struct OnePole {
@@ -59,6 +59,11 @@ impl<const N: usize> Node<N> for OnePole {
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new("rate", 0, (0., 1.), 0., Unit::None)];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => {
@@ -1,9 +1,9 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Unit};
 use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub struct ResonantHighPassFilter {
-    cutoff: f32,
-    q: f32,
+    cutoff: Automation,
+    q: Automation,
     x0: f32,
     x1: f32,
     x2: f32,
@@ -22,8 +22,8 @@ impl Default for ResonantHighPassFilter {
 impl ResonantHighPassFilter {
     pub fn new() -> Self {
         Self {
-            cutoff: 20.,
-            q: 1.0,
+            cutoff: Automation::new(20.),
+            q: Automation::new(1.0),
             x0: 0.,
             x1: 0.,
             x2: 0.,
@@ -34,16 +34,43 @@ impl ResonantHighPassFilter {
         }
     }
     pub fn cutoff(self, cutoff: f32) -> Self {
-        Self { cutoff, ..self }
+        Self {
+            cutoff: Automation::new(cutoff),
+            ..self
+        }
     }
 
     pub fn q(self, q: f32) -> Self {
-        Self { q, ..self }
+        Self {
+            q: Automation::new(q),
+            ..self
+        }
     }
 
     pub fn sr(self, sr: usize) -> Self {
         Self { sr, ..self }
     }
+
+    fn param(&mut self, pos: u8) -> Option<&mut Automation> {
+        match pos {
+            0 => Some(&mut self.cutoff),
+            1 => Some(&mut self.q),
+            _ => None,
+        }
+    }
+}
+
+fn coefficients(cutoff: f32, q: f32, sr: usize) -> (f32, f32, f32, f32, f32) {
+    let theta_c = 2.0 * std::f32::consts::PI * cutoff / sr as f32;
+    let d = 1.0 / q;
+    let beta = 0.5 * (1.0 - d * theta_c.sin() / 2.0) / (1.0 + d * theta_c.sin() / 2.0);
+    let gama = (0.5 + beta) * theta_c.cos();
+    let a0 = (0.5 + beta + gama) / 2.0;
+    let a1 = -0.5 - beta - gama;
+    let a2 = (0.5 + beta + gama) / 2.0;
+    let b1 = -2.0 * gama;
+    let b2 = 2.0 * beta;
+    (a0, a1, a2, b1, b2)
 }
 
 impl<const N: usize> Node<N> for ResonantHighPassFilter {
@@ -52,16 +79,15 @@ impl<const N: usize> Node<N> for ResonantHighPassFilter {
         match inputs.len() {
             1 => {
                 let main_input = inputs.values_mut().next().unwrap();
-                let theta_c = 2.0 * std::f32::consts::PI * self.cutoff / self.sr as f32;
-                let d = 1.0 / self.q;
-                let beta = 0.5 * (1.0 - d * theta_c.sin() / 2.0) / (1.0 + d * theta_c.sin() / 2.0);
-                let gama = (0.5 + beta) * theta_c.cos();
-                let a0 = (0.5 + beta + gama) / 2.0;
-                let a1 = -0.5 - beta - gama;
-                let a2 = (0.5 + beta + gama) / 2.0;
-                let b1 = -2.0 * gama;
-                let b2 = 2.0 * beta;
+                let mut last = None;
+                let mut coefs = (0., 0., 0., 0., 0.);
                 for (out, x0) in output[0].iter_mut().zip(main_input.buffers()[0].iter()) {
+                    let params = (self.cutoff.tick(), self.q.tick());
+                    if last != Some(params) {
+                        coefs = coefficients(params.0, params.1, self.sr);
+                        last = Some(params);
+                    }
+                    let (a0, a1, a2, b1, b2) = coefs;
                     let y =
                         a0 * self.x0 + a1 * self.x1 + a2 * self.x2 - b1 * self.y1 - b2 * self.y2;
                     *out = y;
@@ -75,18 +101,17 @@ impl<const N: usize> Node<N> for ResonantHighPassFilter {
                 let main_input = &inputs[&self.input_order[0]]; // can panic if there is no id
                 let ref_input = &inputs[&self.input_order[1]]; // can panic if there is no id
 
-                let theta_c =
-                    2.0 * std::f32::consts::PI * ref_input.buffers()[0][0] / self.sr as f32;
-                let d = 1.0 / self.q;
-                let beta = 0.5 * (1.0 - d * theta_c.sin() / 2.0) / (1.0 + d * theta_c.sin() / 2.0);
-                let gama = (0.5 + beta) * theta_c.cos();
-                let a0 = (0.5 + beta + gama) / 2.0;
-                let a1 = -0.5 - beta - gama;
-                let a2 = (0.5 + beta + gama) / 2.0;
-                let b1 = -2.0 * gama;
-                let b2 = 2.0 * beta;
-
+                let cutoff = ref_input.buffers()[0][0];
+                self.cutoff.skip(N);
+                let mut last = None;
+                let mut coefs = (0., 0., 0., 0., 0.);
                 for (out, x0) in output[0].iter_mut().zip(main_input.buffers()[0].iter()) {
+                    let q = self.q.tick();
+                    if last != Some(q) {
+                        coefs = coefficients(cutoff, q, self.sr);
+                        last = Some(q);
+                    }
+                    let (a0, a1, a2, b1, b2) = coefs;
                     let y =
                         a0 * self.x0 + a1 * self.x1 + a2 * self.x2 - b1 * self.y1 - b2 * self.y2;
                     *out = y;
@@ -96,20 +121,66 @@ impl<const N: usize> Node<N> for ResonantHighPassFilter {
                     self.y1 = y;
                 }
             }
-            _ => (),
+            _ => {
+                self.cutoff.skip(N);
+                self.q.skip(N);
+            }
+        }
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::new("cutoff", 0, (20., 20000.), 20., Unit::Hz),
+            ParamInfo::new("q", 1, (0.1, 10.), 1., Unit::None),
+        ];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(pos, value) => {
+                if let Some(param) = self.param(pos) {
+                    param.schedule(offset, value, Ramp::Step)
+                }
+            }
+            Message::RampToNumber(pos, value, ramp) => {
+                if let Some(param) = self.param(pos) {
+                    param.schedule(offset, value, ramp)
+                }
+            }
+            info => Node::<N>::send_msg(self, info),
         }
     }
 
     fn send_msg(&mut self, info: Message) {
         match info {
-            Message::SetToNumber(pos, value) => match pos {
-                0 => self.cutoff = value,
-                1 => self.q = value,
-                _ => {}
-            },
+            Message::SetToNumber(pos, value) => {
+                if let Some(param) = self.param(pos) {
+                    param.set(value)
+                }
+            }
+            Message::RampToNumber(pos, value, ramp) => {
+                if let Some(param) = self.param(pos) {
+                    param.schedule(0, value, ramp)
+                }
+            }
+            Message::SetSmoothing(Some(pos), smoothing) => {
+                if let Some(param) = self.param(pos) {
+                    param.set_smoothing(smoothing)
+                }
+            }
+            Message::SetSmoothing(None, smoothing) => {
+                self.cutoff.set_smoothing(smoothing);
+                self.q.set_smoothing(smoothing);
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
 
+            Message::SetSampleRate(sr) => {
+                self.sr = sr;
+                self.cutoff.set_sr(sr);
+                self.q.set_sr(sr);
+            }
             Message::ResetOrder => {
                 self.input_order.clear();
             }
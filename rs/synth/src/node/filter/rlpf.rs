@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Unit};
 use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub struct ResonantLowPassFilter {
@@ -7,8 +7,8 @@ pub struct ResonantLowPassFilter {
     span: f32,
     bpm: f32,
     sr: usize,
-    cutoff: f32,
-    q: f32,
+    cutoff: Automation,
+    q: Automation,
     x0: f32,
     x1: f32,
     x2: f32,
@@ -33,8 +33,8 @@ impl ResonantLowPassFilter {
             bpm: 120.,
             sr: 44100,
             step: 0,
-            cutoff: 20.,
-            q: 1.0,
+            cutoff: Automation::new(20.),
+            q: Automation::new(1.0),
             x0: 0.,
             x1: 0.,
             x2: 0.,
@@ -65,18 +65,44 @@ impl ResonantLowPassFilter {
     }
 
     pub fn cutoff(self, cutoff: f32) -> Self {
-        Self { cutoff, ..self }
+        Self {
+            cutoff: Automation::new(cutoff),
+            ..self
+        }
     }
 
     pub fn q(self, q: f32) -> Self {
-        Self { q, ..self }
+        Self {
+            q: Automation::new(q),
+            ..self
+        }
+    }
+
+    fn param(&mut self, pos: u8) -> Option<&mut Automation> {
+        match pos {
+            0 => Some(&mut self.cutoff),
+            1 => Some(&mut self.q),
+            _ => None,
+        }
     }
 }
 
+fn coefficients(cutoff: f32, q: f32, sr: usize) -> (f32, f32, f32, f32, f32) {
+    let theta_c = 2.0 * std::f32::consts::PI * cutoff / sr as f32;
+    let d = 1.0 / q;
+    let beta = 0.5 * (1.0 - d * theta_c.sin() / 2.0) / (1.0 + d * theta_c.sin() / 2.0);
+    let gama = (0.5 + beta) * theta_c.cos();
+    let a0 = (0.5 + beta - gama) / 2.0;
+    let a1 = 0.5 + beta - gama;
+    let a2 = (0.5 + beta - gama) / 2.0;
+    let b1 = -2.0 * gama;
+    let b2 = 2.0 * beta;
+    (a0, a1, a2, b1, b2)
+}
+
 impl<const N: usize> Node<N> for ResonantLowPassFilter {
     fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
         // println!("inputs {:?} self.input_order {:?}", inputs, self.input_order);
-        let d = 1.0 / self.q;
         match inputs.len() {
             1 => {
                 let cycle_dur = 60. / self.bpm * 4.;
@@ -93,19 +119,12 @@ impl<const N: usize> Node<N> for ResonantLowPassFilter {
                         if (self.step % (bar_dur as usize))
                             == ((event.1 * cycle_dur * self.sr as f32) as usize)
                         {
-                            self.cutoff = event.0
+                            self.cutoff.jump(event.0)
                         }
                     }
 
-                    let theta_c = 2.0 * std::f32::consts::PI * self.cutoff / self.sr as f32;
-                    let beta =
-                        0.5 * (1.0 - d * theta_c.sin() / 2.0) / (1.0 + d * theta_c.sin() / 2.0);
-                    let gama = (0.5 + beta) * theta_c.cos();
-                    let a0 = (0.5 + beta - gama) / 2.0;
-                    let a1 = 0.5 + beta - gama;
-                    let a2 = (0.5 + beta - gama) / 2.0;
-                    let b1 = -2.0 * gama;
-                    let b2 = 2.0 * beta;
+                    let (a0, a1, a2, b1, b2) =
+                        coefficients(self.cutoff.tick(), self.q.tick(), self.sr);
 
                     let y =
                         a0 * self.x0 + a1 * self.x1 + a2 * self.x2 - b1 * self.y1 - b2 * self.y2;
@@ -122,17 +141,17 @@ impl<const N: usize> Node<N> for ResonantLowPassFilter {
                 let main_input = &inputs[&self.input_order[0]]; // can panic if there is no id
                 let ref_input = &inputs[&self.input_order[1]]; // can panic if there is no id
 
-                let theta_c =
-                    2.0 * std::f32::consts::PI * ref_input.buffers()[0][0] / self.sr as f32;
-                let beta = 0.5 * (1.0 - d * theta_c.sin() / 2.0) / (1.0 + d * theta_c.sin() / 2.0);
-                let gama = (0.5 + beta) * theta_c.cos();
-                let a0 = (0.5 + beta - gama) / 2.0;
-                let a1 = 0.5 + beta - gama;
-                let a2 = (0.5 + beta - gama) / 2.0;
-                let b1 = -2.0 * gama;
-                let b2 = 2.0 * beta;
-
+                let cutoff = ref_input.buffers()[0][0];
+                self.cutoff.skip(N);
+                let mut last = None;
+                let mut coefs = (0., 0., 0., 0., 0.);
                 for (out, x0) in output[0].iter_mut().zip(main_input.buffers()[0].iter()) {
+                    let q = self.q.tick();
+                    if last != Some(q) {
+                        coefs = coefficients(cutoff, q, self.sr);
+                        last = Some(q);
+                    }
+                    let (a0, a1, a2, b1, b2) = coefs;
                     let y =
                         a0 * self.x0 + a1 * self.x1 + a2 * self.x2 - b1 * self.y1 - b2 * self.y2;
                     *out = y;
@@ -143,7 +162,34 @@ impl<const N: usize> Node<N> for ResonantLowPassFilter {
                     self.step += 1;
                 }
             }
-            _ => (),
+            _ => {
+                self.cutoff.skip(N);
+                self.q.skip(N);
+            }
+        }
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::new("cutoff", 0, (20., 20000.), 20., Unit::Hz),
+            ParamInfo::new("q", 1, (0.1, 10.), 1., Unit::None),
+        ];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(pos, value) => {
+                if let Some(param) = self.param(pos) {
+                    param.schedule(offset, value, Ramp::Step)
+                }
+            }
+            Message::RampToNumber(pos, value, ramp) => {
+                if let Some(param) = self.param(pos) {
+                    param.schedule(offset, value, ramp)
+                }
+            }
+            info => Node::<N>::send_msg(self, info),
         }
     }
 
@@ -153,11 +199,25 @@ impl<const N: usize> Node<N> for ResonantLowPassFilter {
                 self.pattern = p;
                 self.span = span;
             }
-            Message::SetToNumber(pos, value) => match pos {
-                0 => self.cutoff = value,
-                1 => self.q = value,
-                _ => {}
-            },
+            Message::SetToNumber(pos, value) => {
+                if let Some(param) = self.param(pos) {
+                    param.set(value)
+                }
+            }
+            Message::RampToNumber(pos, value, ramp) => {
+                if let Some(param) = self.param(pos) {
+                    param.schedule(0, value, ramp)
+                }
+            }
+            Message::SetSmoothing(Some(pos), smoothing) => {
+                if let Some(param) = self.param(pos) {
+                    param.set_smoothing(smoothing)
+                }
+            }
+            Message::SetSmoothing(None, smoothing) => {
+                self.cutoff.set_smoothing(smoothing);
+                self.q.set_smoothing(smoothing);
+            }
             Message::Index(i) => {
                 // println!("got index without order {}", i);
                 self.input_order.push(i)
@@ -166,6 +226,11 @@ impl<const N: usize> Node<N> for ResonantLowPassFilter {
                 // println!("got index order {}", index);
                 self.input_order.insert(pos, index)
             }
+            Message::SetSampleRate(sr) => {
+                self.sr = sr;
+                self.cutoff.set_sr(sr);
+                self.q.set_sr(sr);
+            }
             Message::ResetOrder => {
                 self.input_order.clear();
             }
@@ -10,6 +10,12 @@ pub use pass::*;
 #[cfg(feature = "node-sum")]
 mod sum;
 pub use sum::*;
+mod crossfade;
+pub use crossfade::*;
+mod params;
+pub use params::*;
+mod automation;
+pub use automation::*;
 
 pub mod oscillator;
 // pub use oscillator::*;
@@ -50,6 +56,49 @@ pub trait Node<const N: usize> {
     fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]);
     fn send_msg(&mut self, info: crate::Message);
 
+    /// Like `send_msg`, but for `offset` samples into the next block, e.g. for a
+    /// `Message::SetToNumber` or a `Message::RampToNumber` on an [`Automation`] parameter.
+    ///
+    /// Nodes that don't override this get the message before the block starts, and jump straight
+    /// to the target of a ramp.
+    fn send_msg_at(&mut self, offset: usize, info: crate::Message) {
+        let _ = offset;
+        match info {
+            crate::Message::RampToNumber(pos, value, _) => {
+                self.send_msg(crate::Message::SetToNumber(pos, value))
+            }
+            info => self.send_msg(info),
+        }
+    }
+
+    /// The parameters this node takes, by index. Empty if the node doesn't describe them, in which
+    /// case any index may mean something.
+    fn params(&self) -> &'static [ParamInfo] {
+        &[]
+    }
+
+    /// Whether this node reads the previous block of its inputs rather than the current one, like
+    /// [`delay::Feedback`]. The processor runs these first, and doesn't wait for their inputs.
+    fn is_feedback(&self) -> bool {
+        false
+    }
+
+    /// How many channels the processor mixes every input to before handing it to
+    /// [`Self::process`], following [`mix_into`](crate::mix_into). `None`, the default, means as
+    /// many as the node has itself. A node that makes stereo out of mono, like [`effect::Pan`],
+    /// asks for 1.
+    fn input_channels(&self) -> Option<usize> {
+        None
+    }
+
+    /// The index of the parameter called `name`.
+    fn param_index(&self, name: &str) -> Option<u8> {
+        self.params()
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| param.index)
+    }
+
     fn to_boxed_nodedata(self, channels: usize) -> crate::NodeData<BoxedNodeSend<N>, N>
     where
         Self: Send + 'static + Sized,
@@ -107,6 +156,18 @@ where
     fn send_msg(&mut self, info: crate::Message) {
         (**self).send_msg(info)
     }
+    fn send_msg_at(&mut self, offset: usize, info: crate::Message) {
+        (**self).send_msg_at(offset, info)
+    }
+    fn params(&self) -> &'static [ParamInfo] {
+        (**self).params()
+    }
+    fn is_feedback(&self) -> bool {
+        (**self).is_feedback()
+    }
+    fn input_channels(&self) -> Option<usize> {
+        (**self).input_channels()
+    }
 }
 
 impl<T, const N: usize> Node<N> for Box<T>
@@ -117,6 +178,12 @@ where
         (**self).process(inputs, output)
     }
     fn send_msg(&mut self, _info: crate::Message) {}
+    fn is_feedback(&self) -> bool {
+        (**self).is_feedback()
+    }
+    fn input_channels(&self) -> Option<usize> {
+        (**self).input_channels()
+    }
 }
 
 impl<const N: usize> Node<N> for dyn Fn(&HashMap<usize, Input<N>>, &mut [Buffer<N>]) {
@@ -140,3 +207,9 @@ impl<const N: usize> Node<N> for fn(&HashMap<usize, Input<N>>, &mut [Buffer<N>])
     }
     fn send_msg(&mut self, _info: crate::Message) {}
 }
+
+/// `samples` at `from` Hz, counted at `to` Hz instead, for keeping positions and lengths in time
+/// when the sample rate changes.
+pub(crate) fn rescale(samples: usize, from: usize, to: usize) -> usize {
+    (samples as f64 * to as f64 / from.max(1) as f64).round() as usize
+}
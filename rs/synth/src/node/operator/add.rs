@@ -1,17 +1,17 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Unit};
 use hashbrown::HashMap;
 
 use super::apply_op;
 #[derive(Debug, Clone)]
 pub struct Add {
-    val: f32,
+    val: Automation,
     input_order: Vec<usize>,
 }
 
 impl Add {
     pub fn new(val: f32) -> Self {
         Self {
-            val,
+            val: Automation::new(val),
             input_order: Vec::<usize>::new(),
         }
     }
@@ -30,14 +30,36 @@ impl<const N: usize> Node<N> for Add {
             inputs,
             &self.input_order,
             output,
-            self.val,
+            &mut self.val,
             std::ops::Add::add,
         )
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new(
+            "value",
+            0,
+            (f32::NEG_INFINITY, f32::INFINITY),
+            0.,
+            Unit::None,
+        )];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(0, value) => self.val.schedule(offset, value, Ramp::Step),
+            Message::RampToNumber(0, value, ramp) => self.val.schedule(offset, value, ramp),
+            info => Node::<N>::send_msg(self, info),
+        }
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
-            Message::SetToNumber(0, value) => self.val = value,
+            Message::SetToNumber(0, value) => self.val.set(value),
+            Message::RampToNumber(0, value, ramp) => self.val.schedule(0, value, ramp),
+            Message::SetSmoothing(None | Some(0), smoothing) => self.val.set_smoothing(smoothing),
+            Message::SetSampleRate(sr) => self.val.set_sr(sr),
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
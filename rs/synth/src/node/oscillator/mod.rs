@@ -7,21 +7,21 @@ mod tri_osc;
 pub use squ_osc::SquOsc;
 mod squ_osc;
 
-use crate::{Buffer, Input};
+use crate::{Automation, Buffer, Input};
 use hashbrown::HashMap;
 
 fn process_oscillation<const N: usize>(
     inputs: &mut HashMap<usize, Input<N>>,
     input_order: &mut [usize],
     output: &mut [Buffer<N>],
-    freq: f32,
+    freq: &mut Automation,
     inc: &mut f32,
     mut osc: impl FnMut(&mut f32, f32),
 ) {
     match inputs.len() {
         0 => {
             for out in &mut *output[0] {
-                osc(out, freq);
+                osc(out, freq.tick());
             }
         }
         1 => {
@@ -30,6 +30,7 @@ fn process_oscillation<const N: usize>(
                 [ref first_input, ..] => &inputs[first_input],
             };
 
+            freq.skip(N);
             for (out, mod_buf) in output[0].iter_mut().zip(mod_input.buffers()[0].iter()) {
                 if *mod_buf != 0. {
                     *inc = *mod_buf;
@@ -38,6 +39,6 @@ fn process_oscillation<const N: usize>(
                 osc(out, *inc);
             }
         }
-        _ => {}
+        _ => freq.skip(N),
     }
 }
@@ -1,10 +1,10 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Unit};
 use hashbrown::HashMap;
 
 use super::process_oscillation;
 #[derive(Debug, Clone)]
 pub struct SawOsc {
-    pub freq: f32,
+    pub freq: Automation,
     pub phase: f32,
     pub sr: usize,
     inc: f32,
@@ -14,7 +14,7 @@ pub struct SawOsc {
 impl std::default::Default for SawOsc {
     fn default() -> Self {
         Self {
-            freq: 1.0,
+            freq: Automation::new(1.0),
             phase: 0.0,
             sr: 44100,
             inc: 0.,
@@ -28,7 +28,10 @@ impl SawOsc {
         Self::default()
     }
     pub fn freq(self, freq: f32) -> Self {
-        Self { freq, ..self }
+        Self {
+            freq: Automation::new(freq),
+            ..self
+        }
     }
     pub fn sr(self, sr: usize) -> Self {
         Self { sr, ..self }
@@ -44,7 +47,7 @@ impl<const N: usize> Node<N> for SawOsc {
             inputs,
             &mut self.input_order,
             output,
-            self.freq,
+            &mut self.freq,
             &mut self.inc,
             |out, freq| {
                 *out = self.phase * 2. - 1.;
@@ -56,9 +59,28 @@ impl<const N: usize> Node<N> for SawOsc {
         );
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new("freq", 0, (0., 20000.), 1., Unit::Hz)];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(0, value) => self.freq.schedule(offset, value, Ramp::Step),
+            Message::RampToNumber(0, value, ramp) => self.freq.schedule(offset, value, ramp),
+            info => Node::<N>::send_msg(self, info),
+        }
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
-            Message::SetToNumber(0, value) => self.freq = value,
+            Message::SetToNumber(0, value) => self.freq.set(value),
+            Message::RampToNumber(0, value, ramp) => self.freq.schedule(0, value, ramp),
+            Message::SetSmoothing(None | Some(0), smoothing) => self.freq.set_smoothing(smoothing),
+            Message::SetSampleRate(sr) => {
+                self.sr = sr;
+                self.freq.set_sr(sr);
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
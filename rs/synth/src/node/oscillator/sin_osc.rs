@@ -1,8 +1,8 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Unit};
 use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub struct SinOsc {
-    pub freq: f32,
+    pub freq: Automation,
     pub phase: f32,
     pub sr: usize,
     input_order: Vec<usize>,
@@ -11,7 +11,7 @@ pub struct SinOsc {
 impl std::default::Default for SinOsc {
     fn default() -> Self {
         Self {
-            freq: 1.0,
+            freq: Automation::new(1.0),
             phase: 0.0,
             sr: 44100,
             input_order: vec![],
@@ -24,7 +24,10 @@ impl SinOsc {
         Self::default()
     }
     pub fn freq(self, freq: f32) -> Self {
-        Self { freq, ..self }
+        Self {
+            freq: Automation::new(freq),
+            ..self
+        }
     }
     pub fn sr(self, sr: usize) -> Self {
         Self { sr, ..self }
@@ -39,10 +42,11 @@ impl<const N: usize> Node<N> for SinOsc {
         match inputs.len() {
             0 => {
                 for i in 0..N {
+                    let freq = self.freq.tick();
                     for buf in output.iter_mut() {
                         buf[i] = (self.phase * 2.0 * std::f32::consts::PI).sin();
                     }
-                    self.phase += self.freq / self.sr as f32;
+                    self.phase += freq / self.sr as f32;
                     if self.phase > 1.0 {
                         self.phase -= 1.0
                     }
@@ -54,6 +58,7 @@ impl<const N: usize> Node<N> for SinOsc {
                     [ref first_input, ..] => &inputs[first_input],
                 };
 
+                self.freq.skip(N);
                 for (i, mod_buf) in mod_input.buffers()[0].iter().enumerate() {
                     for buf in output.iter_mut() {
                         buf[i] = (self.phase * 2.0 * std::f32::consts::PI).sin();
@@ -65,12 +70,31 @@ impl<const N: usize> Node<N> for SinOsc {
                     }
                 }
             }
-            _ => (),
+            _ => self.freq.skip(N),
         }
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new("freq", 0, (0., 20000.), 1., Unit::Hz)];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(0, value) => self.freq.schedule(offset, value, Ramp::Step),
+            Message::RampToNumber(0, value, ramp) => self.freq.schedule(offset, value, ramp),
+            info => Node::<N>::send_msg(self, info),
+        }
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
-            Message::SetToNumber(0, value) => self.freq = value,
+            Message::SetToNumber(0, value) => self.freq.set(value),
+            Message::RampToNumber(0, value, ramp) => self.freq.schedule(0, value, ramp),
+            Message::SetSmoothing(None | Some(0), smoothing) => self.freq.set_smoothing(smoothing),
+            Message::SetSampleRate(sr) => {
+                self.sr = sr;
+                self.freq.set_sr(sr);
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
@@ -1,8 +1,11 @@
-use crate::{oscillator::process_oscillation, Buffer, Input, Message, Node};
+use crate::{
+    oscillator::process_oscillation, Automation, Buffer, Input, Message, Node, ParamInfo, Ramp,
+    Unit,
+};
 use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub struct TriOsc {
-    pub freq: f32,
+    pub freq: Automation,
     pub phase: f32,
     pub sr: usize,
     inc: f32,
@@ -12,7 +15,7 @@ pub struct TriOsc {
 impl std::default::Default for TriOsc {
     fn default() -> Self {
         Self {
-            freq: 1.0,
+            freq: Automation::new(1.0),
             phase: 0.0,
             sr: 44100,
             inc: 0.,
@@ -26,7 +29,10 @@ impl TriOsc {
         Self::default()
     }
     pub fn freq(self, freq: f32) -> Self {
-        Self { freq, ..self }
+        Self {
+            freq: Automation::new(freq),
+            ..self
+        }
     }
     pub fn sr(self, sr: usize) -> Self {
         Self { sr, ..self }
@@ -42,7 +48,7 @@ impl<const N: usize> Node<N> for TriOsc {
             inputs,
             &mut self.input_order,
             output,
-            self.freq,
+            &mut self.freq,
             &mut self.inc,
             |out, freq| {
                 let v = -1.0 + (self.phase * 2.);
@@ -56,9 +62,28 @@ impl<const N: usize> Node<N> for TriOsc {
             },
         );
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new("freq", 0, (0., 20000.), 1., Unit::Hz)];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(0, value) => self.freq.schedule(offset, value, Ramp::Step),
+            Message::RampToNumber(0, value, ramp) => self.freq.schedule(offset, value, ramp),
+            info => Node::<N>::send_msg(self, info),
+        }
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
-            Message::SetToNumber(0, value) => self.freq = value,
+            Message::SetToNumber(0, value) => self.freq.set(value),
+            Message::RampToNumber(0, value, ramp) => self.freq.schedule(0, value, ramp),
+            Message::SetSmoothing(None | Some(0), smoothing) => self.freq.set_smoothing(smoothing),
+            Message::SetSampleRate(sr) => {
+                self.sr = sr;
+                self.freq.set_sr(sr);
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// What a node parameter is measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// A plain number, e.g. a gain or a ratio.
+    None,
+    Hz,
+    Ms,
+    Seconds,
+    Samples,
+    /// Not a number, e.g. a sample name, a pattern or some code.
+    Symbol,
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None | Self::Symbol => "",
+            Self::Hz => "Hz",
+            Self::Ms => "ms",
+            Self::Seconds => "s",
+            Self::Samples => "samples",
+        })
+    }
+}
+
+/// Describes one of the parameters a node takes in `Message::SetToNumber(index, ..)` and the
+/// other `SetTo..` messages.
+///
+/// `range` and `default` are what makes sense musically, not hard limits: nodes don't reject
+/// values outside of `range`. They are meaningless for `Unit::Symbol` parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub index: u8,
+    pub range: (f32, f32),
+    pub default: f32,
+    pub unit: Unit,
+}
+
+impl ParamInfo {
+    pub const fn new(
+        name: &'static str,
+        index: u8,
+        range: (f32, f32),
+        default: f32,
+        unit: Unit,
+    ) -> Self {
+        Self {
+            name,
+            index,
+            range,
+            default,
+            unit,
+        }
+    }
+
+    /// A parameter that doesn't take a number.
+    pub const fn symbol(name: &'static str, index: u8) -> Self {
+        Self::new(name, index, (0., 0.), 0., Unit::Symbol)
+    }
+}
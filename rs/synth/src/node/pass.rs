@@ -20,12 +20,13 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::{Buffer, Input, Message, Node};
+use crate::{mix_into, Buffer, Input, Message, Node};
 use hashbrown::HashMap;
 
 /// A simple node that passes an input directly to the output.
 ///
-/// Works by mem-copying each buffer of the first input to each buffer of the output respectively.
+/// The first input is copied to the output, up- or downmixed to the channel count of the output
+/// following [`mix_into`](crate::mix_into).
 ///
 /// This can be useful as an intermediary node when feeding the output of a node back into one of
 /// its inputs. It can also be useful for discarding excess input channels by having a `Pass` with
@@ -39,17 +40,10 @@ impl<const N: usize> Node<N> for Pass {
             return;
         };
 
-        match (input.buffers(), &mut *output) {
-            ([ref in_buf], [out_left, out_right]) => {
-                out_left.copy_from_slice(in_buf);
-                out_right.copy_from_slice(in_buf);
-            }
-            _ => {
-                for (out_buf, in_buf) in output.iter_mut().zip(input.buffers()) {
-                    out_buf.copy_from_slice(in_buf);
-                }
-            }
+        for out in output.iter_mut() {
+            out.silence();
         }
+        mix_into(input.buffers(), output);
     }
     fn send_msg(&mut self, _info: Message) {}
 }
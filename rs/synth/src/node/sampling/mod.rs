@@ -2,3 +2,53 @@ mod sampler;
 pub use sampler::*;
 mod psampler;
 pub use psampler::*;
+
+use crate::{Buffer, Sample};
+
+/// Where in a sample a sampler starts, ends or loops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    /// From 0 at the first frame to 1 at the last one.
+    Fraction(f32),
+    /// Milliseconds into the sample, at its own sample rate.
+    Ms(f32),
+}
+
+impl Position {
+    // the frame this is at in `sample`, within it
+    fn frame(self, sample: &Sample) -> f32 {
+        let span = sample.frames().saturating_sub(1) as f32;
+        let frame = match self {
+            Self::Fraction(f) => f * span,
+            Self::Ms(ms) => ms / 1000. * sample.sr as f32,
+        };
+        frame.clamp(0.0, span)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    /// Play once and stop.
+    #[default]
+    Off,
+    /// Jump back to the loop start at the loop end.
+    Forward,
+    /// Turn around at either end of the loop.
+    PingPong,
+}
+
+// Add frame `pos` of `sample` to frame `i` of `output`, while `step` frames go by per output
+// sample, with `gains` for the left and right channels. A mono sample goes to every channel, and
+// a stereo one alternates.
+fn add_frame<const N: usize>(
+    sample: &Sample,
+    pos: f32,
+    step: f32,
+    gains: [f32; 2],
+    output: &mut [Buffer<N>],
+    i: usize,
+) {
+    for (c, out) in output.iter_mut().enumerate() {
+        out[i] += sample.read(c % sample.channels, pos, step) * gains[c % 2];
+    }
+}
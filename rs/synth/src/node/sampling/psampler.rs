@@ -1,12 +1,35 @@
-use crate::{Buffer, Input, Message, Node};
+use super::add_frame;
+use crate::{node::rescale, Buffer, Input, Message, Node, Sample, SampleEvent};
 use hashbrown::HashMap;
+use std::sync::Arc;
+
+// how long a voice that is cut takes to fade out, so that it doesn't click
+const FADE_MS: f32 = 5.0;
+
+#[derive(Debug, Clone)]
+struct Voice {
+    // the step it started at
+    begin: usize,
+    sample: Sample,
+    // in steps
+    dur: f32,
+    gains: [f32; 2],
+    // the steps left of its fade-out, once it has been cut
+    fade: Option<usize>,
+}
+
+impl Voice {
+    fn finished(&self, step: usize) -> bool {
+        (step - self.begin) as f32 / self.dur > 1.0 || self.fade == Some(0)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct PSampler {
-    playback: Vec<(usize, String, f32)>,
-    samples_dict: HashMap<String, (&'static [f32], usize, usize)>,
+    playback: Vec<Voice>,
+    samples_dict: HashMap<String, Sample>,
     pub events: Vec<(String, f32)>,
-    pattern: Vec<(String, f32)>,
+    pattern: Vec<SampleEvent>,
     // len: usize,
     // endindex: usize,
     pub step: usize,
@@ -16,17 +39,22 @@ pub struct PSampler {
     input_order: Vec<usize>,
 }
 
+// room for every sample to play all its voices, and as many again that are fading out
+fn voices_for(samples_dict: &HashMap<String, Sample>) -> usize {
+    samples_dict.values().map(|s| s.max_voices.max(1) * 2).sum()
+}
+
 impl PSampler {
     pub fn new(
-        samples_dict: HashMap<String, (&'static [f32], usize, usize)>,
+        samples_dict: HashMap<String, Sample>,
         sr: usize,
         bpm: f32,
         events: Vec<(String, f32)>,
-        pattern: Vec<(String, f32)>,
+        pattern: Vec<SampleEvent>,
         period_in_cycle: f32,
     ) -> Self {
         Self {
-            playback: vec![],
+            playback: Vec::with_capacity(voices_for(&samples_dict)),
             samples_dict,
             events,
             pattern,
@@ -39,6 +67,28 @@ impl PSampler {
             input_order: vec![],
         }
     }
+
+    fn fade_len(&self) -> usize {
+        ((FADE_MS / 1000. * self.sr as f32) as usize).max(1)
+    }
+}
+
+// fade out the voices that `sample` takes the place of over `fade` steps: those in its choke
+// group, and the oldest ones of its own beyond its voice limit
+fn make_room(playback: &mut [Voice], sample: &Sample, fade: usize) {
+    let playing = playback.iter_mut().filter(|v| v.fade.is_none());
+    if let Some(group) = sample.choke {
+        for voice in playing.filter(|v| v.sample.choke == Some(group)) {
+            voice.fade = Some(fade);
+        }
+    }
+    let same = |v: &Voice| v.fade.is_none() && Arc::ptr_eq(&v.sample.data, &sample.data);
+    let count = playback.iter().filter(|v| same(v)).count();
+    let excess = (count + 1).saturating_sub(sample.max_voices.max(1));
+    // they are started in order, so the oldest come first
+    for voice in playback.iter_mut().filter(|v| same(v)).take(excess) {
+        voice.fade = Some(fade);
+    }
 }
 
 impl<const N: usize> Node<N> for PSampler {
@@ -51,86 +101,52 @@ impl<const N: usize> Node<N> for PSampler {
 
         let bar_dur = self.cycle_dur * self.period_in_cycle * self.sr as f32;
 
+        let fade_len = self.fade_len();
         for i in 0..N {
             // if input_buf[0][i] > 0.0 {
 
             for event in &self.pattern {
-                if (self.step % (bar_dur as usize))
-                    == ((event.1 * self.cycle_dur * self.sr as f32) as usize)
+                if event.rate > 0.0
+                    && (self.step % (bar_dur as usize))
+                        == ((event.time * self.cycle_dur * self.sr as f32) as usize)
                 {
-                    let pitch = 1.0;
-                    let sample_name = &event.0;
-                    let sample = self.samples_dict[sample_name];
-                    let dur = (sample.0.len() / sample.1) as f32
-                        / pitch
-                        / (sample.2 as f32 / self.sr as f32);
-                    self.playback.push((self.step, sample_name.to_owned(), dur));
+                    let sample = self.samples_dict[&event.sample].clone();
+                    let dur =
+                        sample.frames() as f32 / event.rate / (sample.sr as f32 / self.sr as f32);
+                    make_room(&mut self.playback, &sample, fade_len);
+                    self.playback.push(Voice {
+                        begin: self.step,
+                        sample,
+                        dur,
+                        gains: event.gains(),
+                        fade: None,
+                    });
                 }
             }
 
-            let mut to_remove = Vec::with_capacity(self.playback.len());
-            for (count, (begin, name, dur)) in self.playback.iter().enumerate() {
-                let pos = (self.step - begin) as f32 / dur;
+            for voice in &mut self.playback {
+                let pos = (self.step - voice.begin) as f32 / voice.dur;
                 if pos <= 1.0 {
-                    let sample = self.samples_dict[name];
-                    match sample.1 {
-                        1 => {
-                            output[0][i] += match pos {
-                                x if x == 0.0 => sample.0[0],
-                                x if x == 1.0 => sample.0[sample.0.len() - 1],
-                                x if x > 0.0 && x < 1.0 => {
-                                    let pos_index_float = x * ((sample.0.len() - 1) as f32);
-                                    let left = pos_index_float.floor();
-                                    let right = pos_index_float.ceil();
-                                    let left_portion = pos_index_float - left;
-                                    let right_portion = 1. - left_portion;
-
-                                    sample.0[left as usize] * left_portion
-                                        + sample.0[right as usize] * right_portion
-                                }
-                                _ => 0.0,
-                            };
-                            output[1][i] = output[0][i];
+                    let span = voice.sample.frames().saturating_sub(1) as f32;
+                    let fade = match &mut voice.fade {
+                        Some(left) => {
+                            *left = left.saturating_sub(1);
+                            (*left + 1) as f32 / fade_len as f32
                         }
-                        2 => {
-                            match pos {
-                                x if x == 0.0 => {
-                                    output[0][i] += sample.0[0];
-                                    output[1][i] += sample.0[sample.0.len() / sample.1];
-                                }
-                                x if x == 1.0 => {
-                                    output[0][i] += sample.0[(sample.0.len() / sample.1) - 1];
-                                    output[1][i] += sample.0[sample.0.len() - 1];
-                                }
-                                x if x > 0.0 && x < 1.0 => {
-                                    let pos_index_float =
-                                        x * (((sample.0.len() / sample.1) - 2) as f32);
-                                    let left = pos_index_float.floor();
-                                    let right = pos_index_float.ceil();
-                                    let left_portion = pos_index_float - left;
-                                    let right_portion = 1. - left_portion;
-
-                                    output[0][i] += sample.0[left as usize] * left_portion
-                                        + sample.0[right as usize] * right_portion;
-
-                                    output[1][i] += sample.0
-                                        [left as usize + (sample.0.len() / sample.1) + 1]
-                                        * left_portion
-                                        + sample.0[right as usize + (sample.0.len() / sample.1) + 1]
-                                            * right_portion
-                                }
-                                _ => {}
-                            };
-                        }
-                        _ => return,
-                    }
-                } else {
-                    to_remove.push(count)
+                        None => 1.0,
+                    };
+                    let gains = voice.gains.map(|g| g * fade);
+                    add_frame(
+                        &voice.sample,
+                        pos * span,
+                        span / voice.dur,
+                        gains,
+                        output,
+                        i,
+                    );
                 }
             }
-            for c in to_remove.iter().rev() {
-                self.playback.remove(*c);
-            }
+            self.playback.retain(|voice| !voice.finished(self.step));
             self.step += 1;
         }
     }
@@ -139,10 +155,28 @@ impl<const N: usize> Node<N> for PSampler {
         match info {
             Message::SetSamplePattern(pattern, span, samples_dict) => {
                 self.playback.clear();
+                self.playback.reserve(voices_for(&samples_dict));
                 self.pattern = pattern;
                 self.samples_dict = samples_dict;
                 self.period_in_cycle = span
             }
+            Message::ReplaceSample(old, new) => {
+                let playing = self.playback.iter_mut().map(|voice| &mut voice.sample);
+                for sample in self.samples_dict.values_mut().chain(playing) {
+                    if Arc::ptr_eq(&sample.data, &old.data) {
+                        *sample = new.clone();
+                    }
+                }
+            }
+            Message::SetSampleRate(sr) => {
+                for voice in &mut self.playback {
+                    voice.begin = rescale(voice.begin, self.sr, sr);
+                    voice.dur *= sr as f32 / self.sr as f32;
+                    voice.fade = voice.fade.map(|left| rescale(left, self.sr, sr));
+                }
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
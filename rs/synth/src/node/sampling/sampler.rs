@@ -1,123 +1,223 @@
-use crate::{Buffer, Input, Message, Node};
+use super::{add_frame, LoopMode, Position};
+use crate::{Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Sample, Unit};
 use hashbrown::HashMap;
+use std::sync::Arc;
 
+#[derive(Debug, Clone, Copy)]
+struct Voice {
+    // in frames of the sample
+    pos: f32,
+    // the trigger, which scales the pitch
+    rate: f32,
+    // 1 forwards, -1 backwards
+    direction: f32,
+}
+
+/// Plays its sample whenever its input goes above zero, at a rate of that value times its pitch.
+/// It plays from `start` to `end`, the other way round when reversed, and a looping one plays
+/// a single voice that carries on until the next trigger.
 #[derive(Debug, Clone)]
 pub struct Sampler {
-    playback: Vec<(usize, f32)>,
-    pub sample: (&'static [f32], usize, usize),
-    len: usize,
-    endindex: usize,
-    clock: usize,
+    voices: Vec<Voice>,
+    pub sample: Sample,
+    pitch: Automation,
+    start: Position,
+    end: Position,
+    loop_mode: LoopMode,
+    loop_points: Option<(Position, Position)>,
+    reverse: bool,
     sr: usize,
     input_order: Vec<usize>,
 }
 
 impl Sampler {
-    pub fn new(sample: (&'static [f32], usize, usize), sr: usize) -> Self {
+    pub fn new(sample: Sample, sr: usize) -> Self {
         Self {
-            playback: vec![],
+            voices: vec![],
             sample,
-            len: sample.0.len() / sample.1, // total_length/channels
-            endindex: sample.0.len() - 1,
-            clock: 0,
+            pitch: Automation::new(1.0),
+            start: Position::Fraction(0.0),
+            end: Position::Fraction(1.0),
+            loop_mode: LoopMode::Off,
+            loop_points: None,
+            reverse: false,
             sr,
             input_order: vec![],
         }
     }
+
+    pub fn pitch(self, pitch: f32) -> Self {
+        Self {
+            pitch: Automation::new(pitch),
+            ..self
+        }
+    }
+
+    pub fn start(self, start: Position) -> Self {
+        Self { start, ..self }
+    }
+
+    pub fn end(self, end: Position) -> Self {
+        Self { end, ..self }
+    }
+
+    /// Loop between `points`, or between `start` and `end` without them.
+    pub fn looping(self, loop_mode: LoopMode, loop_points: Option<(Position, Position)>) -> Self {
+        Self {
+            loop_mode,
+            loop_points,
+            ..self
+        }
+    }
+
+    pub fn reverse(self, reverse: bool) -> Self {
+        Self { reverse, ..self }
+    }
+
+    // the first and last frames of `a` and `b`, whichever way round they are
+    fn frames(&self, (a, b): (Position, Position)) -> (f32, f32) {
+        let (a, b) = (a.frame(&self.sample), b.frame(&self.sample));
+        (a.min(b), a.max(b))
+    }
+
+    fn trigger(&mut self, rate: f32) {
+        if self.loop_mode != LoopMode::Off {
+            self.voices.clear();
+        }
+        let (start, end) = self.frames((self.start, self.end));
+        self.voices.push(if self.reverse {
+            Voice {
+                pos: end,
+                rate,
+                direction: -1.0,
+            }
+        } else {
+            Voice {
+                pos: start,
+                rate,
+                direction: 1.0,
+            }
+        });
+    }
+
+    // play every voice for one frame, and let go of the ones that have finished
+    fn play<const N: usize>(&mut self, pitch: f32, output: &mut [Buffer<N>], i: usize) {
+        let ratio = self.sample.sr as f32 / self.sr as f32;
+        let (start, end) = self.frames((self.start, self.end));
+        let (loop_start, loop_end) =
+            self.frames(self.loop_points.unwrap_or((self.start, self.end)));
+        let looping = self.loop_mode != LoopMode::Off && loop_end > loop_start;
+        let sample = &self.sample;
+        let loop_mode = self.loop_mode;
+
+        self.voices.retain_mut(|voice| {
+            let step = voice.rate * pitch * ratio;
+            add_frame(sample, voice.pos, step.abs(), [1.0; 2], output, i);
+            let velocity = step * voice.direction;
+            voice.pos += velocity;
+            if !looping {
+                return (start..=end).contains(&voice.pos);
+            }
+            // only the end of the loop it is heading for wraps it, so that a voice that starts
+            // outside of the loop plays into it. A forward loop never plays its end, which is
+            // where its start comes round again
+            let past = if velocity > 0.0 {
+                voice.pos > loop_end || (loop_mode == LoopMode::Forward && voice.pos == loop_end)
+            } else {
+                voice.pos < loop_start
+            };
+            if past {
+                voice.pos = match loop_mode {
+                    LoopMode::PingPong => {
+                        voice.direction = -voice.direction;
+                        let edge = if velocity > 0.0 { loop_end } else { loop_start };
+                        (2.0 * edge - voice.pos).clamp(loop_start, loop_end)
+                    }
+                    _ => loop_start + (voice.pos - loop_start).rem_euclid(loop_end - loop_start),
+                };
+            }
+            true
+        });
+    }
 }
 
 impl<const N: usize> Node<N> for Sampler {
     fn process(&mut self, inputs: &mut HashMap<usize, Input<N>>, output: &mut [Buffer<N>]) {
         output[0].silence();
         output[1].silence();
-        if inputs.len() == 1 {
-            let main_input = inputs.values_mut().next().unwrap();
-            let input_buf = &mut main_input.buffers();
-            for i in 0..N {
-                if input_buf[0][i] > 0.0 {
-                    let dur =
-                        self.len as f32 / input_buf[0][i] / (self.sample.2 as f32 / self.sr as f32);
-                    self.playback.push((self.clock, dur));
-                }
-
-                let mut to_remove = vec![];
-                for (count, (begin, dur)) in self.playback.iter().enumerate() {
-                    let pos = (self.clock - begin) as f32 / dur;
-                    if pos <= 1.0 {
-                        match self.sample.1 {
-                            1 => {
-                                output[0][i] += match pos {
-                                    0.0 => self.sample.0[0],
-                                    1.0 => self.sample.0[self.endindex],
-                                    // this match arm guard is technically not correct but since
-                                    // we've already guaranteed that it can't be 0.0 or 1.0, it's
-                                    // fine
-                                    0.0..=1.0 => {
-                                        let pos_index_float = pos * (self.endindex as f32);
-                                        let left = pos_index_float.floor();
-                                        let right = pos_index_float.ceil();
-                                        let left_portion = pos_index_float - left;
-                                        let right_portion = 1. - left_portion;
-
-                                        self.sample.0[left as usize] * left_portion
-                                            + self.sample.0[right as usize] * right_portion
-                                    }
-                                    _ => 0.0,
-                                };
-                                output[1][i] = output[0][i];
-                            }
-                            2 => match pos {
-                                0.0 => {
-                                    output[0][i] += self.sample.0[0];
-                                    output[1][i] += self.sample.0[self.len];
-                                }
-                                1.0 => {
-                                    output[0][i] += self.sample.0[self.len - 1];
-                                    output[1][i] += self.sample.0[self.endindex];
-                                }
-                                // same comment here about match arms as above
-                                0.0..=1.0 => {
-                                    let pos_index_float = pos * ((self.len - 2) as f32);
-                                    let left = pos_index_float.floor();
-                                    let right = pos_index_float.ceil();
-                                    let left_portion = pos_index_float - left;
-                                    let right_portion = 1. - left_portion;
-
-                                    output[0][i] += self.sample.0[left as usize] * left_portion
-                                        + self.sample.0[right as usize] * right_portion;
-
-                                    output[1][i] += self.sample.0[left as usize + self.len + 1]
-                                        * left_portion
-                                        + self.sample.0[right as usize + self.len + 1]
-                                            * right_portion;
-                                }
-                                _ => {}
-                            },
-                            _ => (),
-                        }
-                    } else {
-                        // panic!();
-                        to_remove.push(count)
-                    }
-                }
-                for c in to_remove.iter().rev() {
-                    self.playback.remove(*c);
-                }
-                // if self.playback.len() > 10 {
-                //     panic!("too much playback")
-                // }
-
-                self.clock += 1;
+        let (trigger, pitch) = match inputs.len() {
+            1 => (inputs.values().next().unwrap(), None),
+            2 => (
+                &inputs[&self.input_order[0]],
+                Some(&inputs[&self.input_order[1]]),
+            ),
+            _ => {
+                self.pitch.skip(N);
+                return;
+            }
+        };
+        let (trigger, pitch) = (trigger.buffers(), pitch.map(|p| p.buffers()));
+        for i in 0..N {
+            if trigger[0][i] > 0.0 {
+                self.trigger(trigger[0][i]);
             }
+            // ticked either way, so that what is scheduled lands on time
+            let value = self.pitch.tick();
+            let pitch = pitch.map_or(value, |buf| buf[0][i]);
+            self.play(pitch, output, i);
+        }
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::symbol("sample", 0),
+            ParamInfo::new("pitch", 1, (0.25, 4.), 1., Unit::None),
+            ParamInfo::new("start", 2, (0., 1.), 0., Unit::None),
+            ParamInfo::new("end", 3, (0., 1.), 1., Unit::None),
+            ParamInfo::new("loop_start", 4, (0., 1.), 0., Unit::None),
+            ParamInfo::new("loop_end", 5, (0., 1.), 1., Unit::None),
+        ];
+        PARAMS
+    }
+
+    fn input_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(1, value) => self.pitch.schedule(offset, value, Ramp::Step),
+            Message::RampToNumber(1, value, ramp) => self.pitch.schedule(offset, value, ramp),
+            info => Node::<N>::send_msg(self, info),
         }
     }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToSamples(0, sample) => {
                 self.sample = sample;
-                self.len = sample.0.len() / sample.1;
-                self.endindex = sample.0.len() - 1;
-                // self.playback.clear();
+                // self.voices.clear();
+            }
+            Message::ReplaceSample(old, new) if Arc::ptr_eq(&self.sample.data, &old.data) => {
+                self.sample = new
+            }
+            Message::SetToNumber(1, value) => self.pitch.set(value),
+            Message::RampToNumber(1, value, ramp) => self.pitch.schedule(0, value, ramp),
+            Message::SetSmoothing(None | Some(1), smoothing) => self.pitch.set_smoothing(smoothing),
+            Message::SetToNumber(2, value) => self.start = Position::Fraction(value),
+            Message::SetToNumber(3, value) => self.end = Position::Fraction(value),
+            Message::SetToNumber(4, value) => {
+                let (_, end) = self.loop_points.unwrap_or((self.start, self.end));
+                self.loop_points = Some((Position::Fraction(value), end));
+            }
+            Message::SetToNumber(5, value) => {
+                let (start, _) = self.loop_points.unwrap_or((self.start, self.end));
+                self.loop_points = Some((start, Position::Fraction(value)));
+            }
+            // the voices are in frames of the sample, so they carry on at the same speed
+            Message::SetSampleRate(sr) => {
+                self.sr = sr;
+                self.pitch.set_sr(sr);
             }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node};
 use glicol_parser::nodes::NumberOrRef;
 use hashbrown::HashMap;
 
@@ -92,6 +92,10 @@ impl<const N: usize> Node<N> for Arrange {
                     .extend(std::iter::from_fn(|| Some(NumberOrRef::Number(0.0))).take(to_push));
                 self.events.push(NumberOrRef::Number(value));
             }
+            Message::SetSampleRate(sr) => {
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             Message::ResetOrder => {
@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo};
 use dasp_signal::{self as signal, Signal};
 use hashbrown::HashMap;
 
@@ -27,6 +27,11 @@ impl<const N: usize> Node<N> for Choose {
         };
         output[0].iter_mut().for_each(|s| *s = self.note_list[id]);
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::symbol("choices", 0)];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumberList(0, list) => self.note_list = list,
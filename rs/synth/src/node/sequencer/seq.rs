@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node};
 use glicol_parser::nodes::UsizeOrRef;
 use hashbrown::HashMap;
 
@@ -105,6 +105,10 @@ impl<const N: usize> Node<N> for Sequencer {
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetBPM(bpm) => self.bpm = bpm,
+            Message::SetSampleRate(sr) => {
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+            }
             Message::SetToSeq(0, events) => self.events = events,
             Message::SetRefOrder(ref_order) => {
                 self.ref_order = ref_order;
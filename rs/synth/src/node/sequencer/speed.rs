@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 
 #[derive(Debug, Clone)]
@@ -21,6 +21,17 @@ impl<const N: usize> Node<N> for Speed {
         output[0].silence();
         output[0][0] = self.val;
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new(
+            "speed",
+            0,
+            (0., f32::INFINITY),
+            1.,
+            Unit::None,
+        )];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => self.val = value,
@@ -1,8 +1,8 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Automation, Buffer, Input, Message, Node, ParamInfo, Ramp, Unit};
 use hashbrown::HashMap;
 #[derive(Debug, Clone)]
 pub struct ConstSig {
-    val: f32,
+    val: Automation,
     events: Vec<(f32, f32)>,
     pattern: Vec<(f32, f32)>,
     span: f32,
@@ -15,7 +15,7 @@ pub struct ConstSig {
 impl ConstSig {
     pub fn new(val: f32) -> Self {
         Self {
-            val,
+            val: Automation::new(val),
             events: vec![],
             pattern: vec![],
             span: 1.,
@@ -57,7 +57,7 @@ impl<const N: usize> Node<N> for ConstSig {
                 if (self.step % (bar_dur as usize))
                     == ((event.1 * cycle_dur * self.sr as f32) as usize)
                 {
-                    self.val = event.0
+                    self.val.jump(event.0)
                 }
             }
 
@@ -65,22 +65,48 @@ impl<const N: usize> Node<N> for ConstSig {
                 if (self.step % (bar_dur as usize))
                     == ((event.1 * cycle_dur * self.sr as f32) as usize)
                 {
-                    self.val = event.0
+                    self.val.jump(event.0)
                 }
             }
 
-            *out = self.val;
+            *out = self.val.tick();
             self.step += 1;
         }
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new(
+            "value",
+            0,
+            (f32::NEG_INFINITY, f32::INFINITY),
+            0.,
+            Unit::None,
+        )];
+        PARAMS
+    }
+
+    fn send_msg_at(&mut self, offset: usize, info: Message) {
+        match info {
+            Message::SetToNumber(0, value) => self.val.schedule(offset, value, Ramp::Step),
+            Message::RampToNumber(0, value, ramp) => self.val.schedule(offset, value, ramp),
+            info => Node::<N>::send_msg(self, info),
+        }
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetPattern(p, span) => {
                 self.pattern = p;
                 self.span = span;
             }
-            Message::SetToNumber(0, value) => self.val = value,
+            Message::SetToNumber(0, value) => self.val.set(value),
+            Message::RampToNumber(0, value, ramp) => self.val.schedule(0, value, ramp),
+            Message::SetSmoothing(None | Some(0), smoothing) => self.val.set_smoothing(smoothing),
             Message::SetBPM(bpm) => self.bpm = bpm,
+            Message::SetSampleRate(sr) => {
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+                self.val.set_sr(sr);
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             _ => {}
@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 
 #[derive(Debug, Clone)]
@@ -48,9 +48,19 @@ impl<const N: usize> Node<N> for Impulse {
         }
     }
 
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new("freq", 0, (0., 20000.), 1., Unit::Hz)];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => self.period = (self.sr as f32 / value) as usize,
+            Message::SetSampleRate(sr) => {
+                self.period = rescale(self.period, self.sr, sr).max(1);
+                self.clock = rescale(self.clock, self.sr, sr);
+                self.sr = sr;
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             _ => {}
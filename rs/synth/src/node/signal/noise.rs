@@ -1,4 +1,4 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 
 use dasp_signal::{self as signal, Signal};
@@ -23,6 +23,17 @@ impl<const N: usize> Node<N> for Noise {
             out.iter_mut().for_each(|s| *s = self.sig.next() as f32);
         }
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::new(
+            "seed",
+            0,
+            (0., f32::INFINITY),
+            42.,
+            Unit::None,
+        )];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(0, value) => self.sig = Box::new(signal::noise(value as u64)),
@@ -1,5 +1,5 @@
 use crate::GlicolPara;
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node, ParamInfo, Unit};
 use glicol_parser::nodes::{Duration, TimeList};
 use hashbrown::HashMap;
 
@@ -152,6 +152,15 @@ impl<const N: usize> Node<N> for Points {
             }
         }
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::symbol("points", 0),
+            ParamInfo::new("span", 1, (0., f32::INFINITY), 1., Unit::None),
+            ParamInfo::new("is_looping", 2, (0., 1.), 0., Unit::None),
+        ];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetParam(0, GlicolPara::Points(time_list)) => {
@@ -167,6 +176,13 @@ impl<const N: usize> Node<N> for Points {
                 self.step = 0;
             }
             Message::SetBPM(bpm) => self.bpm = bpm,
+            Message::SetSampleRate(sr) => {
+                for (time, _) in &mut self.point_list {
+                    *time = rescale(*time, self.sr, sr);
+                }
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+            }
             Message::Index(i) => self.input_order.push(i),
             Message::IndexOrder(pos, index) => self.input_order.insert(pos, index),
             _ => {}
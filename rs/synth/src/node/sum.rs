@@ -20,7 +20,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::{Buffer, Input, Message, Node};
+use crate::{mix_into, Buffer, Input, Message, Node};
 use hashbrown::HashMap;
 
 /// A stateless node that sums each of the inputs onto the output.
@@ -45,6 +45,8 @@ pub struct Sum;
 #[derive(Clone, Debug, PartialEq)]
 pub struct SumBuffers;
 
+/// A stateless node that sums the inputs onto the output, up- or downmixing every input to the
+/// channel count of the output following [`mix_into`](crate::mix_into).
 #[derive(Clone, Debug, PartialEq)]
 pub struct Sum2;
 
@@ -74,19 +76,8 @@ impl<const N: usize> Node<N> for Sum2 {
         for out_buffer in output.iter_mut() {
             out_buffer.silence();
         }
-        // Sum the inputs onto the output.
-        for (channel, out_buffer) in output.iter_mut().enumerate() {
-            for input in inputs.values() {
-                let in_buffers = input.buffers();
-                match in_buffers.get(channel) {
-                    Some(in_buffer) => {
-                        dasp_slice::add_in_place(out_buffer, in_buffer);
-                    }
-                    None => {
-                        dasp_slice::add_in_place(out_buffer, &in_buffers[0]);
-                    }
-                };
-            }
+        for input in inputs.values() {
+            mix_into(input.buffers(), output);
         }
     }
     fn send_msg(&mut self, _info: Message) {}
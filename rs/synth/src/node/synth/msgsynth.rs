@@ -1,12 +1,12 @@
 use std::cmp::Ordering;
 
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node, ParamInfo, Unit};
 use hashbrown::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct MsgSynth {
-    synth_list: Vec<(usize, f32)>,
-    phase_list: Vec<f32>,
+    // (start step, freq, phase) of the notes that are playing
+    voices: Vec<(usize, f32, f32)>,
     att: f32,
     dec: f32,
     events: Vec<(usize, f32)>, // event.0 is step to play the note, event.1 is midi
@@ -27,8 +27,7 @@ impl Default for MsgSynth {
 impl MsgSynth {
     pub fn new() -> Self {
         Self {
-            synth_list: vec![],
-            phase_list: vec![],
+            voices: vec![],
             events: vec![], // test with (88200, 60.)
             att: 0.001,
             dec: 0.1,
@@ -88,22 +87,16 @@ impl<const N: usize> Node<N> for MsgSynth {
                 if self.step == event.0 {
                     let midi = event.1;
                     let freq = 2f32.powf((midi - 69.) / 12.) * 440.;
-                    self.synth_list.push((self.step, freq));
-                    self.phase_list.push(0.0);
+                    self.voices.push((self.step, freq, 0.0));
                 }
             }
 
-            let mut to_remove = Vec::with_capacity(self.synth_list.len());
-            for (synth_index, (synth_info, phase)) in self
-                .synth_list
-                .iter()
-                .zip(self.phase_list.iter_mut())
-                .enumerate()
-            {
+            // drop the notes that are over in place, so that nothing is allocated
+            self.voices.retain_mut(|(start, freq, phase)| {
                 let dur = (self.att + self.dec) * self.sr as f32;
 
-                if self.step - synth_info.0 <= dur as usize {
-                    let pos = self.step - synth_info.0;
+                if self.step - *start <= dur as usize {
+                    let pos = self.step - *start;
 
                     let amp = match pos.cmp(&attack_n) {
                         Ordering::Greater | Ordering::Equal => {
@@ -123,25 +116,31 @@ impl<const N: usize> Node<N> for MsgSynth {
                     };
 
                     let phase_out = *phase * 2. - 1.;
-                    *phase += synth_info.1 / self.sr as f32;
+                    *phase += *freq / self.sr as f32;
                     if *phase > 1. {
                         *phase -= 1.
                     }
                     // println!("amp {} out {} step {}", amp, out, self.step);
                     *out += amp * phase_out * 0.1;
                     // println!("output[{}] {}",i, output[0][i]);
+                    true
                 } else {
-                    // remove this from start_step_list and output_list
-                    to_remove.push(synth_index)
+                    false
                 }
-            }
-            for c in to_remove.iter().rev() {
-                self.synth_list.remove(*c);
-                self.phase_list.remove(*c);
-            }
+            });
             self.step += 1;
         }
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[
+            ParamInfo::symbol("symbol", 0),
+            ParamInfo::new("attack", 1, (0., f32::INFINITY), 0.001, Unit::Seconds),
+            ParamInfo::new("decay", 2, (0., f32::INFINITY), 0.1, Unit::Seconds),
+            ParamInfo::symbol("event", 3),
+        ];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             Message::SetToNumber(pos, v) => match pos {
@@ -172,6 +171,16 @@ impl<const N: usize> Node<N> for MsgSynth {
                     _ => {}
                 }
             }
+            Message::SetSampleRate(sr) => {
+                for (start, ..) in &mut self.voices {
+                    *start = rescale(*start, self.sr, sr);
+                }
+                for (at, _) in &mut self.events {
+                    *at = rescale(*at, self.sr, sr);
+                }
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+            }
             Message::SetRefOrder(ref_order) => {
                 self.ref_order = ref_order;
             }
@@ -1,11 +1,11 @@
-use crate::{Buffer, Input, Message, Node};
+use crate::{node::rescale, Buffer, Input, Message, Node, ParamInfo};
 use hashbrown::HashMap;
 use std::cmp::Ordering;
 
 #[derive(Debug, Clone)]
 pub struct PatternSynth {
-    synth_list: Vec<(usize, f32)>,
-    phase_list: Vec<f32>,
+    // (start step, freq, phase) of the notes that are playing
+    voices: Vec<(usize, f32, f32)>,
     att: f32,
     dec: f32,
     events: Vec<(f32, f32)>, // event.0 is frac, event.1 is midi
@@ -20,8 +20,7 @@ pub struct PatternSynth {
 impl PatternSynth {
     pub fn new(events: Vec<(f32, f32)>) -> Self {
         Self {
-            synth_list: vec![],
-            phase_list: vec![],
+            voices: vec![],
             events,
             att: 0.001,
             dec: 0.1,
@@ -71,22 +70,16 @@ impl<const N: usize> Node<N> for PatternSynth {
 
                         // need to push current step to the playback list
                         // println!("{}{}", event.0 * self.cycle_dur);
-                        self.synth_list.push((self.step, freq));
-                        self.phase_list.push(0.0);
+                        self.voices.push((self.step, freq, 0.0));
                     }
                 }
 
-                let mut to_remove = Vec::with_capacity(self.synth_list.len());
-                for (synth_index, (synth_info, phase)) in self
-                    .synth_list
-                    .iter()
-                    .zip(self.phase_list.iter_mut())
-                    .enumerate()
-                {
+                // drop the notes that are over in place, so that nothing is allocated
+                self.voices.retain_mut(|(start, freq, phase)| {
                     let dur = (self.att + self.dec) * self.sr as f32;
 
-                    if self.step - synth_info.0 <= dur as usize {
-                        let pos = self.step - synth_info.0;
+                    if self.step - *start <= dur as usize {
+                        let pos = self.step - *start;
 
                         let amp = match pos.cmp(&attack_n) {
                             Ordering::Less | Ordering::Equal => {
@@ -106,28 +99,29 @@ impl<const N: usize> Node<N> for PatternSynth {
                         };
 
                         let phase_out = *phase * 2. - 1.;
-                        *phase += synth_info.1 / self.sr as f32;
+                        *phase += *freq / self.sr as f32;
                         if *phase > 1. {
                             *phase -= 1.
                         }
                         // println!("amp {} out {} step {}", amp, out, self.step);
                         *out += amp * phase_out * 0.1;
                         // println!("output[{}] {}",i, output[0][i]);
+                        true
                     } else {
-                        // remove this from start_step_list and output_list
-                        to_remove.push(synth_index)
+                        false
                     }
-                }
-                for c in to_remove.iter().rev() {
-                    self.synth_list.remove(*c);
-                    self.phase_list.remove(*c);
-                }
+                });
                 self.step += 1;
                 // println!("output, {}", output[0][i]);
             }
-            // println!("self.synth_list {:?} step, {:?}", self.synth_list, self.step);
+            // println!("self.voices {:?} step, {:?}", self.voices, self.step);
         }
     }
+    fn params(&self) -> &'static [ParamInfo] {
+        const PARAMS: &[ParamInfo] = &[ParamInfo::symbol("pattern", 0)];
+        PARAMS
+    }
+
     fn send_msg(&mut self, info: Message) {
         match info {
             // Message::SetBPM(bpm) => {
@@ -149,6 +143,13 @@ impl<const N: usize> Node<N> for PatternSynth {
                         .push((result.next().unwrap(), result.next().unwrap()));
                 }
             }
+            Message::SetSampleRate(sr) => {
+                for (start, ..) in &mut self.voices {
+                    *start = rescale(*start, self.sr, sr);
+                }
+                self.step = rescale(self.step, self.sr, sr);
+                self.sr = sr;
+            }
             Message::SetRefOrder(ref_order) => {
                 self.ref_order = ref_order;
             }
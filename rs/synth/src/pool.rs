@@ -0,0 +1,136 @@
+//! The worker threads behind [`Processor::set_threads`](crate::Processor::set_threads).
+//!
+//! The threads are started once and then wait for work. Every block, [`Pool::run`] hands them a
+//! job made of numbered tasks, helps out on the calling thread, and only returns when every
+//! worker is done with the job, so the job can borrow from the caller's stack.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+type Job = dyn Fn(usize) + Sync;
+
+struct State {
+    // bumped for every job, so that a worker takes part in each job once
+    generation: usize,
+    job: Option<(*const Job, usize)>,
+    quit: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    wake: Condvar,
+    next: AtomicUsize,
+    // the workers that haven't finished the current job yet
+    busy: AtomicUsize,
+    panicked: AtomicBool,
+}
+
+// The job pointer is only handed out while `run` keeps the job alive.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn work(&self, job: &(dyn Fn(usize) + Sync + '_), tasks: usize) {
+        loop {
+            let task = self.next.fetch_add(1, Ordering::Relaxed);
+            if task >= tasks {
+                break;
+            }
+            if catch_unwind(AssertUnwindSafe(|| job(task))).is_err() {
+                self.panicked.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+pub(crate) struct Pool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Pool {
+    pub(crate) fn new(workers: usize) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                generation: 0,
+                job: None,
+                quit: false,
+            }),
+            wake: Condvar::new(),
+            next: AtomicUsize::new(0),
+            busy: AtomicUsize::new(0),
+            panicked: AtomicBool::new(false),
+        });
+        let workers = (0..workers)
+            .map(|i| {
+                let shared = shared.clone();
+                std::thread::Builder::new()
+                    .name(format!("glicol-worker-{i}"))
+                    .spawn(move || worker(&shared))
+                    .expect("failed to start a worker thread")
+            })
+            .collect();
+        Self { shared, workers }
+    }
+
+    /// The number of threads, the calling one included.
+    pub(crate) fn threads(&self) -> usize {
+        self.workers.len() + 1
+    }
+
+    /// Call `job` once for every task in `0..tasks`, spread over the workers and this thread.
+    pub(crate) fn run(&self, tasks: usize, job: &(dyn Fn(usize) + Sync + '_)) {
+        let shared = &*self.shared;
+        // SAFETY: the workers only use the job until `busy` drops back to zero below
+        let erased: *const Job = unsafe { std::mem::transmute(job as *const _) };
+        {
+            let mut state = shared.state.lock().unwrap();
+            state.generation += 1;
+            state.job = Some((erased, tasks));
+            shared.next.store(0, Ordering::Relaxed);
+            shared.busy.store(self.workers.len(), Ordering::Relaxed);
+        }
+        shared.wake.notify_all();
+
+        shared.work(job, tasks);
+        while shared.busy.load(Ordering::Acquire) != 0 {
+            std::thread::yield_now();
+        }
+        shared.state.lock().unwrap().job = None;
+        if shared.panicked.swap(false, Ordering::Relaxed) {
+            panic!("a node panicked while processing in parallel");
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().quit = true;
+        self.shared.wake.notify_all();
+        for worker in self.workers.drain(..) {
+            _ = worker.join();
+        }
+    }
+}
+
+fn worker(shared: &Shared) {
+    let mut seen = 0;
+    loop {
+        let (job, tasks) = {
+            let mut state = shared.state.lock().unwrap();
+            while state.generation == seen && !state.quit {
+                state = shared.wake.wait(state).unwrap();
+            }
+            if state.quit {
+                return;
+            }
+            seen = state.generation;
+            state.job.expect("a new generation always comes with a job")
+        };
+        // SAFETY: `run` doesn't return, and so drop the job, before `busy` is back to zero
+        shared.work(unsafe { &*job }, tasks);
+        shared.busy.fetch_sub(1, Ordering::Release);
+    }
+}
@@ -0,0 +1,52 @@
+//! Opt-in timing of every node, for finding out what is using up the audio budget.
+//!
+//! The [`Processor`](crate::Processor) only reads the clock when one has been set with
+//! [`Processor::set_clock`](crate::Processor::set_clock), so there is no cost otherwise.
+
+/// The time in seconds since some fixed point.
+///
+/// `std::time::Instant` panics on `wasm32-unknown-unknown`, so the clock is passed in rather than
+/// built in: [`std_clock`] everywhere else, and something like `Date.now() / 1000` in a browser.
+pub type Clock = fn() -> f64;
+
+/// A [`Clock`] based on `std::time::Instant`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn std_clock() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
+
+/// The time spent processing, summed over `blocks` blocks.
+///
+/// It covers the blocks since the profile was last taken, or since the processing order last
+/// changed, whichever is later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile<Id> {
+    pub blocks: usize,
+    /// Seconds spent on the whole graph.
+    pub total: f64,
+    /// Seconds spent in each node, in processing order.
+    pub nodes: Vec<(Id, f64)>,
+}
+
+impl<Id> Default for Profile<Id> {
+    fn default() -> Self {
+        Self {
+            blocks: 0,
+            total: 0.0,
+            nodes: vec![],
+        }
+    }
+}
+
+impl<Id> Profile<Id> {
+    /// Seconds per block spent on the whole graph, on average.
+    pub fn per_block(&self) -> f64 {
+        match self.blocks {
+            0 => 0.0,
+            blocks => self.total / blocks as f64,
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Sample data for the samplers, reading it between its frames, and the hits of sample patterns.
+
+use std::{
+    f32::consts::PI,
+    sync::{Arc, OnceLock},
+};
+
+/// How a sample is read between its frames, which is whenever it plays at another rate than its
+/// own or is pitched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// A straight line between the two nearest frames. Cheap, but it dulls the sound and aliases
+    /// when the sample plays faster than its own rate.
+    #[default]
+    Linear,
+    /// A curve through the four nearest frames.
+    Cubic,
+    /// A windowed sinc, which also filters out what would alias when the sample plays faster than
+    /// its own rate. The most expensive by far. It reads the kernel from a table that is built
+    /// the first time [`Sample::interpolation`] picks it.
+    Sinc,
+}
+
+// zero crossings on each side of the sinc kernel
+const SINC_DEPTH: usize = 16;
+// the kernel is widened by up to this much to filter a sample that plays faster, beyond that it
+// aliases again, but its cost stays bounded
+const MAX_STRETCH: f32 = 4.0;
+// entries of the kernel table per zero crossing
+const SINC_RESOLUTION: usize = 512;
+
+/// How many times a pattern plays a sample at once, unless it says otherwise.
+pub const DEFAULT_MAX_VOICES: usize = 8;
+
+/// A sample as the samplers play it. The data has the frames of each channel, one channel after
+/// the other, and is shared by the nodes that play it, so it is freed once the last one is gone.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub data: Arc<[f32]>,
+    pub channels: usize,
+    pub sr: usize,
+    pub interpolation: Interpolation,
+    /// Starting this sample in a pattern cuts the samples of the same group that the pattern is
+    /// playing, like a closed hi-hat cuts an open one.
+    pub choke: Option<u8>,
+    /// Starting this sample in a pattern that already plays it this many times cuts the oldest.
+    pub max_voices: usize,
+}
+
+impl Sample {
+    pub fn new(data: impl Into<Arc<[f32]>>, channels: usize, sr: usize) -> Self {
+        Self {
+            data: data.into(),
+            channels,
+            sr,
+            interpolation: Interpolation::default(),
+            choke: None,
+            max_voices: DEFAULT_MAX_VOICES,
+        }
+    }
+
+    pub fn interpolation(self, interpolation: Interpolation) -> Self {
+        if interpolation == Interpolation::Sinc {
+            // so that it isn't built on the audio thread
+            sinc_table();
+        }
+        Self {
+            interpolation,
+            ..self
+        }
+    }
+
+    pub fn choke(self, choke: Option<u8>) -> Self {
+        Self { choke, ..self }
+    }
+
+    pub fn max_voices(self, max_voices: usize) -> Self {
+        Self { max_voices, ..self }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.data.len() / self.channels.max(1)
+    }
+
+    pub fn channel(&self, c: usize) -> &[f32] {
+        let frames = self.frames();
+        &self.data[c * frames..(c + 1) * frames]
+    }
+
+    /// Channel `c` at `pos` frames in, while `step` frames go by per output sample.
+    pub fn read(&self, c: usize, pos: f32, step: f32) -> f32 {
+        let i = pos.floor();
+        self.read_between(c, i as isize, pos - i, step)
+    }
+
+    // channel `c` at `frac` of the way from frame `i` to the next one
+    fn read_between(&self, c: usize, i: isize, frac: f32, step: f32) -> f32 {
+        let data = self.channel(c);
+        let Some(last) = data.len().checked_sub(1) else {
+            return 0.0;
+        };
+        // linear and cubic hold the first and last frames, sinc fades into silence around them
+        let held = |i: isize| data[i.clamp(0, last as isize) as usize];
+        match self.interpolation {
+            Interpolation::Linear => {
+                let (a, b) = (held(i), held(i + 1));
+                a + (b - a) * frac
+            }
+            Interpolation::Cubic => {
+                let (y0, y1, y2, y3) = (held(i - 1), held(i), held(i + 1), held(i + 2));
+                // Catmull-Rom
+                let a = -0.5 * y0 + 1.5 * y1 - 1.5 * y2 + 0.5 * y3;
+                let b = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+                let c = -0.5 * y0 + 0.5 * y2;
+                ((a * frac + b) * frac + c) * frac + y1
+            }
+            Interpolation::Sinc => convolve(data, i, frac, step, table_sinc),
+        }
+    }
+
+    /// Resample to `sr` ahead of time with a windowed sinc, so that it plays at `sr` without being
+    /// interpolated at all. It keeps its interpolation for when it is pitched.
+    pub fn resample(&self, sr: usize) -> Self {
+        if sr == self.sr || self.frames() == 0 {
+            return self.clone();
+        }
+        let step = self.sr as f64 / sr as f64;
+        let frames = (self.frames() as f64 / step).round() as usize;
+        let mut data = Vec::with_capacity(frames * self.channels);
+        for c in 0..self.channels {
+            let channel = self.channel(c);
+            data.extend((0..frames).map(|j| {
+                let pos = j as f64 * step;
+                let i = pos.floor();
+                // computed exactly, as this isn't on the audio thread
+                convolve(
+                    channel,
+                    i as isize,
+                    (pos - i) as f32,
+                    step as f32,
+                    windowed_sinc,
+                )
+            }));
+        }
+        Self {
+            data: data.into(),
+            sr,
+            ..self.clone()
+        }
+    }
+}
+
+// `data` at `frac` of the way from frame `i` to the next one through the windowed sinc `kernel`,
+// widened to filter what would alias at `step` frames per output sample
+fn convolve(data: &[f32], i: isize, frac: f32, step: f32, kernel: fn(f32) -> f32) -> f32 {
+    let Some(last) = data.len().checked_sub(1) else {
+        return 0.0;
+    };
+    let stretch = step.abs().clamp(1.0, MAX_STRETCH);
+    let half = (SINC_DEPTH as f32 * stretch).ceil() as isize;
+    let from = (i - half + 1).max(0);
+    let to = (i + half).min(last as isize);
+    let mut sum = 0.0;
+    for k in from..=to {
+        sum += data[k as usize] * kernel(((i - k) as f32 + frac) / stretch);
+    }
+    sum / stretch
+}
+
+// one side of the kernel, which is symmetric, from 0 to past `SINC_DEPTH` where it is 0
+fn sinc_table() -> &'static [f32] {
+    static TABLE: OnceLock<Box<[f32]>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        (0..=(SINC_DEPTH * SINC_RESOLUTION + 1))
+            .map(|j| windowed_sinc(j as f32 / SINC_RESOLUTION as f32))
+            .collect()
+    })
+}
+
+// the kernel read from the table, between its entries in a straight line
+fn table_sinc(x: f32) -> f32 {
+    let pos = x.abs() * SINC_RESOLUTION as f32;
+    let j = pos as usize;
+    match sinc_table().get(j..j + 2) {
+        Some(&[a, b]) => a + (b - a) * (pos - j as f32),
+        _ => 0.0,
+    }
+}
+
+fn windowed_sinc(x: f32) -> f32 {
+    sinc(x) * blackman(x / SINC_DEPTH as f32)
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn blackman(t: f32) -> f32 {
+    if t.abs() >= 1.0 {
+        0.0
+    } else {
+        0.42 + 0.5 * (PI * t).cos() + 0.08 * (2.0 * PI * t).cos()
+    }
+}
+
+/// One hit in a `psampler` pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleEvent {
+    pub sample: String,
+    /// In cycles from the start of the pattern.
+    pub time: f32,
+    /// How fast it plays, which also changes its pitch. Events that aren't above 0 are skipped.
+    pub rate: f32,
+    pub amp: f32,
+    /// From -1 for left to 1 for right.
+    pub pan: f32,
+}
+
+impl SampleEvent {
+    pub fn new(sample: impl Into<String>, time: f32) -> Self {
+        Self {
+            sample: sample.into(),
+            time,
+            rate: 1.0,
+            amp: 1.0,
+            pan: 0.0,
+        }
+    }
+
+    pub fn rate(self, rate: f32) -> Self {
+        Self { rate, ..self }
+    }
+
+    pub fn amp(self, amp: f32) -> Self {
+        Self { amp, ..self }
+    }
+
+    pub fn pan(self, pan: f32) -> Self {
+        Self { pan, ..self }
+    }
+
+    /// The gains of the left and right channels. The middle leaves both as they are, and either
+    /// side turns the other one down.
+    pub fn gains(&self) -> [f32; 2] {
+        let pan = self.pan.clamp(-1.0, 1.0);
+        [
+            self.amp * (1.0 - pan).min(1.0),
+            self.amp * (1.0 + pan).min(1.0),
+        ]
+    }
+}
@@ -3,22 +3,32 @@ extern crate lazy_static;
 
 use std::sync::{Mutex, MutexGuard};
 
-use glicol::{Engine, EngineError};
+use glicol::{AudioProcessor, Controller, Engine, EngineError, Quantize};
+use glicol_synth::Interpolation;
+use std::fmt::Write as _;
 use wasm_bindgen::prelude::wasm_bindgen;
 
+// Code updates and messages only ever lock the controller, so they can't hold up `process`.
 lazy_static! {
-    static ref ENGINE: Mutex<Engine<128>> = Mutex::new(Engine::<128>::new());
+    static ref ENGINE: (Mutex<Controller<128>>, Mutex<AudioProcessor<128>>) = {
+        let (controller, processor) = Engine::<128>::new().split();
+        (Mutex::new(controller), Mutex::new(processor))
+    };
+}
+
+fn get_engine() -> MutexGuard<'static, Controller<128>> {
+    ENGINE.0.lock().unwrap_or_else(|e| e.into_inner())
 }
 
-fn get_engine() -> MutexGuard<'static, Engine<128>> {
-    ENGINE.lock().unwrap_or_else(|e| e.into_inner())
+fn get_processor() -> MutexGuard<'static, AudioProcessor<128>> {
+    ENGINE.1.lock().unwrap_or_else(|e| e.into_inner())
 }
 
 #[wasm_bindgen]
 pub fn process(size: usize) -> Vec<f32> {
-    let mut engine = get_engine();
+    let mut processor = get_processor();
 
-    let engine_out = engine.next_block(vec![]);
+    let engine_out = processor.next_block(&[]);
 
     let mut out_buf = vec![0.; size];
     let half_size = size / 2;
@@ -30,10 +40,42 @@ pub fn process(size: usize) -> Vec<f32> {
 
 #[wasm_bindgen]
 pub fn add_sample(name: String, sample: Box<[f32]>, channels: usize, sr: usize) {
-    let mut engine = ENGINE.lock().unwrap();
-    let leaked_sample = Box::leak(sample);
-    engine.add_sample(&name, leaked_sample, channels, sr);
-    // engine.update(code);
+    get_engine().add_sample(&name, sample, channels, sr);
+}
+
+#[wasm_bindgen]
+pub fn remove_sample(name: String) -> bool {
+    get_engine().remove_sample(&name)
+}
+
+/// `interpolation` is `"linear"`, `"cubic"` or `"sinc"`. Returns false if there is no such sample
+/// or interpolation.
+#[wasm_bindgen]
+pub fn set_interpolation(name: String, interpolation: String) -> bool {
+    let interpolation = match interpolation.as_str() {
+        "linear" => Interpolation::Linear,
+        "cubic" => Interpolation::Cubic,
+        "sinc" => Interpolation::Sinc,
+        _ => return false,
+    };
+    get_engine().set_interpolation(&name, interpolation)
+}
+
+/// Only the samples that the same `psampler` plays are cut, see `Engine::set_choke_group`.
+/// `undefined` takes the sample out of its group.
+#[wasm_bindgen]
+pub fn set_choke_group(name: String, group: Option<u8>) -> bool {
+    get_engine().set_choke_group(&name, group)
+}
+
+#[wasm_bindgen]
+pub fn set_max_voices(name: String, max_voices: usize) -> bool {
+    get_engine().set_max_voices(&name, max_voices)
+}
+
+#[wasm_bindgen]
+pub fn set_resample_samples(resample: bool) {
+    get_engine().set_resample_samples(resample);
 }
 
 /// # Safety
@@ -50,15 +92,20 @@ pub unsafe fn update(code_buf: &[u8]) -> Vec<u8> {
     res
 }
 
+/// Returns the same kind of buffer as `update`, with error code 6 if a message couldn't be sent.
 #[wasm_bindgen]
-pub fn send_msg(msg: String) {
-    //, result_ptr: *mut u8
-    get_engine().send_msg(&msg);
+pub fn send_msg(msg: String) -> Vec<u8> {
+    let mut res = vec![0; RES_BUFFER_SIZE];
+    if let Err(e) = get_engine().send_msg(&msg) {
+        res[0] = 6;
+        write_str_to_buf(&e.to_string(), &mut res);
+    }
+    res
 }
 
 #[wasm_bindgen]
 pub fn live_coding_mode(io: bool) {
-    get_engine().livecoding = io;
+    get_engine().set_livecoding(io);
 }
 
 #[wasm_bindgen]
@@ -67,6 +114,23 @@ pub fn set_bpm(bpm: f32) {
     // engine.reset();
 }
 
+/// `bars` is the grid for code updates: 0 applies them straight away, 0.25 waits for the next
+/// beat, and 1 or more for the next multiple of that many bars.
+#[wasm_bindgen]
+pub fn set_quantize(bars: f32) {
+    let quantize = match bars {
+        b if b <= 0. => Quantize::Off,
+        b if b < 1. => Quantize::Beat,
+        b => Quantize::Bars(b as usize),
+    };
+    get_engine().set_quantize(quantize);
+}
+
+#[wasm_bindgen]
+pub fn is_update_pending() -> bool {
+    get_engine().is_update_pending()
+}
+
 #[wasm_bindgen]
 pub fn set_track_amp(amp: f32) {
     get_engine().set_track_amp(amp);
@@ -82,6 +146,45 @@ pub fn set_seed(seed: f32) {
     get_engine().set_seed(seed as usize);
 }
 
+#[wasm_bindgen]
+extern "C" {
+    // `performance` isn't there in an AudioWorklet, so only whole milliseconds, which average out
+    // over the blocks of a profile
+    #[wasm_bindgen(js_namespace = Date)]
+    fn now() -> f64;
+}
+
+fn clock() -> f64 {
+    now() / 1000.
+}
+
+#[wasm_bindgen]
+pub fn set_profiling(on: bool) {
+    get_engine().set_profiling(on.then_some(clock as fn() -> f64));
+}
+
+/// The profile since the last call, as JSON: `{"blocks":..,"load":..,"chains":{"o":[..]}}`, with
+/// the share of the audio budget taken by every node of every chain.
+#[wasm_bindgen]
+pub fn take_profile() -> String {
+    let profile = get_processor().take_profile();
+    let profile = get_engine().name_profile(profile);
+    let mut json = format!(
+        "{{\"blocks\":{},\"load\":{},\"chains\":{{",
+        profile.blocks,
+        profile.load()
+    );
+    for (i, (name, nodes)) in profile.chains.iter().enumerate() {
+        let loads: Vec<_> = nodes
+            .iter()
+            .map(|t| (t / profile.budget).to_string())
+            .collect();
+        let sep = if i == 0 { "" } else { "," };
+        _ = write!(json, "{sep}\"{name}\":[{}]", loads.join(","));
+    }
+    json + "}}"
+}
+
 #[wasm_bindgen]
 pub fn reset() {
     get_engine().reset();
@@ -89,23 +192,22 @@ pub fn reset() {
 
 const RES_BUFFER_SIZE: usize = 256;
 
+// result[0] is the error kind, the rest is a utf-8 message starting with the location of the
+// error, so that it still makes it into the buffer when a long message gets cut off
 fn write_err_to_buf(err: EngineError, result: &mut [u8]) {
     result[0] = match err {
         EngineError::ParsingError(_) => 1,
-        EngineError::NonExistSample(_) => 2,
-        EngineError::NonExistReference(_) => 3,
+        EngineError::NonExistSample(..) => 2,
+        EngineError::NonExistReference(..) => 3,
+        EngineError::UnsupportedNode(..) => 4,
+        EngineError::InvalidParameter(..) => 5,
+        EngineError::CyclicReference(..) => 7,
+        EngineError::ChannelMismatch(..) => 8,
     };
 
+    let span = err.span();
     let error = match err {
         EngineError::ParsingError(v) => {
-            let location = match v.location {
-                pest::error::InputLocation::Pos(u) => u,
-                pest::error::InputLocation::Span((s, _)) => s,
-            };
-            let (line, col) = match v.line_col {
-                pest::error::LineColLocation::Pos(u) => u,
-                pest::error::LineColLocation::Span(u, _) => u,
-            };
             let (positives, negatives) = match &v.variant {
                 pest::error::ErrorVariant::ParsingError {
                     positives,
@@ -116,14 +218,31 @@ fn write_err_to_buf(err: EngineError, result: &mut [u8]) {
 
             format!(
                 "pos[{:?}], line[{:?}], col[{:?}], positives{:?}, negatives{:?}",
-                location, line, col, positives, negatives
+                span.start, span.line, span.col, positives, negatives
             )
         }
-        EngineError::NonExistSample(v) => format!("There is no sample named {v}"),
-        EngineError::NonExistReference(v) => format!("There is no reference named {v}"),
+        EngineError::NonExistSample(v, _) => format!("There is no sample named {v}"),
+        EngineError::NonExistReference(v, _) => format!("There is no reference named {v}"),
+        EngineError::UnsupportedNode(v, _) => format!("Unsupported node: {v}"),
+        EngineError::InvalidParameter(v, _) => format!("Invalid parameter: {v}"),
+        EngineError::CyclicReference(v, _) => {
+            format!("The reference {v} leads back to itself, use fb {v} to read it a block late")
+        }
+        EngineError::ChannelMismatch(v, _) => format!("Can't mix {v}"),
+    };
+    let error = match result[0] {
+        1 => error,
+        _ => format!(
+            "pos[{}], end[{}], line[{}], col[{}], {error}",
+            span.start, span.end, span.line, span.col
+        ),
     };
 
-    let s = error.as_bytes();
+    write_str_to_buf(&error, result);
+}
+
+fn write_str_to_buf(s: &str, result: &mut [u8]) {
+    let s = s.as_bytes();
     let max_bytes_len = s.len().min(RES_BUFFER_SIZE - 2);
     result[2..][..max_bytes_len].copy_from_slice(&s[..max_bytes_len]);
 }